@@ -127,3 +127,134 @@ async fn client_mark_compressed_without_header_server_enabled() {
         "protocol error: received message with compressed-flag but no grpc-encoding was specified"
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn zstd_client_enabled_server_enabled() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc =
+        test_server::TestServer::new(Svc::default()).accept_compressed(CompressionEncoding::Zstd);
+
+    let request_bytes_counter = Arc::new(AtomicUsize::new(0));
+
+    fn assert_right_encoding<B>(req: http::Request<B>) -> http::Request<B> {
+        assert_eq!(req.headers().get("grpc-encoding").unwrap(), "zstd");
+        req
+    }
+
+    tokio::spawn({
+        let request_bytes_counter = request_bytes_counter.clone();
+        async move {
+            Server::builder()
+                .layer(
+                    ServiceBuilder::new()
+                        .map_request(assert_right_encoding)
+                        .layer(measure_request_body_size_layer(request_bytes_counter))
+                        .into_inner(),
+                )
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .send_compressed(CompressionEncoding::Zstd);
+
+    client
+        .compress_input_unary(SomeData {
+            data: [0_u8; UNCOMPRESSED_MIN_BODY_SIZE].to_vec(),
+        })
+        .await
+        .unwrap();
+    let bytes_sent = request_bytes_counter.load(SeqCst);
+    assert!(bytes_sent < UNCOMPRESSED_MIN_BODY_SIZE);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn custom_compressor_client_enabled_server_enabled() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc = test_server::TestServer::new(Svc::default())
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::custom(OnlyZeroes).unwrap());
+
+    let request_bytes_counter = Arc::new(AtomicUsize::new(0));
+
+    fn assert_right_encoding<B>(req: http::Request<B>) -> http::Request<B> {
+        assert_eq!(req.headers().get("grpc-encoding").unwrap(), "only-zeroes");
+        req
+    }
+
+    tokio::spawn({
+        let request_bytes_counter = request_bytes_counter.clone();
+        async move {
+            Server::builder()
+                .layer(
+                    ServiceBuilder::new()
+                        .map_request(assert_right_encoding)
+                        .layer(measure_request_body_size_layer(request_bytes_counter))
+                        .into_inner(),
+                )
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .send_compressed(CompressionEncoding::custom(OnlyZeroes).unwrap());
+
+    client
+        .compress_input_unary(SomeData {
+            data: [0_u8; UNCOMPRESSED_MIN_BODY_SIZE].to_vec(),
+        })
+        .await
+        .unwrap();
+    let bytes_sent = request_bytes_counter.load(SeqCst);
+    assert!(bytes_sent < 32);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn request_compression_override() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc = test_server::TestServer::new(Svc::default())
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+
+    let request_bytes_counter = Arc::new(AtomicUsize::new(0));
+
+    tokio::spawn({
+        let request_bytes_counter = request_bytes_counter.clone();
+        async move {
+            Server::builder()
+                .layer(measure_request_body_size_layer(request_bytes_counter))
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .send_compressed(CompressionEncoding::Gzip);
+
+    let mut request = Request::new(SomeData {
+        data: [0_u8; UNCOMPRESSED_MIN_BODY_SIZE].to_vec(),
+    });
+    request.set_compression(None);
+    client.compress_input_unary(request).await.unwrap();
+    let bytes_sent = request_bytes_counter.swap(0, SeqCst);
+    assert!(bytes_sent > UNCOMPRESSED_MIN_BODY_SIZE);
+
+    let mut request = Request::new(SomeData {
+        data: [0_u8; UNCOMPRESSED_MIN_BODY_SIZE].to_vec(),
+    });
+    request.set_compression(Some(CompressionEncoding::Zstd));
+    client.compress_input_unary(request).await.unwrap();
+    let bytes_sent = request_bytes_counter.load(SeqCst);
+    assert!(bytes_sent < UNCOMPRESSED_MIN_BODY_SIZE);
+}
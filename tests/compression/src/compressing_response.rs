@@ -1,5 +1,5 @@
 use super::*;
-use tonic::codec::CompressionEncoding;
+use tonic::codec::{CompressionEncoding, CompressionSettings};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn client_enabled_server_enabled() {
@@ -220,6 +220,7 @@ async fn disabling_compression_on_single_response() {
 
     let svc = test_server::TestServer::new(Svc {
         disable_compressing_on_response: true,
+        ..Default::default()
     })
     .send_compressed(CompressionEncoding::Gzip);
 
@@ -261,6 +262,7 @@ async fn disabling_compression_on_response_but_keeping_compression_on_stream() {
 
     let svc = test_server::TestServer::new(Svc {
         disable_compressing_on_response: true,
+        ..Default::default()
     })
     .send_compressed(CompressionEncoding::Gzip);
 
@@ -317,6 +319,7 @@ async fn disabling_compression_on_response_from_client_stream() {
 
     let svc = test_server::TestServer::new(Svc {
         disable_compressing_on_response: true,
+        ..Default::default()
     })
     .send_compressed(CompressionEncoding::Gzip);
 
@@ -354,3 +357,207 @@ async fn disabling_compression_on_response_from_client_stream() {
     let bytes_sent = response_bytes_counter.load(SeqCst);
     assert!(bytes_sent > UNCOMPRESSED_MIN_BODY_SIZE);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn zstd_client_enabled_server_enabled() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc =
+        test_server::TestServer::new(Svc::default()).send_compressed(CompressionEncoding::Zstd);
+
+    let response_bytes_counter = Arc::new(AtomicUsize::new(0));
+
+    tokio::spawn({
+        let response_bytes_counter = response_bytes_counter.clone();
+        async move {
+            Server::builder()
+                .layer(
+                    ServiceBuilder::new()
+                        .layer(MapResponseBodyLayer::new(move |body| {
+                            util::CountBytesBody {
+                                inner: body,
+                                counter: response_bytes_counter.clone(),
+                            }
+                        }))
+                        .into_inner(),
+                )
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .accept_compressed(CompressionEncoding::Zstd);
+
+    for _ in 0..3 {
+        let res = client.compress_output_unary(()).await.unwrap();
+        assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "zstd");
+        let bytes_sent = response_bytes_counter.load(SeqCst);
+        assert!(bytes_sent < UNCOMPRESSED_MIN_BODY_SIZE);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_picks_encoding_enabled_on_both_sides() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    fn assert_right_accept_encoding<B>(req: http::Request<B>) -> http::Request<B> {
+        assert_eq!(
+            req.headers().get("grpc-accept-encoding").unwrap(),
+            "gzip,zstd,identity"
+        );
+        req
+    }
+
+    let svc =
+        test_server::TestServer::new(Svc::default()).send_compressed(CompressionEncoding::Zstd);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(
+                ServiceBuilder::new()
+                    .map_request(assert_right_accept_encoding)
+                    .into_inner(),
+            )
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+            .await
+            .unwrap();
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+
+    let res = client.compress_output_unary(()).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "zstd");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn messages_below_min_size_are_not_compressed() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc = test_server::TestServer::new(Svc::default())
+        .send_compressed(CompressionEncoding::Gzip)
+        .compression_settings(
+            CompressionSettings::new().min_message_size(UNCOMPRESSED_MIN_BODY_SIZE * 2),
+        );
+
+    let response_bytes_counter = Arc::new(AtomicUsize::new(0));
+
+    tokio::spawn({
+        let response_bytes_counter = response_bytes_counter.clone();
+        async move {
+            Server::builder()
+                .layer(
+                    ServiceBuilder::new()
+                        .layer(MapResponseBodyLayer::new(move |body| {
+                            util::CountBytesBody {
+                                inner: body,
+                                counter: response_bytes_counter.clone(),
+                            }
+                        }))
+                        .into_inner(),
+                )
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .accept_compressed(CompressionEncoding::Gzip);
+
+    let res = client.compress_output_unary(()).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "gzip");
+    let bytes_sent = response_bytes_counter.load(SeqCst);
+    assert!(bytes_sent > UNCOMPRESSED_MIN_BODY_SIZE);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn response_compression_override() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc = test_server::TestServer::new(Svc {
+        response_compression: Some(Some(CompressionEncoding::Zstd)),
+        ..Default::default()
+    })
+    .send_compressed(CompressionEncoding::Gzip);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+            .await
+            .unwrap();
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+
+    let res = client.compress_output_unary(()).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "zstd");
+
+    let res = client.compress_output_server_stream(()).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "zstd");
+    let mut stream: Streaming<SomeData> = res.into_inner();
+    stream
+        .next()
+        .await
+        .expect("stream empty")
+        .expect("item was error");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn response_compression_override_not_accepted_by_client() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc = test_server::TestServer::new(Svc {
+        response_compression: Some(Some(CompressionEncoding::Zstd)),
+        ..Default::default()
+    })
+    .send_compressed(CompressionEncoding::Gzip);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+            .await
+            .unwrap();
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .accept_compressed(CompressionEncoding::Gzip);
+
+    let res = client.compress_output_unary(()).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "gzip");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn response_compression_override_disables_stream_compression() {
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let svc = test_server::TestServer::new(Svc {
+        response_compression: Some(None),
+        ..Default::default()
+    })
+    .send_compressed(CompressionEncoding::Gzip);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+            .await
+            .unwrap();
+    });
+
+    let mut client = test_client::TestClient::new(mock_io_channel(client).await)
+        .accept_compressed(CompressionEncoding::Gzip);
+
+    let res = client.compress_output_server_stream(()).await.unwrap();
+    assert!(res.metadata().get("grpc-encoding").is_none());
+}
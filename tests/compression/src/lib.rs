@@ -30,6 +30,7 @@ tonic::include_proto!("test");
 #[derive(Debug, Default)]
 struct Svc {
     disable_compressing_on_response: bool,
+    response_compression: Option<Option<tonic::codec::CompressionEncoding>>,
 }
 
 const UNCOMPRESSED_MIN_BODY_SIZE: usize = 1024;
@@ -40,6 +41,10 @@ impl Svc {
             res.disable_compression();
         }
 
+        if let Some(encoding) = &self.response_compression {
+            res.set_compression(encoding.clone());
+        }
+
         res
     }
 }
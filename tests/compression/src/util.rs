@@ -99,3 +99,29 @@ pub async fn mock_io_channel(client: tokio::io::DuplexStream) -> Channel {
         .await
         .unwrap()
 }
+
+/// Replaces every message with its length, so compressed requests are tiny.
+pub struct OnlyZeroes;
+
+impl tonic::codec::Compressor for OnlyZeroes {
+    fn name(&self) -> &'static str {
+        "only-zeroes"
+    }
+
+    fn compress(
+        &self,
+        input: &[u8],
+        output: &mut bytes::BytesMut,
+        _level: tonic::codec::CompressionLevel,
+    ) -> std::io::Result<()> {
+        assert!(input.iter().all(|byte| *byte == 0));
+        bytes::BufMut::put_u64(output, input.len() as u64);
+        Ok(())
+    }
+
+    fn decompress(&self, mut input: &[u8], output: &mut bytes::BytesMut) -> std::io::Result<()> {
+        let len = bytes::Buf::get_u64(&mut input) as usize;
+        output.resize(output.len() + len, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tonic::{
+    transport::{
+        channel::{RefreshingToken, StaticToken, Token},
+        Channel, Endpoint, RetryPolicy, Server,
+    },
+    Code, Request, Response, Status,
+};
+use tower::discover::Change;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        match req.metadata().get("authorization") {
+            Some(token) if token == "Bearer secret" => Ok(Response::new(Output {})),
+            Some(_) => Err(Status::permission_denied("wrong token")),
+            None => Err(Status::unauthenticated("no token")),
+        }
+    }
+}
+
+#[tokio::test]
+async fn credentials_are_refused_over_plaintext() {
+    let server = Server::builder()
+        .add_service(test_server::TestServer::new(Svc))
+        .into_in_process();
+    let endpoint = Endpoint::from_static("http://in-process")
+        .call_credentials(StaticToken::bearer("secret").unwrap());
+
+    let mut client = test_client::TestClient::new(server.connect_lazy(&endpoint));
+    let err = client.unary_call(Input {}).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    assert_eq!(err.message(), "call credentials require a TLS connection");
+
+    let endpoint = endpoint.allow_insecure_call_credentials(true);
+    let mut client = test_client::TestClient::new(server.connect_lazy(&endpoint));
+    client.unary_call(Input {}).await.unwrap();
+}
+
+#[tokio::test]
+async fn refreshing_token_is_fetched_once() {
+    let server = Server::builder()
+        .add_service(test_server::TestServer::new(Svc))
+        .into_in_process();
+
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let credentials = RefreshingToken::new({
+        let fetches = fetches.clone();
+        move || {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async {
+                let token = Token::bearer("secret").unwrap();
+                Ok(token.expires_in(Duration::from_secs(3600)))
+            }
+        }
+    });
+    let endpoint = Endpoint::from_static("http://in-process")
+        .call_credentials(credentials)
+        .allow_insecure_call_credentials(true);
+
+    let mut client = test_client::TestClient::new(server.connect_lazy(&endpoint));
+    for _ in 0..3 {
+        client.unary_call(Input {}).await.unwrap();
+    }
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn balanced_channels_never_send_calls_without_credentials() {
+    let (tx, rx) = oneshot::channel::<()>();
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_shutdown("127.0.0.1:1348".parse().unwrap(), async { drop(rx.await) })
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let credentials = StaticToken::bearer("secret").unwrap();
+    let endpoint = Endpoint::from_static("http://127.0.0.1:1348");
+
+    // The channel has no credentials to attach to the calls sent to the endpoint, which is
+    // ignored.
+    let (channel, changes) = Channel::balance_channel(1);
+    let inserted = endpoint.clone().call_credentials(credentials.clone());
+    changes.send(Change::Insert(0, inserted)).await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    let call = tokio::time::timeout(Duration::from_millis(500), client.unary_call(Input {}));
+    assert!(call.await.is_err());
+
+    // Every attempt, including the retried ones, refuses to send the credentials in plaintext.
+    let (channel, changes) = Endpoint::from_static("https://example.com")
+        .call_credentials(credentials.clone())
+        .retry_policy(RetryPolicy::new().retryable_status_codes([Code::Unauthenticated]))
+        .balance_channel(1);
+    changes
+        .send(Change::Insert(0, endpoint.clone()))
+        .await
+        .unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    let err = client.unary_call(Input {}).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    assert_eq!(err.message(), "call credentials require a TLS connection");
+
+    // The credentials of the channel are attached to the calls sent to its endpoints.
+    let (channel, changes) = Endpoint::from_static("http://example.com")
+        .call_credentials(credentials)
+        .allow_insecure_call_credentials(true)
+        .balance_channel(1);
+    changes.send(Change::Insert(0, endpoint)).await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    client.unary_call(Input {}).await.unwrap();
+
+    tx.send(()).unwrap();
+    jh.await.unwrap();
+}
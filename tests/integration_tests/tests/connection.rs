@@ -1,9 +1,11 @@
 use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use integration_tests::pb::{test_stream_client, test_stream_server, InputStream, OutputStream};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::{
-    transport::{Endpoint, Server},
+    transport::{Channel, ConnectivityState, Endpoint, Server, ServiceConfig},
     Code, Request, Response, Status,
 };
 
@@ -63,7 +65,9 @@ async fn connect_lazy_reconnects_after_first_failure() {
     let sender = Arc::new(Mutex::new(Some(tx)));
     let svc = test_server::TestServer::new(Svc(sender));
 
-    let channel = Endpoint::from_static("http://127.0.0.1:1339").connect_lazy();
+    let channel = Endpoint::from_static("http://127.0.0.1:1339")
+        .initial_reconnect_backoff(Duration::from_millis(10))
+        .connect_lazy();
 
     let mut client = TestClient::new(channel);
 
@@ -90,3 +94,231 @@ async fn connect_lazy_reconnects_after_first_failure() {
 
     jh.await.unwrap();
 }
+
+#[tokio::test]
+async fn calls_fail_fast_while_backing_off() {
+    let channel = Endpoint::from_static("http://127.0.0.1:1340")
+        .initial_reconnect_backoff(Duration::from_secs(60))
+        .connect_lazy();
+
+    let mut client = TestClient::new(channel);
+
+    let err = client.unary_call(Request::new(Input {})).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+
+    // No connection attempt is made until the backoff ends.
+    let err = tokio::time::timeout(
+        Duration::from_secs(1),
+        client.unary_call(Request::new(Input {})),
+    )
+    .await
+    .unwrap()
+    .unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+}
+
+#[tokio::test]
+async fn wait_for_ready_calls_wait_for_connection() {
+    let (tx, rx) = oneshot::channel();
+    let sender = Arc::new(Mutex::new(Some(tx)));
+    let svc = test_server::TestServer::new(Svc(sender));
+
+    let config: ServiceConfig = r#"{ "methodConfig": [{ "name": [{}], "waitForReady": true }] }"#
+        .parse()
+        .unwrap();
+    let channel = Endpoint::from_static("http://127.0.0.1:1341")
+        .initial_reconnect_backoff(Duration::from_millis(50))
+        .service_config(config)
+        .connect_lazy();
+
+    let mut client = TestClient::new(channel);
+    let call = tokio::spawn(async move { client.unary_call(Request::new(Input {})).await });
+
+    // Let a few connection attempts fail before starting the server.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_shutdown("127.0.0.1:1341".parse().unwrap(), async { drop(rx.await) })
+            .await
+            .unwrap();
+    });
+
+    call.await.unwrap().unwrap();
+
+    jh.await.unwrap();
+}
+
+#[tokio::test]
+async fn channel_reports_connectivity_state() {
+    let channel = Endpoint::from_static("http://127.0.0.1:1342")
+        .initial_reconnect_backoff(Duration::from_millis(10))
+        .connect_lazy();
+    assert_eq!(channel.state(), ConnectivityState::Idle);
+
+    let mut state = channel.watch_state();
+    let mut client = TestClient::new(channel.clone());
+
+    let err = client.unary_call(Request::new(Input {})).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+    assert_eq!(channel.state(), ConnectivityState::TransientFailure);
+
+    let (tx, rx) = oneshot::channel();
+    let sender = Arc::new(Mutex::new(Some(tx)));
+    let svc = test_server::TestServer::new(Svc(sender));
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_shutdown("127.0.0.1:1342".parse().unwrap(), async {
+                drop(shutdown_rx.await)
+            })
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    client.unary_call(Request::new(Input {})).await.unwrap();
+    rx.await.unwrap();
+    assert_eq!(*state.borrow_and_update(), ConnectivityState::Ready);
+
+    shutdown_tx.send(()).unwrap();
+    jh.await.unwrap();
+}
+
+#[tokio::test]
+async fn balanced_channel_aggregates_connectivity_state() {
+    let endpoints = ["http://127.0.0.1:1343", "http://127.0.0.1:1344"]
+        .iter()
+        .map(|uri| Endpoint::from_static(*uri).initial_reconnect_backoff(Duration::from_secs(60)));
+    let channel = Channel::balance_list(endpoints);
+    assert_eq!(channel.state(), ConnectivityState::Idle);
+
+    let mut client = TestClient::new(channel.clone());
+
+    // Every endpoint fails to connect, so the call fails fast instead of waiting for the backoff.
+    let err = client.unary_call(Request::new(Input {})).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+    assert_eq!(channel.state(), ConnectivityState::TransientFailure);
+}
+
+#[tokio::test]
+async fn wait_for_ready_request_waits_for_connection() {
+    let (tx, rx) = oneshot::channel();
+    let sender = Arc::new(Mutex::new(Some(tx)));
+    let svc = test_server::TestServer::new(Svc(sender));
+
+    let channel = Endpoint::from_static("http://127.0.0.1:1345")
+        .initial_reconnect_backoff(Duration::from_millis(50))
+        .connect_lazy();
+
+    let mut client = TestClient::new(channel);
+    let call = tokio::spawn(async move {
+        let mut request = Request::new(Input {});
+        request.set_wait_for_ready(true);
+        client.unary_call(request).await
+    });
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_shutdown("127.0.0.1:1345".parse().unwrap(), async { drop(rx.await) })
+            .await
+            .unwrap();
+    });
+
+    call.await.unwrap().unwrap();
+
+    jh.await.unwrap();
+}
+
+#[tokio::test]
+async fn wait_for_ready_calls_end_with_their_timeout() {
+    let channel = Endpoint::from_static("http://127.0.0.1:1346")
+        .initial_reconnect_backoff(Duration::from_millis(50))
+        .wait_for_ready(true)
+        .connect_lazy();
+
+    let mut client = TestClient::new(channel);
+
+    let mut request = Request::new(Input {});
+    request.set_timeout(Duration::from_millis(300));
+    let err = client.unary_call(request).await.unwrap_err();
+    assert_eq!(err.code(), Code::DeadlineExceeded);
+
+    // The request flag overrides the endpoint default.
+    let mut request = Request::new(Input {});
+    request.set_wait_for_ready(false);
+    let err = client.unary_call(request).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+}
+
+#[tokio::test]
+async fn idle_timeout_waits_for_running_streams() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_stream_server::TestStream for Svc {
+        type StreamCallStream = ReceiverStream<Result<OutputStream, Status>>;
+
+        async fn stream_call(
+            &self,
+            _: Request<InputStream>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            let (tx, rx) = mpsc::channel(1);
+
+            tokio::spawn(async move {
+                for _ in 0..5 {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    if tx.send(Ok(OutputStream {})).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
+    }
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_stream_server::TestStreamServer::new(Svc))
+            .serve_with_shutdown("127.0.0.1:1347".parse().unwrap(), async { drop(rx.await) })
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let channel = Endpoint::from_static("http://127.0.0.1:1347")
+        .idle_timeout(Duration::from_millis(150))
+        .connect()
+        .await
+        .unwrap();
+    let mut client = test_stream_client::TestStreamClient::new(channel.clone());
+
+    // The stream outlives the idle timeout several times over.
+    let mut stream = client
+        .stream_call(Request::new(InputStream {}))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let mut received = 0;
+    while let Some(message) = stream.next().await {
+        message.unwrap();
+        received += 1;
+        assert_eq!(channel.state(), ConnectivityState::Ready);
+    }
+    assert_eq!(received, 5);
+
+    // Once the stream ended the connection closes after the timeout.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(channel.state(), ConnectivityState::Idle);
+
+    tx.send(()).unwrap();
+    jh.await.unwrap();
+}
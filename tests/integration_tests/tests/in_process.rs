@@ -0,0 +1,96 @@
+use bytes::Bytes;
+use integration_tests::pb::{test_client, test_server, test_stream_client, test_stream_server};
+use integration_tests::pb::{Input, InputStream, Output, OutputStream};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Code, Request, Response, Status};
+
+#[tokio::test]
+async fn unary_call_keeps_metadata_and_status() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+            match req.metadata().get("x-fail") {
+                Some(_) => Err(Status::with_details(
+                    Code::ResourceExhausted,
+                    "Too many requests",
+                    Bytes::from_static(&[1]),
+                )),
+                None => {
+                    let mut res = Response::new(Output {});
+                    let echo = req.metadata().get("x-echo").unwrap().clone();
+                    res.metadata_mut().insert("x-echo", echo);
+                    Ok(res)
+                }
+            }
+        }
+    }
+
+    let server = Server::builder()
+        .add_service(test_server::TestServer::new(Svc))
+        .into_in_process();
+    let mut client = test_client::TestClient::new(server.channel());
+
+    let mut req = Request::new(Input {});
+    req.metadata_mut()
+        .insert("x-echo", "hello".parse().unwrap());
+    let res = client.unary_call(req).await.unwrap();
+    assert_eq!(res.metadata().get("x-echo").unwrap(), "hello");
+
+    let mut req = Request::new(Input {});
+    req.metadata_mut().insert("x-fail", "1".parse().unwrap());
+    let err = client.unary_call(req).await.unwrap_err();
+    assert_eq!(err.code(), Code::ResourceExhausted);
+    assert_eq!(err.message(), "Too many requests");
+    assert_eq!(err.details(), &[1]);
+}
+
+#[tokio::test]
+async fn dropped_stream_is_cancelled() {
+    struct Svc(mpsc::Sender<oneshot::Receiver<()>>);
+
+    #[tonic::async_trait]
+    impl test_stream_server::TestStream for Svc {
+        type StreamCallStream = ReceiverStream<Result<OutputStream, Status>>;
+
+        async fn stream_call(
+            &self,
+            _: Request<InputStream>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            let (tx, rx) = mpsc::channel(1);
+            let (cancelled_tx, cancelled_rx) = oneshot::channel();
+            self.0.send(cancelled_rx).await.unwrap();
+
+            tokio::spawn(async move {
+                while tx.send(Ok(OutputStream {})).await.is_ok() {}
+                cancelled_tx.send(()).unwrap();
+            });
+
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
+    }
+
+    let (calls_tx, mut calls_rx) = mpsc::channel(1);
+    let server = Server::builder()
+        .add_service(test_stream_server::TestStreamServer::new(Svc(calls_tx)))
+        .into_in_process();
+    let mut client = test_stream_client::TestStreamClient::new(server.channel());
+
+    let mut stream = client
+        .stream_call(InputStream {})
+        .await
+        .unwrap()
+        .into_inner();
+    stream.message().await.unwrap().unwrap();
+    stream.message().await.unwrap().unwrap();
+    drop(stream);
+
+    let cancelled = calls_rx.recv().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), cancelled)
+        .await
+        .unwrap()
+        .unwrap();
+}
@@ -1,9 +1,24 @@
-use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
-use std::time::Duration;
-use tokio::sync::oneshot;
+use integration_tests::pb::{
+    test1_client, test1_server, test_client::TestClient, test_server,
+    test_stream_client::TestStreamClient, test_stream_server, Input, Input1, InputStream, Output,
+    Output1, OutputStream,
+};
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot};
 use tonic::{
+    service::{
+        interceptor::ResponseInterceptedService, response_interceptor, MessageInterceptor,
+        ResponseInterceptor,
+    },
     transport::{Endpoint, Server},
-    GrpcMethod, Request, Response, Status,
+    Code, GrpcMethod, Request, Response, Status,
 };
 
 #[tokio::test]
@@ -50,3 +65,181 @@ async fn interceptor_retrieves_grpc_method() {
     tx.send(()).unwrap();
     jh.await.unwrap();
 }
+
+#[tokio::test]
+async fn async_interceptors_modify_requests() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+            assert_eq!(req.metadata().get("authorization").unwrap(), "token");
+            assert_eq!(*req.extensions().get::<&str>().unwrap(), "authorized");
+            Ok(Response::new(Output {}))
+        }
+    }
+
+    async fn server_intercept(mut req: Request<()>) -> Result<Request<()>, Status> {
+        tokio::task::yield_now().await;
+
+        match req.metadata().get("authorization") {
+            Some(_) => {
+                req.extensions_mut().insert("authorized");
+                Ok(req)
+            }
+            None => Err(Status::unauthenticated("missing token")),
+        }
+    }
+    let svc = test_server::TestServer::with_async_interceptor(Svc, server_intercept);
+    let server = Server::builder().add_service(svc).into_in_process();
+
+    let err = TestClient::new(server.channel())
+        .unary_call(Request::new(Input {}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+
+    let mut client =
+        TestClient::with_async_interceptor(server.channel(), |mut req: Request<()>| async {
+            tokio::task::yield_now().await;
+            req.metadata_mut()
+                .insert("authorization", "token".parse().unwrap());
+            Ok(req)
+        });
+    client.unary_call(Request::new(Input {})).await.unwrap();
+}
+
+#[tokio::test]
+async fn response_interceptors_modify_streaming_status() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_stream_server::TestStream for Svc {
+        type StreamCallStream =
+            tokio_stream::Iter<std::vec::IntoIter<Result<OutputStream, Status>>>;
+
+        async fn stream_call(
+            &self,
+            _: Request<InputStream>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            let messages = vec![Ok(OutputStream {}), Err(Status::internal("secret"))];
+            Ok(Response::new(tokio_stream::iter(messages)))
+        }
+    }
+
+    #[derive(Clone)]
+    struct Redact;
+
+    impl ResponseInterceptor for Redact {
+        fn on_status(&mut self, status: &mut Status) {
+            if status.code() == Code::Internal {
+                *status = Status::internal("internal error");
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct Audit(mpsc::UnboundedSender<(Code, String)>);
+
+    impl ResponseInterceptor for Audit {
+        fn on_status(&mut self, status: &mut Status) {
+            let _ = self.0.send((status.code(), status.message().to_string()));
+        }
+    }
+
+    let server = Server::builder()
+        .layer(response_interceptor(Redact))
+        .add_service(test_stream_server::TestStreamServer::new(Svc))
+        .into_in_process();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let channel = ResponseInterceptedService::new(server.channel(), Audit(tx));
+    let mut client = TestStreamClient::new(channel);
+
+    let mut stream = client
+        .stream_call(InputStream {})
+        .await
+        .unwrap()
+        .into_inner();
+    stream.message().await.unwrap().unwrap();
+    let err = stream.message().await.unwrap_err();
+    assert_eq!(err.code(), Code::Internal);
+    assert_eq!(err.message(), "internal error");
+
+    let audited = rx.recv().await.unwrap();
+    assert_eq!(audited, (Code::Internal, "internal error".to_string()));
+}
+
+#[tokio::test]
+async fn message_interceptors_see_every_message() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test1_server::Test1 for Svc {
+        async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+            Ok(Response::new(Output1 {
+                buf: req.into_inner().buf,
+            }))
+        }
+
+        type StreamCallStream = tokio_stream::Iter<std::vec::IntoIter<Result<Output1, Status>>>;
+
+        async fn stream_call(
+            &self,
+            req: Request<Input1>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            let buf = req.into_inner().buf;
+            let messages = (0..3).map(|_| Ok(Output1 { buf: buf.clone() }));
+            Ok(Response::new(tokio_stream::iter(
+                messages.collect::<Vec<_>>(),
+            )))
+        }
+    }
+
+    struct MeasureSent(Arc<AtomicUsize>);
+
+    impl MessageInterceptor for MeasureSent {
+        fn on_outbound(&self, message: &mut dyn Any) -> Result<(), Status> {
+            let message = message.downcast_ref::<Output1>().unwrap();
+            self.0.fetch_add(message.buf.len(), Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    struct RejectEmpty;
+
+    impl MessageInterceptor for RejectEmpty {
+        fn on_inbound(&self, message: &mut dyn Any) -> Result<(), Status> {
+            let message = message.downcast_mut::<Output1>().unwrap();
+            if message.buf.is_empty() {
+                return Err(Status::data_loss("empty message"));
+            }
+            message.buf.reverse();
+            Ok(())
+        }
+    }
+
+    let sent = Arc::new(AtomicUsize::new(0));
+    let svc = test1_server::Test1Server::new(Svc).message_interceptor(MeasureSent(sent.clone()));
+    let server = Server::builder().add_service(svc).into_in_process();
+    let mut client =
+        test1_client::Test1Client::new(server.channel()).message_interceptor(RejectEmpty);
+
+    let mut stream = client
+        .stream_call(Input1 { buf: vec![1, 2] })
+        .await
+        .unwrap()
+        .into_inner();
+    for _ in 0..3 {
+        assert_eq!(stream.message().await.unwrap().unwrap().buf, vec![2, 1]);
+    }
+    assert!(stream.message().await.unwrap().is_none());
+    assert_eq!(sent.load(Ordering::SeqCst), 6);
+
+    let err = client
+        .unary_call(Input1 { buf: Vec::new() })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::DataLoss);
+    assert_eq!(err.message(), "empty message");
+}
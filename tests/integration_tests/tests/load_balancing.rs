@@ -0,0 +1,157 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        channel::{OutlierDetection, PickFirst, RingHash, RoundRobin},
+        Channel, Endpoint, Server,
+    },
+    Request, Response, Status,
+};
+use tower::discover::Change;
+
+struct Svc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(Response::new(Output {}))
+    }
+}
+
+struct FailingSvc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl test_server::Test for FailingSvc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Err(Status::unavailable("failing"))
+    }
+}
+
+#[tokio::test]
+async fn pick_first_sticks_to_first_connected_endpoint() {
+    let (addr1, calls1) = run_service_in_background().await;
+    let (addr2, calls2) = run_service_in_background().await;
+
+    // Nothing listens on the first endpoint, so the channel fails over to the second one.
+    let endpoints = vec![
+        Endpoint::from_static("http://127.0.0.1:1"),
+        endpoint(addr1),
+        endpoint(addr2),
+    ];
+    let channel = Channel::balance_list_with_policy(endpoints.into_iter(), PickFirst::new());
+    let mut client = TestClient::new(channel);
+
+    for _ in 0..10 {
+        client.unary_call(Request::new(Input {})).await.unwrap();
+    }
+
+    assert_eq!(calls1.load(Ordering::SeqCst), 10);
+    assert_eq!(calls2.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn round_robin_spreads_calls_evenly() {
+    let (addr1, calls1) = run_service_in_background().await;
+    let (addr2, calls2) = run_service_in_background().await;
+
+    let endpoints = vec![endpoint(addr1), endpoint(addr2)];
+    let channel = Channel::balance_list_with_policy(endpoints.into_iter(), RoundRobin::new());
+    let mut client = TestClient::new(channel);
+
+    for _ in 0..10 {
+        client.unary_call(Request::new(Input {})).await.unwrap();
+    }
+
+    assert_eq!(calls1.load(Ordering::SeqCst), 5);
+    assert_eq!(calls2.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn ring_hash_keeps_users_on_one_endpoint() {
+    let (addr1, calls1) = run_service_in_background().await;
+    let (addr2, calls2) = run_service_in_background().await;
+
+    let policy = RingHash::new().hash_metadata("x-user-id".parse().unwrap());
+    let (channel, tx) = Channel::balance_channel_with_policy(10, policy);
+    tx.send(Change::Insert(1, endpoint(addr1))).await.unwrap();
+    tx.send(Change::Insert(2, endpoint(addr2))).await.unwrap();
+    let mut client = TestClient::new(channel);
+
+    // Wait for both endpoints to connect, so that no call falls back to another endpoint.
+    for _ in 0..10 {
+        client.unary_call(Request::new(Input {})).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    calls1.store(0, Ordering::SeqCst);
+    calls2.store(0, Ordering::SeqCst);
+
+    for _ in 0..10 {
+        client.unary_call(request_for("user-1")).await.unwrap();
+    }
+
+    let calls = (calls1.load(Ordering::SeqCst), calls2.load(Ordering::SeqCst));
+    assert!(calls == (10, 0) || calls == (0, 10), "{:?}", calls);
+}
+
+#[tokio::test]
+async fn outlier_detection_ejects_failing_endpoint() {
+    let (addr1, calls1) = run_service_in_background().await;
+    let calls2 = Arc::new(AtomicUsize::new(0));
+    let addr2 = serve(test_server::TestServer::new(FailingSvc(calls2.clone()))).await;
+
+    let policy = OutlierDetection::new(RoundRobin::new()).consecutive_failures(Some(3));
+    let endpoints = vec![endpoint(addr1), endpoint(addr2)];
+    let channel = Channel::balance_list_with_policy(endpoints.into_iter(), policy);
+    let mut client = TestClient::new(channel);
+
+    for _ in 0..20 {
+        let _ = client.unary_call(Request::new(Input {})).await;
+    }
+
+    assert_eq!(calls1.load(Ordering::SeqCst), 17);
+    assert_eq!(calls2.load(Ordering::SeqCst), 3);
+}
+
+fn request_for(user: &str) -> Request<Input> {
+    let mut request = Request::new(Input {});
+    request
+        .metadata_mut()
+        .insert("x-user-id", user.parse().unwrap());
+    request
+}
+
+fn endpoint(addr: SocketAddr) -> Endpoint {
+    Endpoint::from_shared(format!("http://{}", addr)).unwrap()
+}
+
+async fn run_service_in_background() -> (SocketAddr, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = serve(test_server::TestServer::new(Svc(calls.clone()))).await;
+
+    (addr, calls)
+}
+
+async fn serve<S: test_server::Test>(svc: test_server::TestServer<S>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
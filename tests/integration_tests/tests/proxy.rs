@@ -0,0 +1,129 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tonic::{
+    transport::{Endpoint, Server, Uri},
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[tokio::test]
+async fn connects_through_proxy() {
+    let addr = run_service_in_background().await;
+    let (proxy, requests) = run_proxy_in_background(true).await;
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .proxy(
+            format!("http://user:secret@{}", proxy)
+                .parse::<Uri>()
+                .unwrap(),
+        )
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+
+    client.unary_call(Request::new(Input {})).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0][0], format!("CONNECT {} HTTP/1.1", addr));
+    // "user:secret" in base64.
+    assert!(requests[0].contains(&"Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=".to_string()));
+}
+
+#[tokio::test]
+async fn fails_when_proxy_refuses_tunnel() {
+    let addr = run_service_in_background().await;
+    let (proxy, _) = run_proxy_in_background(false).await;
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .proxy(format!("http://{}", proxy).parse::<Uri>().unwrap())
+        .connect_lazy();
+    let mut client = TestClient::new(channel);
+
+    let status = client.unary_call(Request::new(Input {})).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+}
+
+/// Run a proxy recording the header lines of the `CONNECT` requests it receives, and tunneling
+/// them when `accept` is true.
+async fn run_proxy_in_background(accept: bool) -> (SocketAddr, Arc<Mutex<Vec<Vec<String>>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let recorded = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+
+            let mut lines = Vec::new();
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                lines.push(line);
+            }
+            let target = lines[0].split(' ').nth(1).unwrap().to_string();
+            recorded.lock().unwrap().push(lines);
+
+            let mut stream = stream.into_inner();
+            if !accept {
+                stream
+                    .write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n")
+                    .await
+                    .unwrap();
+                continue;
+            }
+
+            let mut upstream = TcpStream::connect(target).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            tokio::spawn(async move {
+                let _ = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+            });
+        }
+    });
+
+    (addr, requests)
+}
+
+async fn run_service_in_background() -> SocketAddr {
+    let svc = test_server::TestServer::new(Svc);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
@@ -0,0 +1,76 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+
+struct Svc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[tokio::test]
+async fn balances_over_ip_target() {
+    let (addr1, calls1) = run_service_in_background().await;
+    let (addr2, calls2) = run_service_in_background().await;
+
+    let channel = Channel::from_target(format!("ipv4:{},{}", addr1, addr2))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+
+    for _ in 0..20 {
+        client.unary_call(Request::new(Input {})).await.unwrap();
+    }
+
+    assert!(calls1.load(Ordering::SeqCst) > 0);
+    assert!(calls2.load(Ordering::SeqCst) > 0);
+}
+
+#[tokio::test]
+async fn resolves_dns_target() {
+    let (addr, calls) = run_service_in_background().await;
+
+    let channel = Channel::from_target(format!("dns:///localhost:{}", addr.port()))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+
+    client.unary_call(Request::new(Input {})).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+async fn run_service_in_background() -> (SocketAddr, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = test_server::TestServer::new(Svc(calls.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    (addr, calls)
+}
@@ -0,0 +1,139 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{Endpoint, HedgingPolicy, RetryPolicy, Server},
+    Code, Request, Response, Status,
+};
+use tower::discover::Change;
+
+#[tokio::test]
+async fn retries_unavailable_calls() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = run_service_in_background(2, calls.clone()).await;
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(10)))
+        .connect()
+        .await
+        .unwrap();
+    let mut client = test_client::TestClient::new(channel);
+
+    client.unary_call(Request::new(Input {})).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn returns_last_error_after_max_attempts() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = run_service_in_background(usize::MAX, calls.clone()).await;
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .method_retry_policy(
+            "test.Test/UnaryCall",
+            RetryPolicy::new()
+                .max_attempts(2)
+                .initial_backoff(Duration::from_millis(10)),
+        )
+        .connect()
+        .await
+        .unwrap();
+    let mut client = test_client::TestClient::new(channel);
+
+    let err = client.unary_call(Request::new(Input {})).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_without_policy() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = run_service_in_background(1, calls.clone()).await;
+
+    let mut client = test_client::TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let err = client.unary_call(Request::new(Input {})).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn hedges_past_non_fatal_failures() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = run_service_in_background(1, calls.clone()).await;
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .hedging_policy(
+            HedgingPolicy::new()
+                .hedging_delay(Duration::from_secs(10))
+                .non_fatal_status_codes([Code::Unavailable]),
+        )
+        .connect()
+        .await
+        .unwrap();
+    let mut client = test_client::TestClient::new(channel);
+
+    client.unary_call(Request::new(Input {})).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retries_on_balanced_channel() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let addr = run_service_in_background(2, calls.clone()).await;
+
+    let (channel, tx) = Endpoint::from_static("http://[::]:50051")
+        .retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(10)))
+        .balance_channel(1);
+    let endpoint = Endpoint::from_shared(format!("http://{}", addr)).unwrap();
+    tx.send(Change::Insert(addr, endpoint)).await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+
+    client.unary_call(Request::new(Input {})).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+/// Runs a service that fails the first `failures` calls with `UNAVAILABLE`.
+async fn run_service_in_background(failures: usize, calls: Arc<AtomicUsize>) -> SocketAddr {
+    struct Svc {
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, _req: Request<Input>) -> Result<Response<Output>, Status> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(Status::unavailable("try again"));
+            }
+            Ok(Response::new(Output {}))
+        }
+    }
+
+    let svc = test_server::TestServer::new(Svc { failures, calls });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
@@ -0,0 +1,107 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::{net::SocketAddr, pin::Pin, time::Duration};
+use tokio::net::TcpListener;
+use tokio_stream::Stream;
+use tonic::{
+    transport::{Channel, Endpoint, Server, ServiceConfig},
+    Code, Request, Response, Status,
+};
+
+const SERVICE_CONFIG: &str = r#"{
+    "methodConfig": [{
+        "name": [{ "service": "test1.Test1", "method": "UnaryCall" }],
+        "timeout": "0.5s",
+        "maxRequestMessageBytes": 1024,
+        "maxResponseMessageBytes": 1024
+    }]
+}"#;
+
+#[tokio::test]
+async fn applies_message_size_limits() {
+    let addr = run_service_in_background().await;
+    let mut client = test1_client::Test1Client::new(connect(addr).await);
+
+    client
+        .unary_call(Request::new(Input1 { buf: vec![0; 16] }))
+        .await
+        .unwrap();
+
+    let err = client
+        .unary_call(Request::new(Input1 { buf: vec![0; 2048] }))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::OutOfRange);
+
+    let err = client
+        .unary_call(Request::new(Input1 { buf: vec![1; 1] }))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::OutOfRange);
+}
+
+#[tokio::test]
+async fn applies_timeout() {
+    let addr = run_service_in_background().await;
+    let mut client = test1_client::Test1Client::new(connect(addr).await);
+
+    let err = client
+        .unary_call(Request::new(Input1 { buf: vec![2; 1] }))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::DeadlineExceeded);
+}
+
+async fn connect(addr: SocketAddr) -> Channel {
+    Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .service_config(SERVICE_CONFIG.parse::<ServiceConfig>().unwrap())
+        .connect()
+        .await
+        .unwrap()
+}
+
+/// Runs a service whose reply depends on the first byte of the request: `1` asks for a large
+/// response and `2` for a slow one.
+async fn run_service_in_background() -> SocketAddr {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test1_server::Test1 for Svc {
+        async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+            let buf = match req.into_inner().buf.first() {
+                Some(1) => vec![0; 2048],
+                Some(2) => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            };
+            Ok(Response::new(Output1 { buf }))
+        }
+
+        type StreamCallStream =
+            Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+        async fn stream_call(
+            &self,
+            _req: Request<Input1>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            unimplemented!()
+        }
+    }
+
+    let svc = test1_server::Test1Server::new(Svc);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
@@ -0,0 +1,88 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::{net::SocketAddr, time::Duration};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{Channel, ConnectivityState, Endpoint, Server},
+    Code, Request, Response, Status,
+};
+
+#[tokio::test]
+async fn shutdown_drains_running_calls() {
+    let addr = run_service_in_background(Duration::from_millis(500)).await;
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let mut client = test_client::TestClient::new(channel.clone());
+    let running = tokio::spawn(async move { client.unary_call(Input {}).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let shutdown = tokio::spawn({
+        let channel = channel.clone();
+        async move { channel.shutdown().await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let err = test_client::TestClient::new(channel.clone())
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unavailable);
+
+    running.await.unwrap().unwrap();
+    shutdown.await.unwrap();
+    assert_eq!(channel.state(), ConnectivityState::Shutdown);
+}
+
+#[tokio::test]
+async fn shutdown_with_timeout_cuts_off_calls() {
+    let addr = run_service_in_background(Duration::from_secs(30)).await;
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let mut client = test_client::TestClient::new(channel.clone());
+    let running = tokio::spawn(async move { client.unary_call(Input {}).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        channel.shutdown_with_timeout(Duration::from_millis(100)),
+    )
+    .await
+    .unwrap();
+    running.await.unwrap().unwrap_err();
+}
+
+async fn run_service_in_background(latency: Duration) -> SocketAddr {
+    struct Svc {
+        latency: Duration,
+    }
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, _req: Request<Input>) -> Result<Response<Output>, Status> {
+            tokio::time::sleep(self.latency).await;
+            Ok(Response::new(Output {}))
+        }
+    }
+
+    let svc = test_server::TestServer::new(Svc { latency });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
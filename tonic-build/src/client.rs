@@ -94,6 +94,20 @@ pub(crate) fn generate_internal<T: Service>(
                     #service_ident::new(InterceptedService::new(inner, interceptor))
                 }
 
+                pub fn with_async_interceptor<F>(inner: T, interceptor: F) -> #service_ident<InterceptedService<T, tonic::service::interceptor::Async<F>>>
+                where
+                    F: tonic::service::AsyncInterceptor,
+                    T::ResponseBody: Default,
+                    T: tonic::codegen::Service<
+                        http::Request<tonic::body::BoxBody>,
+                        Response = http::Response<<T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody>
+                    >,
+                    T: Clone,
+                    <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error: Into<StdError> + Send + Sync,
+                {
+                    #service_ident::new(InterceptedService::new_async(inner, interceptor))
+                }
+
                 /// Compress requests with the given encoding.
                 ///
                 /// This requires the server to support it otherwise it might respond with an
@@ -111,6 +125,13 @@ pub(crate) fn generate_internal<T: Service>(
                     self
                 }
 
+                /// Configure the compression level and minimum message size used for requests.
+                #[must_use]
+                pub fn compression_settings(mut self, settings: CompressionSettings) -> Self {
+                    self.inner = self.inner.compression_settings(settings);
+                    self
+                }
+
                 /// Limits the maximum size of a decoded message.
                 ///
                 /// Default: `4MB`
@@ -129,6 +150,14 @@ pub(crate) fn generate_internal<T: Service>(
                     self
                 }
 
+                /// Intercept every message sent before it is encoded, and every message received
+                /// once it is decoded.
+                #[must_use]
+                pub fn message_interceptor(mut self, interceptor: impl tonic::service::MessageInterceptor) -> Self {
+                    self.inner = self.inner.message_interceptor(interceptor);
+                    self
+                }
+
                 #methods
             }
         }
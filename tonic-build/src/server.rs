@@ -69,6 +69,13 @@ pub(crate) fn generate_internal<T: Service>(
             self.send_compression_encodings.enable(encoding);
             self
         }
+
+        /// Configure the compression level and minimum message size used for responses.
+        #[must_use]
+        pub fn compression_settings(mut self, settings: CompressionSettings) -> Self {
+            self.compression_settings = settings;
+            self
+        }
     };
 
     let configure_max_message_size_methods = quote! {
@@ -91,6 +98,16 @@ pub(crate) fn generate_internal<T: Service>(
         }
     };
 
+    let configure_message_interceptor_method = quote! {
+        /// Intercept every message received once it is decoded, and every message sent before it
+        /// is encoded.
+        #[must_use]
+        pub fn message_interceptor(mut self, interceptor: impl tonic::service::MessageInterceptor) -> Self {
+            self.message_interceptor = Some(Arc::new(interceptor));
+            self
+        }
+    };
+
     quote! {
         /// Generated server implementations.
         #(#mod_attributes)*
@@ -113,8 +130,10 @@ pub(crate) fn generate_internal<T: Service>(
                 inner: _Inner<T>,
                 accept_compression_encodings: EnabledCompressionEncodings,
                 send_compression_encodings: EnabledCompressionEncodings,
+                compression_settings: CompressionSettings,
                 max_decoding_message_size: Option<usize>,
                 max_encoding_message_size: Option<usize>,
+                message_interceptor: Option<Arc<dyn tonic::service::MessageInterceptor>>,
             }
 
             struct _Inner<T>(Arc<T>);
@@ -130,8 +149,10 @@ pub(crate) fn generate_internal<T: Service>(
                         inner,
                         accept_compression_encodings: Default::default(),
                         send_compression_encodings: Default::default(),
+                        compression_settings: Default::default(),
                         max_decoding_message_size: None,
                         max_encoding_message_size: None,
+                        message_interceptor: None,
                     }
                 }
 
@@ -142,9 +163,18 @@ pub(crate) fn generate_internal<T: Service>(
                     InterceptedService::new(Self::new(inner), interceptor)
                 }
 
+                pub fn with_async_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, tonic::service::interceptor::Async<F>>
+                where
+                    F: tonic::service::AsyncInterceptor,
+                {
+                    InterceptedService::new_async(Self::new(inner), interceptor)
+                }
+
                 #configure_compression_methods
 
                 #configure_max_message_size_methods
+
+                #configure_message_interceptor_method
             }
 
             impl<T, B> tonic::codegen::Service<http::Request<B>> for #server_service<T>
@@ -184,10 +214,12 @@ pub(crate) fn generate_internal<T: Service>(
                     let inner = self.inner.clone();
                     Self {
                         inner,
-                        accept_compression_encodings: self.accept_compression_encodings,
-                        send_compression_encodings: self.send_compression_encodings,
+                        accept_compression_encodings: self.accept_compression_encodings.clone(),
+                        send_compression_encodings: self.send_compression_encodings.clone(),
+                        compression_settings: self.compression_settings.clone(),
                         max_decoding_message_size: self.max_decoding_message_size,
                         max_encoding_message_size: self.max_encoding_message_size,
+                        message_interceptor: self.message_interceptor.clone(),
                     }
                 }
             }
@@ -484,10 +516,12 @@ fn generate_unary<T: Method>(
             }
         }
 
-        let accept_compression_encodings = self.accept_compression_encodings;
-        let send_compression_encodings = self.send_compression_encodings;
+        let accept_compression_encodings = self.accept_compression_encodings.clone();
+        let send_compression_encodings = self.send_compression_encodings.clone();
+        let compression_settings = self.compression_settings.clone();
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
+        let message_interceptor = self.message_interceptor.clone();
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
@@ -496,7 +530,9 @@ fn generate_unary<T: Method>(
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
-                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
+                .apply_compression_settings(compression_settings)
+                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size)
+                .apply_message_interceptor(message_interceptor);
 
             let res = grpc.unary(method, req).await;
             Ok(res)
@@ -552,10 +588,12 @@ fn generate_server_streaming<T: Method>(
             }
         }
 
-        let accept_compression_encodings = self.accept_compression_encodings;
-        let send_compression_encodings = self.send_compression_encodings;
+        let accept_compression_encodings = self.accept_compression_encodings.clone();
+        let send_compression_encodings = self.send_compression_encodings.clone();
+        let compression_settings = self.compression_settings.clone();
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
+        let message_interceptor = self.message_interceptor.clone();
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
@@ -564,7 +602,9 @@ fn generate_server_streaming<T: Method>(
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
-                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
+                .apply_compression_settings(compression_settings)
+                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size)
+                .apply_message_interceptor(message_interceptor);
 
             let res = grpc.server_streaming(method, req).await;
             Ok(res)
@@ -611,10 +651,12 @@ fn generate_client_streaming<T: Method>(
             }
         }
 
-        let accept_compression_encodings = self.accept_compression_encodings;
-        let send_compression_encodings = self.send_compression_encodings;
+        let accept_compression_encodings = self.accept_compression_encodings.clone();
+        let send_compression_encodings = self.send_compression_encodings.clone();
+        let compression_settings = self.compression_settings.clone();
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
+        let message_interceptor = self.message_interceptor.clone();
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
@@ -623,7 +665,9 @@ fn generate_client_streaming<T: Method>(
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
-                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
+                .apply_compression_settings(compression_settings)
+                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size)
+                .apply_message_interceptor(message_interceptor);
 
             let res = grpc.client_streaming(method, req).await;
             Ok(res)
@@ -680,10 +724,12 @@ fn generate_streaming<T: Method>(
             }
         }
 
-        let accept_compression_encodings = self.accept_compression_encodings;
-        let send_compression_encodings = self.send_compression_encodings;
+        let accept_compression_encodings = self.accept_compression_encodings.clone();
+        let send_compression_encodings = self.send_compression_encodings.clone();
+        let compression_settings = self.compression_settings.clone();
         let max_decoding_message_size = self.max_decoding_message_size;
         let max_encoding_message_size = self.max_encoding_message_size;
+        let message_interceptor = self.message_interceptor.clone();
         let inner = self.inner.clone();
         let fut = async move {
             let inner = inner.0;
@@ -692,7 +738,9 @@ fn generate_streaming<T: Method>(
 
             let mut grpc = tonic::server::Grpc::new(codec)
                 .apply_compression_config(accept_compression_encodings, send_compression_encodings)
-                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
+                .apply_compression_settings(compression_settings)
+                .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size)
+                .apply_message_interceptor(message_interceptor);
 
             let res = grpc.streaming(method, req).await;
             Ok(res)
@@ -0,0 +1,159 @@
+//! Contains all healthcheck based client utilities.
+
+use crate::pb::health_check_response::ServingStatus;
+use crate::pb::health_client::HealthClient;
+use crate::pb::HealthCheckRequest;
+use std::time::Duration;
+use tonic::transport::channel::{HealthCheck, HealthStream};
+use tonic::transport::Channel;
+use tonic::Code;
+
+/// The default delay before watching the health of a subchannel again once its stream failed.
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A client-side health check watching the `grpc.health.v1.Health` service of the endpoints of a
+/// balanced channel.
+///
+/// Each subchannel of the channel watches the status of `service` on its backend, and only takes
+/// calls while the backend reports it `SERVING`. A subchannel whose watch fails isn't serving
+/// until the watch, retried after the [`retry_interval`](HealthWatch::retry_interval), reports it
+/// serving again. Backends that don't implement the `Watch` method are always serving.
+///
+/// ```
+/// use tonic::transport::Endpoint;
+/// use tonic_health::client::HealthWatch;
+///
+/// let endpoint = Endpoint::from_static("http://[::1]:50051")
+///     .health_check(HealthWatch::new("helloworld.Greeter"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HealthWatch {
+    service: String,
+    retry_interval: Duration,
+}
+
+impl HealthWatch {
+    /// Watch the status of `service`, or of the whole server when `service` is empty.
+    pub fn new(service: impl Into<String>) -> Self {
+        HealthWatch {
+            service: service.into(),
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+        }
+    }
+
+    /// Set the delay before watching the health of a subchannel again once its stream failed.
+    /// Default is 1 second.
+    pub fn retry_interval(self, interval: Duration) -> Self {
+        HealthWatch {
+            retry_interval: interval,
+            ..self
+        }
+    }
+}
+
+impl HealthCheck for HealthWatch {
+    fn watch(&self, channel: Channel) -> HealthStream {
+        let service = self.service.clone();
+        let retry_interval = self.retry_interval;
+
+        let output = async_stream::stream! {
+            let mut client = HealthClient::new(channel);
+            loop {
+                let request = HealthCheckRequest {
+                    service: service.clone(),
+                };
+                let error = match client.watch(request).await {
+                    Ok(response) => {
+                        let mut statuses = response.into_inner();
+                        loop {
+                            match statuses.message().await {
+                                Ok(Some(response)) => {
+                                    yield response.status == ServingStatus::Serving as i32;
+                                }
+                                Ok(None) => break None,
+                                Err(status) => break Some(status),
+                            }
+                        }
+                    }
+                    Err(status) => Some(status),
+                };
+
+                if matches!(error, Some(ref status) if status.code() == Code::Unimplemented) {
+                    yield true;
+                    return;
+                }
+
+                yield false;
+                tokio::time::sleep(retry_interval).await;
+            }
+        };
+
+        Box::pin(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{health_reporter, HealthReporter};
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Endpoint, Server};
+
+    const SERVICE: &str = "test.Service";
+
+    /// Serve a health service reporting `status` for `SERVICE`, and the service `name` as
+    /// serving to tell which server answered a check.
+    async fn serve(name: &str, status: crate::ServingStatus) -> (HealthReporter, Endpoint) {
+        let (mut reporter, service) = health_reporter();
+        reporter
+            .set_service_status(name, crate::ServingStatus::Serving)
+            .await;
+        reporter.set_service_status(SERVICE, status).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let endpoint = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .health_check(HealthWatch::new(SERVICE));
+        (reporter, endpoint)
+    }
+
+    /// Check the service of server `a` on `channel`, which only server `a` knows.
+    async fn answered_by_a(channel: &Channel) -> bool {
+        let request = HealthCheckRequest {
+            service: "a".to_string(),
+        };
+        HealthClient::new(channel.clone())
+            .check(request)
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn sends_calls_to_serving_endpoints() {
+        let (mut a, endpoint_a) = serve("a", crate::ServingStatus::Serving).await;
+        let (mut b, endpoint_b) = serve("b", crate::ServingStatus::NotServing).await;
+        let channel = Channel::balance_list(vec![endpoint_a, endpoint_b].into_iter());
+
+        for _ in 0..10 {
+            assert!(answered_by_a(&channel).await);
+        }
+
+        a.set_service_status(SERVICE, crate::ServingStatus::NotServing)
+            .await;
+        b.set_service_status(SERVICE, crate::ServingStatus::Serving)
+            .await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        for _ in 0..10 {
+            assert!(!answered_by_a(&channel).await);
+        }
+    }
+}
@@ -49,6 +49,9 @@ pub mod pb {
     pub use crate::generated::{grpc_health_v1::*, FILE_DESCRIPTOR_SET};
 }
 
+#[cfg(feature = "transport")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+pub mod client;
 pub mod server;
 
 /// An enumeration of values representing gRPC service health.
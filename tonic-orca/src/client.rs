@@ -0,0 +1,372 @@
+//! Contains the load balancing policy consuming load reports.
+
+use crate::{pb::OrcaLoadReport, TRAILER};
+use http::{HeaderMap, Request, Uri};
+use prost::Message;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tonic::{
+    body::BoxBody,
+    metadata::MetadataMap,
+    transport::channel::{CallTracker, LoadBalancingPolicy, Subchannel},
+};
+
+const DEFAULT_BLACKOUT_PERIOD: Duration = Duration::from_secs(10);
+const DEFAULT_WEIGHT_EXPIRATION_PERIOD: Duration = Duration::from_secs(180);
+const DEFAULT_ERROR_UTILIZATION_PENALTY: f64 = 1.0;
+
+/// The gRPC `weighted_round_robin` policy: sends calls to the ready endpoints in turn, each in
+/// proportion to its weight.
+///
+/// The weight of an endpoint is derived from the load reports in the trailers of its calls, as
+/// `qps / (utilization + eps / qps * error_utilization_penalty)`, where the utilization is the
+/// application utilization when reported, else the CPU utilization. Endpoints without a recent
+/// report get the mean weight of the others.
+///
+/// ```
+/// # use tonic::transport::Channel;
+/// # use tonic_orca::client::WeightedRoundRobin;
+/// # async fn f() {
+/// let policy = WeightedRoundRobin::new();
+/// let (channel, tx) = Channel::balance_channel_with_policy::<String, _>(10, policy);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WeightedRoundRobin {
+    config: Config,
+    weights: Arc<Mutex<HashMap<Uri, EndpointWeight>>>,
+    /// The smooth weighted round robin counter of each endpoint.
+    current: HashMap<Uri, f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    blackout_period: Duration,
+    weight_expiration_period: Duration,
+    error_utilization_penalty: f64,
+}
+
+#[derive(Debug)]
+struct EndpointWeight {
+    weight: f64,
+    /// When the endpoint started reporting its load, since its weight last expired.
+    non_empty_since: Instant,
+    last_updated: Instant,
+}
+
+impl WeightedRoundRobin {
+    /// Create a `weighted_round_robin` policy.
+    pub fn new() -> Self {
+        WeightedRoundRobin {
+            config: Config {
+                blackout_period: DEFAULT_BLACKOUT_PERIOD,
+                weight_expiration_period: DEFAULT_WEIGHT_EXPIRATION_PERIOD,
+                error_utilization_penalty: DEFAULT_ERROR_UTILIZATION_PENALTY,
+            },
+            weights: Arc::default(),
+            current: HashMap::new(),
+        }
+    }
+
+    /// Set how long an endpoint must have reported its load before its weight is used, so that
+    /// the first reports of a new endpoint don't skew its weight. Default is 10 seconds.
+    pub fn blackout_period(mut self, period: Duration) -> Self {
+        self.config.blackout_period = period;
+        self
+    }
+
+    /// Set how long the weight of an endpoint is used after its last report. Default is 3
+    /// minutes.
+    pub fn weight_expiration_period(mut self, period: Duration) -> Self {
+        self.config.weight_expiration_period = period;
+        self
+    }
+
+    /// Set how much the errors per second reported by an endpoint lower its weight. Default is
+    /// 1.0.
+    pub fn error_utilization_penalty(mut self, penalty: f64) -> Self {
+        self.config.error_utilization_penalty = penalty;
+        self
+    }
+
+    /// The weights of `subchannels`, `None` for those without a usable weight.
+    fn weights(&self, subchannels: &[Subchannel<'_>]) -> Vec<Option<f64>> {
+        let now = Instant::now();
+        let weights = self.weights.lock().unwrap();
+
+        subchannels
+            .iter()
+            .map(|subchannel| {
+                let weight = weights.get(subchannel.uri())?;
+                let expired =
+                    now.duration_since(weight.last_updated) >= self.config.weight_expiration_period;
+                let blackout =
+                    now.duration_since(weight.non_empty_since) < self.config.blackout_period;
+                if expired || blackout {
+                    None
+                } else {
+                    Some(weight.weight)
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for WeightedRoundRobin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for WeightedRoundRobin {
+    /// Create a policy with the same configuration, but none of the weights of this one.
+    fn clone(&self) -> Self {
+        WeightedRoundRobin {
+            config: self.config,
+            weights: Arc::default(),
+            current: HashMap::new(),
+        }
+    }
+}
+
+impl LoadBalancingPolicy for WeightedRoundRobin {
+    fn pick(&mut self, _: &Request<BoxBody>, subchannels: &[Subchannel<'_>]) -> Option<usize> {
+        let weights = self.weights(subchannels);
+        let known: Vec<f64> = weights.iter().flatten().copied().collect();
+        let mean = if known.is_empty() {
+            1.0
+        } else {
+            known.iter().sum::<f64>() / known.len() as f64
+        };
+
+        if self.current.len() != subchannels.len() {
+            self.current
+                .retain(|uri, _| subchannels.iter().any(|s| s.uri() == uri));
+        }
+
+        // Smooth weighted round robin: every ready endpoint earns its weight, the one with the
+        // most credit is picked and pays for all of them.
+        let mut total = 0.0;
+        let mut picked: Option<(usize, f64)> = None;
+        for (index, subchannel) in subchannels.iter().enumerate() {
+            if !subchannel.is_ready() {
+                continue;
+            }
+
+            let weight = weights[index].unwrap_or(mean);
+            let current = self.current.entry(subchannel.uri().clone()).or_default();
+            *current += weight;
+            total += weight;
+
+            if !matches!(picked, Some((_, best)) if best >= *current) {
+                picked = Some((index, *current));
+            }
+        }
+
+        let (index, _) = picked?;
+        *self.current.get_mut(subchannels[index].uri()).unwrap() -= total;
+        Some(index)
+    }
+
+    fn track_call(&mut self, subchannel: &Subchannel<'_>) -> Option<Box<dyn CallTracker>> {
+        Some(Box::new(LoadReportTracker {
+            uri: subchannel.uri().clone(),
+            weights: self.weights.clone(),
+            config: self.config,
+        }))
+    }
+}
+
+/// Updates the weight of an endpoint with the load report in the trailers of a call.
+struct LoadReportTracker {
+    uri: Uri,
+    weights: Arc<Mutex<HashMap<Uri, EndpointWeight>>>,
+    config: Config,
+}
+
+impl CallTracker for LoadReportTracker {
+    fn finish(self: Box<Self>, trailers: Option<&HeaderMap>) {
+        let weight = match trailers
+            .and_then(decode_report)
+            .and_then(|report| weight(&report, self.config.error_utilization_penalty))
+        {
+            Some(weight) => weight,
+            None => return,
+        };
+
+        let now = Instant::now();
+        let mut weights = self.weights.lock().unwrap();
+        let entry = weights.entry(self.uri).or_insert(EndpointWeight {
+            weight,
+            non_empty_since: now,
+            last_updated: now,
+        });
+
+        // An endpoint reporting again after its weight expired goes through the blackout again.
+        if now.duration_since(entry.last_updated) >= self.config.weight_expiration_period {
+            entry.non_empty_since = now;
+        }
+        entry.weight = weight;
+        entry.last_updated = now;
+    }
+}
+
+fn decode_report(trailers: &HeaderMap) -> Option<OrcaLoadReport> {
+    let metadata = MetadataMap::from_headers(trailers.clone());
+    let bytes = metadata.get_bin(TRAILER)?.to_bytes().ok()?;
+    OrcaLoadReport::decode(bytes).ok()
+}
+
+/// The weight of an endpoint reporting `report`, `None` when the report is unusable.
+fn weight(report: &OrcaLoadReport, error_utilization_penalty: f64) -> Option<f64> {
+    let utilization = if report.application_utilization > 0.0 {
+        report.application_utilization
+    } else {
+        report.cpu_utilization
+    };
+    let qps = report.rps_fractional;
+
+    if qps <= 0.0 || utilization <= 0.0 {
+        return None;
+    }
+
+    Some(qps / (utilization + report.eps / qps * error_utilization_penalty))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pb::{
+            open_rca_service_client::OpenRcaServiceClient,
+            open_rca_service_server::{OpenRcaService, OpenRcaServiceServer},
+            OrcaLoadReportRequest,
+        },
+        server::CallMetricsLayer,
+        MetricsRecorder,
+    };
+    use std::{
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::{wrappers::TcpListenerStream, Stream, StreamExt};
+    use tonic::{
+        transport::{Channel, Endpoint, Server},
+        Status,
+    };
+
+    fn report(cpu: f64, application: f64, qps: f64, eps: f64) -> OrcaLoadReport {
+        OrcaLoadReport {
+            cpu_utilization: cpu,
+            application_utilization: application,
+            rps_fractional: qps,
+            eps,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weighs_qps_by_utilization() {
+        assert_eq!(weight(&report(0.5, 0.0, 100.0, 0.0), 1.0), Some(200.0));
+        assert_eq!(weight(&report(0.5, 0.25, 100.0, 0.0), 1.0), Some(400.0));
+        assert_eq!(weight(&report(0.5, 0.0, 100.0, 50.0), 1.0), Some(100.0));
+        assert_eq!(weight(&report(0.5, 0.0, 100.0, 50.0), 0.0), Some(200.0));
+    }
+
+    #[test]
+    fn ignores_empty_reports() {
+        assert_eq!(weight(&report(0.0, 0.0, 100.0, 0.0), 1.0), None);
+        assert_eq!(weight(&report(0.5, 0.0, 0.0, 0.0), 1.0), None);
+    }
+
+    /// A backend reporting the same CPU utilization and QPS for every call.
+    struct Backend {
+        cpu_utilization: f64,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[tonic::async_trait]
+    impl OpenRcaService for Backend {
+        type StreamCoreMetricsStream =
+            Pin<Box<dyn Stream<Item = Result<OrcaLoadReport, Status>> + Send + 'static>>;
+
+        async fn stream_core_metrics(
+            &self,
+            request: tonic::Request<OrcaLoadReportRequest>,
+        ) -> Result<tonic::Response<Self::StreamCoreMetricsStream>, Status> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let recorder = request.extensions().get::<MetricsRecorder>().unwrap();
+            recorder.set_cpu_utilization(self.cpu_utilization);
+            recorder.set_qps(100.0);
+
+            Ok(tonic::Response::new(Box::pin(tokio_stream::empty())))
+        }
+    }
+
+    async fn run_backend(cpu_utilization: f64) -> (Endpoint, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let backend = Backend {
+            cpu_utilization,
+            calls: calls.clone(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            Server::builder()
+                .layer(CallMetricsLayer::new())
+                .add_service(OpenRcaServiceServer::new(backend))
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+                .unwrap();
+        });
+
+        let endpoint = Endpoint::from_shared(format!("http://{}", addr)).unwrap();
+        (endpoint, calls)
+    }
+
+    #[tokio::test]
+    async fn balances_by_reported_load() {
+        let (idle, idle_calls) = run_backend(0.2).await;
+        let (busy, busy_calls) = run_backend(0.8).await;
+
+        let policy = WeightedRoundRobin::new().blackout_period(Duration::ZERO);
+        let channel = Channel::balance_list_with_policy(vec![idle, busy].into_iter(), policy);
+        let client = OpenRcaServiceClient::new(channel);
+
+        let call = || {
+            let request = OrcaLoadReportRequest::default();
+            let mut client = client.clone();
+            async move {
+                let mut stream = client
+                    .stream_core_metrics(request)
+                    .await
+                    .unwrap()
+                    .into_inner();
+                while stream.next().await.is_some() {}
+            }
+        };
+
+        // Get a load report from both backends.
+        for _ in 0..10 {
+            call().await;
+        }
+        idle_calls.store(0, Ordering::SeqCst);
+        busy_calls.store(0, Ordering::SeqCst);
+
+        for _ in 0..100 {
+            call().await;
+        }
+
+        // The idle backend reports 4 times the weight of the busy one.
+        let idle_calls = idle_calls.load(Ordering::SeqCst);
+        let busy_calls = busy_calls.load(Ordering::SeqCst);
+        assert!((75..=85).contains(&idle_calls), "{} idle calls", idle_calls);
+        assert_eq!(idle_calls + busy_calls, 100);
+    }
+}
@@ -0,0 +1,49 @@
+/// See section `ORCA load report format` of the design document in
+/// :ref:`<https://github.com/envoyproxy/envoy/issues/6614`.>
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OrcaLoadReport {
+    /// CPU utilization expressed as a fraction of available CPU resources. This
+    /// should be derived from the latest sample or measurement. The value may be
+    /// larger than 1.0 when the usage exceeds the reporter dependent notion of
+    /// soft limits.
+    #[prost(double, tag = "1")]
+    pub cpu_utilization: f64,
+    /// Memory utilization expressed as a fraction of available memory
+    /// resources. This should be derived from the latest sample or measurement.
+    #[prost(double, tag = "2")]
+    pub mem_utilization: f64,
+    /// Total RPS being served by an endpoint. This should cover all services that an endpoint is
+    /// responsible for.
+    /// Deprecated -- use `rps_fractional` field instead.
+    #[deprecated]
+    #[prost(uint64, tag = "3")]
+    pub rps: u64,
+    /// Application specific requests costs. Each value is an absolute cost (e.g. 3487 bytes of
+    /// storage) associated with the request.
+    #[prost(map = "string, double", tag = "4")]
+    pub request_cost: ::std::collections::HashMap<::prost::alloc::string::String, f64>,
+    /// Resource utilization values. Each value is expressed as a fraction of total resources
+    /// available, derived from the latest sample or measurement.
+    #[prost(map = "string, double", tag = "5")]
+    pub utilization: ::std::collections::HashMap<::prost::alloc::string::String, f64>,
+    /// Total RPS being served by an endpoint. This should cover all services that an endpoint is
+    /// responsible for.
+    #[prost(double, tag = "6")]
+    pub rps_fractional: f64,
+    /// Total EPS (errors/second) being served by an endpoint. This should cover
+    /// all services that an endpoint is responsible for.
+    #[prost(double, tag = "7")]
+    pub eps: f64,
+    /// Application specific opaque metrics.
+    #[prost(map = "string, double", tag = "8")]
+    pub named_metrics: ::std::collections::HashMap<::prost::alloc::string::String, f64>,
+    /// Application specific utilization expressed as a fraction of available
+    /// resources. For example, an application may report the max of CPU and memory
+    /// utilization for better load balancing if it is both CPU and memory bound.
+    /// This should be derived from the latest sample or measurement.
+    /// The value may be larger than 1.0 when the usage exceeds the reporter
+    /// dependent notion of soft limits.
+    #[prost(double, tag = "9")]
+    pub application_utilization: f64,
+}
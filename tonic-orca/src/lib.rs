@@ -0,0 +1,157 @@
+//! A `tonic` based implementation of [ORCA] (Open Request Cost Aggregation) backend load
+//! reporting.
+//!
+//! Servers report their load to clients in two ways:
+//!
+//! - in the `endpoint-load-metrics-bin` trailer of each call, recorded by the handler of the call
+//!   with the [`MetricsRecorder`] that [`CallMetricsLayer`](server::CallMetricsLayer) adds to the
+//!   extensions of requests,
+//! - out-of-band, on the streams of the `xds.service.orca.v3.OpenRcaService` served by
+//!   [`orca_service`](server::orca_service).
+//!
+//! Clients balance calls over the endpoints of a channel by the load they report with the
+//! [`WeightedRoundRobin`](client::WeightedRoundRobin) load balancing policy.
+//!
+//! # Example
+//!
+//! ```
+//! use tonic_orca::{server::CallMetricsLayer, MetricsRecorder};
+//! use tonic::{Request, Response, Status};
+//!
+//! # struct Input; struct Output;
+//! async fn handler(request: Request<Input>) -> Result<Response<Output>, Status> {
+//!     if let Some(recorder) = request.extensions().get::<MetricsRecorder>() {
+//!         recorder.set_cpu_utilization(0.4);
+//!         recorder.set_qps(120.0);
+//!     }
+//!     Ok(Response::new(Output))
+//! }
+//!
+//! # fn f() {
+//! let server = tonic::transport::Server::builder().layer(CallMetricsLayer::new());
+//! # }
+//! ```
+//!
+//! [ORCA]: https://github.com/grpc/proposal/blob/master/A51-custom-backend-metrics.md
+
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![doc(html_root_url = "https://docs.rs/tonic-orca/0.10.0")]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+#![doc(test(no_crate_inject, attr(deny(rust_2018_idioms))))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use std::sync::{Arc, Mutex};
+
+mod generated {
+    #![allow(unreachable_pub)]
+    #![allow(missing_docs)]
+
+    pub mod xds {
+        pub mod data {
+            pub mod orca {
+                pub mod v3 {
+                    include!("generated/xds_data_orca_v3.rs");
+                }
+            }
+        }
+
+        pub mod service {
+            pub mod orca {
+                pub mod v3 {
+                    include!("generated/xds_service_orca_v3.rs");
+                }
+            }
+        }
+    }
+}
+
+/// Generated protobuf types from the `xds.data.orca.v3` and `xds.service.orca.v3` packages.
+pub mod pb {
+    pub use crate::generated::xds::data::orca::v3::*;
+    pub use crate::generated::xds::service::orca::v3::*;
+}
+
+#[cfg(feature = "transport")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+pub mod client;
+pub mod server;
+
+/// The trailer carrying the load report of a call.
+const TRAILER: &str = "endpoint-load-metrics-bin";
+
+/// Records load metrics reported to clients.
+///
+/// A recorder is either the recorder of one call, found in the extensions of its request, or the
+/// recorder of the whole server given to [`orca_service`](server::orca_service). Clones share the
+/// same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRecorder {
+    report: Arc<Mutex<pb::OrcaLoadReport>>,
+}
+
+impl MetricsRecorder {
+    /// Create a recorder without metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the CPU utilization, as a fraction of the available CPU.
+    pub fn set_cpu_utilization(&self, utilization: f64) {
+        self.update(|report| report.cpu_utilization = utilization);
+    }
+
+    /// Set the memory utilization, as a fraction of the available memory.
+    pub fn set_memory_utilization(&self, utilization: f64) {
+        self.update(|report| report.mem_utilization = utilization);
+    }
+
+    /// Set the application specific utilization, as a fraction of the available resources.
+    ///
+    /// Weighted round robin balances on this utilization rather than the CPU one when it is set.
+    pub fn set_application_utilization(&self, utilization: f64) {
+        self.update(|report| report.application_utilization = utilization);
+    }
+
+    /// Set the number of queries per second served.
+    pub fn set_qps(&self, qps: f64) {
+        self.update(|report| report.rps_fractional = qps);
+    }
+
+    /// Set the number of errors per second served.
+    pub fn set_eps(&self, eps: f64) {
+        self.update(|report| report.eps = eps);
+    }
+
+    /// Set the application specific cost `name` of the request.
+    pub fn set_request_cost(&self, name: impl Into<String>, cost: f64) {
+        self.update(|report| report.request_cost.insert(name.into(), cost));
+    }
+
+    /// Set the utilization of the resource `name`, as a fraction of its available amount.
+    pub fn set_utilization(&self, name: impl Into<String>, utilization: f64) {
+        self.update(|report| report.utilization.insert(name.into(), utilization));
+    }
+
+    /// Set the application specific metric `name`.
+    pub fn set_named_metric(&self, name: impl Into<String>, value: f64) {
+        self.update(|report| report.named_metrics.insert(name.into(), value));
+    }
+
+    /// The load report of the recorded metrics.
+    pub fn report(&self) -> pb::OrcaLoadReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut pb::OrcaLoadReport) -> T) {
+        f(&mut self.report.lock().unwrap());
+    }
+}
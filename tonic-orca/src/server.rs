@@ -0,0 +1,342 @@
+//! Contains all load reporting server utilities.
+
+use crate::pb::open_rca_service_server::{OpenRcaService, OpenRcaServiceServer};
+use crate::pb::{OrcaLoadReport, OrcaLoadReportRequest};
+use crate::{MetricsRecorder, TRAILER};
+use http::{HeaderMap, Request, Response};
+use http_body::Body;
+use pin_project::pin_project;
+use prost::Message;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio_stream::Stream;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The default minimum interval between two out-of-band load reports.
+const DEFAULT_MIN_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Creates an `OpenRcaServiceServer` streaming the metrics of `recorder` to the clients of the
+/// `xds.service.orca.v3.OpenRcaService`.
+///
+/// Clients ask for reports at an interval of at least 30 seconds, see
+/// [`OrcaReportingService::min_report_interval`] to allow shorter ones.
+pub fn orca_service(recorder: MetricsRecorder) -> OpenRcaServiceServer<OrcaReportingService> {
+    OpenRcaServiceServer::new(OrcaReportingService::new(recorder))
+}
+
+/// A service streaming out-of-band load reports.
+#[derive(Debug)]
+pub struct OrcaReportingService {
+    recorder: MetricsRecorder,
+    min_report_interval: Duration,
+}
+
+impl OrcaReportingService {
+    /// Create a service streaming the metrics of `recorder`.
+    pub fn new(recorder: MetricsRecorder) -> Self {
+        OrcaReportingService {
+            recorder,
+            min_report_interval: DEFAULT_MIN_REPORT_INTERVAL,
+        }
+    }
+
+    /// Set the minimum interval between two reports of a stream, used for the streams asking
+    /// for a shorter one. Default is 30 seconds.
+    pub fn min_report_interval(self, interval: Duration) -> Self {
+        OrcaReportingService {
+            min_report_interval: interval,
+            ..self
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl OpenRcaService for OrcaReportingService {
+    type StreamCoreMetricsStream =
+        Pin<Box<dyn Stream<Item = Result<OrcaLoadReport, tonic::Status>> + Send + 'static>>;
+
+    async fn stream_core_metrics(
+        &self,
+        request: tonic::Request<OrcaLoadReportRequest>,
+    ) -> Result<tonic::Response<Self::StreamCoreMetricsStream>, tonic::Status> {
+        let request = request.into_inner();
+        let interval = request
+            .report_interval
+            .and_then(|interval| Duration::try_from(interval).ok())
+            .map_or(self.min_report_interval, |interval| {
+                interval.max(self.min_report_interval)
+            });
+        let names = request.request_cost_names;
+        let recorder = self.recorder.clone();
+
+        let output = async_stream::try_stream! {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+
+                let mut report = recorder.report();
+                if !names.is_empty() {
+                    report.request_cost.retain(|name, _| names.contains(name));
+                }
+                yield report;
+            }
+        };
+
+        Ok(tonic::Response::new(
+            Box::pin(output) as Self::StreamCoreMetricsStream
+        ))
+    }
+}
+
+/// A layer reporting the load metrics of each call in its `endpoint-load-metrics-bin` trailer.
+///
+/// The layer inserts a [`MetricsRecorder`] into the extensions of each request, and the handler
+/// of the call records the metrics of the call with it. Calls without metrics have no load
+/// report.
+#[derive(Debug, Clone, Default)]
+pub struct CallMetricsLayer {
+    _p: (),
+}
+
+impl CallMetricsLayer {
+    /// Create a new `CallMetricsLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for CallMetricsLayer {
+    type Service = CallMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CallMetrics { inner }
+    }
+}
+
+/// A service reporting the load metrics of each call, see [`CallMetricsLayer`].
+#[derive(Debug, Clone)]
+pub struct CallMetrics<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CallMetrics<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = Response<CallMetricsBody<ResBody>>;
+    type Error = S::Error;
+    type Future = CallMetricsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let recorder = MetricsRecorder::new();
+        request.extensions_mut().insert(recorder.clone());
+
+        CallMetricsFuture {
+            inner: self.inner.call(request),
+            recorder: Some(recorder),
+        }
+    }
+}
+
+/// Response future of [`CallMetrics`].
+#[pin_project]
+#[derive(Debug)]
+pub struct CallMetricsFuture<F> {
+    #[pin]
+    inner: F,
+    recorder: Option<MetricsRecorder>,
+}
+
+impl<F, B, E> Future for CallMetricsFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<CallMetricsBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.inner.poll(cx))?;
+        let mut recorder = this.recorder.take();
+
+        // A call failing right away has no trailers, its status is in the headers.
+        if response.headers().contains_key("grpc-status") {
+            if let Some(recorder) = recorder.take() {
+                append_report(response.headers_mut(), &recorder);
+            }
+        }
+
+        Poll::Ready(Ok(response.map(|inner| CallMetricsBody { inner, recorder })))
+    }
+}
+
+/// Response body of [`CallMetrics`], appending the load report to the trailers.
+#[pin_project]
+#[derive(Debug)]
+pub struct CallMetricsBody<B> {
+    #[pin]
+    inner: B,
+    recorder: Option<MetricsRecorder>,
+}
+
+impl<B: Body> Body for CallMetricsBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let mut trailers = ready!(this.inner.poll_trailers(cx))?;
+
+        if let (Some(trailers), Some(recorder)) = (trailers.as_mut(), this.recorder.take()) {
+            append_report(trailers, &recorder);
+        }
+
+        Poll::Ready(Ok(trailers))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn append_report(headers: &mut HeaderMap, recorder: &MetricsRecorder) {
+    let report = recorder.report();
+    if report == OrcaLoadReport::default() {
+        return;
+    }
+
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(TRAILER, MetadataValue::from_bytes(&report.encode_to_vec()));
+    headers.extend(metadata.into_headers());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::open_rca_service_server::OpenRcaService;
+    use std::{convert::Infallible, future};
+    use tokio_stream::StreamExt;
+    use tower::service_fn;
+
+    fn decode(headers: &HeaderMap) -> OrcaLoadReport {
+        let metadata = MetadataMap::from_headers(headers.clone());
+        let bytes = metadata.get_bin(TRAILER).unwrap().to_bytes().unwrap();
+        OrcaLoadReport::decode(bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reports_call_metrics_in_trailers() {
+        let svc = service_fn(|request: Request<()>| {
+            let recorder = request.extensions().get::<MetricsRecorder>().unwrap();
+            recorder.set_cpu_utilization(0.5);
+            recorder.set_named_metric("queue", 3.0);
+
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", "0".parse().unwrap());
+            future::ready(Ok::<_, Infallible>(Response::new(TrailersBody(Some(
+                trailers,
+            )))))
+        });
+        let mut svc = CallMetricsLayer::new().layer(svc);
+
+        let response = svc.call(Request::new(())).await.unwrap();
+        let mut body = response.into_body();
+        let trailers = body.trailers().await.unwrap().unwrap();
+
+        let report = decode(&trailers);
+        assert_eq!(report.cpu_utilization, 0.5);
+        assert_eq!(report.named_metrics["queue"], 3.0);
+        assert_eq!(trailers["grpc-status"], "0");
+    }
+
+    #[tokio::test]
+    async fn reports_metrics_of_failed_calls_in_headers() {
+        let svc = service_fn(|request: Request<()>| {
+            let recorder = request.extensions().get::<MetricsRecorder>().unwrap();
+            recorder.set_qps(10.0);
+
+            let mut response = Response::new(TrailersBody(None));
+            response
+                .headers_mut()
+                .insert("grpc-status", "14".parse().unwrap());
+            future::ready(Ok::<_, Infallible>(response))
+        });
+        let mut svc = CallMetricsLayer::new().layer(svc);
+
+        let response = svc.call(Request::new(())).await.unwrap();
+        assert_eq!(decode(response.headers()).rps_fractional, 10.0);
+    }
+
+    #[tokio::test]
+    async fn streams_server_metrics() {
+        let recorder = MetricsRecorder::new();
+        recorder.set_memory_utilization(0.25);
+        recorder.set_request_cost("db", 1.0);
+        recorder.set_request_cost("cache", 2.0);
+
+        let service =
+            OrcaReportingService::new(recorder.clone()).min_report_interval(Duration::ZERO);
+        let request = OrcaLoadReportRequest {
+            report_interval: Some(Duration::from_millis(10).try_into().unwrap()),
+            request_cost_names: vec!["db".to_string()],
+        };
+        let mut stream = service
+            .stream_core_metrics(tonic::Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let report = stream.next().await.unwrap().unwrap();
+        assert_eq!(report.mem_utilization, 0.25);
+        assert_eq!(report.request_cost.len(), 1);
+
+        recorder.set_memory_utilization(0.75);
+        let report = stream.next().await.unwrap().unwrap();
+        assert_eq!(report.mem_utilization, 0.75);
+    }
+
+    /// A body without data, ending with the given trailers.
+    struct TrailersBody(Option<HeaderMap>);
+
+    impl Body for TrailersBody {
+        type Data = tonic::codegen::Bytes;
+        type Error = Infallible;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            Poll::Ready(None)
+        }
+
+        fn poll_trailers(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(self.0.take()))
+        }
+    }
+}
@@ -196,6 +196,30 @@ pub mod server_reflection_client {
         {
             ServerReflectionClient::new(InterceptedService::new(inner, interceptor))
         }
+        pub fn with_async_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> ServerReflectionClient<
+            InterceptedService<T, tonic::service::interceptor::Async<F>>,
+        >
+        where
+            F: tonic::service::AsyncInterceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            T: Clone,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            ServerReflectionClient::new(
+                InterceptedService::new_async(inner, interceptor),
+            )
+        }
         /// Compress requests with the given encoding.
         ///
         /// This requires the server to support it otherwise it might respond with an
@@ -211,6 +235,12 @@ pub mod server_reflection_client {
             self.inner = self.inner.accept_compressed(encoding);
             self
         }
+        /// Configure the compression level and minimum message size used for requests.
+        #[must_use]
+        pub fn compression_settings(mut self, settings: CompressionSettings) -> Self {
+            self.inner = self.inner.compression_settings(settings);
+            self
+        }
         /// Limits the maximum size of a decoded message.
         ///
         /// Default: `4MB`
@@ -227,6 +257,16 @@ pub mod server_reflection_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
+        /// Intercept every message sent before it is encoded, and every message received
+        /// once it is decoded.
+        #[must_use]
+        pub fn message_interceptor(
+            mut self,
+            interceptor: impl tonic::service::MessageInterceptor,
+        ) -> Self {
+            self.inner = self.inner.message_interceptor(interceptor);
+            self
+        }
         /// The reflection service is structured as a bidirectional stream, ensuring
         /// all related requests go to a single server.
         pub async fn server_reflection_info(
@@ -294,8 +334,10 @@ pub mod server_reflection_server {
         inner: _Inner<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
+        compression_settings: CompressionSettings,
         max_decoding_message_size: Option<usize>,
         max_encoding_message_size: Option<usize>,
+        message_interceptor: Option<Arc<dyn tonic::service::MessageInterceptor>>,
     }
     struct _Inner<T>(Arc<T>);
     impl<T: ServerReflection> ServerReflectionServer<T> {
@@ -308,8 +350,10 @@ pub mod server_reflection_server {
                 inner,
                 accept_compression_encodings: Default::default(),
                 send_compression_encodings: Default::default(),
+                compression_settings: Default::default(),
                 max_decoding_message_size: None,
                 max_encoding_message_size: None,
+                message_interceptor: None,
             }
         }
         pub fn with_interceptor<F>(
@@ -321,6 +365,15 @@ pub mod server_reflection_server {
         {
             InterceptedService::new(Self::new(inner), interceptor)
         }
+        pub fn with_async_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, tonic::service::interceptor::Async<F>>
+        where
+            F: tonic::service::AsyncInterceptor,
+        {
+            InterceptedService::new_async(Self::new(inner), interceptor)
+        }
         /// Enable decompressing requests with the given encoding.
         #[must_use]
         pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
@@ -333,6 +386,12 @@ pub mod server_reflection_server {
             self.send_compression_encodings.enable(encoding);
             self
         }
+        /// Configure the compression level and minimum message size used for responses.
+        #[must_use]
+        pub fn compression_settings(mut self, settings: CompressionSettings) -> Self {
+            self.compression_settings = settings;
+            self
+        }
         /// Limits the maximum size of a decoded message.
         ///
         /// Default: `4MB`
@@ -349,6 +408,16 @@ pub mod server_reflection_server {
             self.max_encoding_message_size = Some(limit);
             self
         }
+        /// Intercept every message received once it is decoded, and every message sent before it
+        /// is encoded.
+        #[must_use]
+        pub fn message_interceptor(
+            mut self,
+            interceptor: impl tonic::service::MessageInterceptor,
+        ) -> Self {
+            self.message_interceptor = Some(Arc::new(interceptor));
+            self
+        }
     }
     impl<T, B> tonic::codegen::Service<http::Request<B>> for ServerReflectionServer<T>
     where
@@ -398,10 +467,12 @@ pub mod server_reflection_server {
                             Box::pin(fut)
                         }
                     }
-                    let accept_compression_encodings = self.accept_compression_encodings;
-                    let send_compression_encodings = self.send_compression_encodings;
+                    let accept_compression_encodings = self.accept_compression_encodings.clone();
+                    let send_compression_encodings = self.send_compression_encodings.clone();
+                    let compression_settings = self.compression_settings.clone();
                     let max_decoding_message_size = self.max_decoding_message_size;
                     let max_encoding_message_size = self.max_encoding_message_size;
+                    let message_interceptor = self.message_interceptor.clone();
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
@@ -412,10 +483,12 @@ pub mod server_reflection_server {
                                 accept_compression_encodings,
                                 send_compression_encodings,
                             )
+                            .apply_compression_settings(compression_settings)
                             .apply_max_message_size_config(
                                 max_decoding_message_size,
                                 max_encoding_message_size,
-                            );
+                            )
+                            .apply_message_interceptor(message_interceptor);
                         let res = grpc.streaming(method, req).await;
                         Ok(res)
                     };
@@ -441,10 +514,12 @@ pub mod server_reflection_server {
             let inner = self.inner.clone();
             Self {
                 inner,
-                accept_compression_encodings: self.accept_compression_encodings,
-                send_compression_encodings: self.send_compression_encodings,
+                accept_compression_encodings: self.accept_compression_encodings.clone(),
+                send_compression_encodings: self.send_compression_encodings.clone(),
+                compression_settings: self.compression_settings.clone(),
                 max_decoding_message_size: self.max_decoding_message_size,
                 max_encoding_message_size: self.max_encoding_message_size,
+                message_interceptor: self.message_interceptor.clone(),
             }
         }
     }
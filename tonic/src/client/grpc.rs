@@ -1,9 +1,12 @@
-use crate::codec::compression::{CompressionEncoding, EnabledCompressionEncodings};
+use crate::codec::compression::{
+    CompressionEncoding, CompressionOverride, CompressionSettings, EnabledCompressionEncodings,
+};
 use crate::{
     body::BoxBody,
     client::GrpcService,
     codec::{encode_client, Codec, Decoder, Streaming},
     request::SanitizeHeaders,
+    service::interceptor::{MessageDecoder, MessageEncoder, MessageInterceptor},
     Code, Request, Response, Status,
 };
 use http::{
@@ -11,7 +14,7 @@ use http::{
     uri::{Parts, PathAndQuery, Uri},
 };
 use http_body::Body;
-use std::{fmt, future};
+use std::{fmt, future, sync::Arc};
 use tokio_stream::{Stream, StreamExt};
 
 /// A gRPC client dispatcher.
@@ -38,12 +41,25 @@ struct GrpcConfig {
     accept_compression_encodings: EnabledCompressionEncodings,
     /// The compression encoding that will be applied to requests.
     send_compression_encodings: Option<CompressionEncoding>,
+    /// How requests are compressed.
+    compression_settings: CompressionSettings,
     /// Limits the maximum size of a decoded message.
     max_decoding_message_size: Option<usize>,
     /// Limits the maximum size of an encoded message.
     max_encoding_message_size: Option<usize>,
+    /// Intercepts the messages sent and received.
+    message_interceptor: Option<Arc<dyn MessageInterceptor>>,
 }
 
+/// The maximum size of the response messages of a call, limited by the service config of a
+/// channel.
+///
+/// Channels add it to the extensions of the responses of the calls they limit, and [`Grpc`]
+/// applies it unless it limits the messages further itself.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "transport"), allow(dead_code))]
+pub(crate) struct MaxResponseMessageSize(pub(crate) usize);
+
 impl<T> Grpc<T> {
     /// Creates a new gRPC client with the provided [`GrpcService`].
     pub fn new(inner: T) -> Self {
@@ -61,8 +77,10 @@ impl<T> Grpc<T> {
                 origin,
                 send_compression_encodings: None,
                 accept_compression_encodings: EnabledCompressionEncodings::default(),
+                compression_settings: CompressionSettings::default(),
                 max_decoding_message_size: None,
                 max_encoding_message_size: None,
+                message_interceptor: None,
             },
         }
     }
@@ -129,6 +147,44 @@ impl<T> Grpc<T> {
         self
     }
 
+    /// Configure the compression level and minimum message size used for compressed requests.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a client generated by tonic-build:
+    ///
+    /// ```rust
+    /// use tonic::transport::Channel;
+    /// # enum CompressionEncoding { Gzip }
+    /// # struct CompressionSettings;
+    /// # impl CompressionSettings {
+    /// #     fn new() -> Self { Self }
+    /// #     fn min_message_size(self, _: usize) -> Self { self }
+    /// # }
+    /// # struct TestClient<T>(T);
+    /// # impl<T> TestClient<T> {
+    /// #     fn new(channel: T) -> Self { Self(channel) }
+    /// #     fn send_compressed(self, _: CompressionEncoding) -> Self { self }
+    /// #     fn compression_settings(self, _: CompressionSettings) -> Self { self }
+    /// # }
+    ///
+    /// # async {
+    /// let channel = Channel::builder("127.0.0.1:3000".parse().unwrap())
+    ///     .connect()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// // Only compress requests of at least 1KiB.
+    /// let client = TestClient::new(channel)
+    ///     .send_compressed(CompressionEncoding::Gzip)
+    ///     .compression_settings(CompressionSettings::new().min_message_size(1024));
+    /// # };
+    /// ```
+    pub fn compression_settings(mut self, settings: CompressionSettings) -> Self {
+        self.config.compression_settings = settings;
+        self
+    }
+
     /// Limits the maximum size of a decoded message.
     ///
     /// # Example
@@ -189,6 +245,43 @@ impl<T> Grpc<T> {
         self
     }
 
+    /// Intercept every message sent by a call before it is encoded, and every message received
+    /// once it is decoded.
+    ///
+    /// See [`MessageInterceptor`] for more details.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a client generated by tonic-build:
+    ///
+    /// ```rust
+    /// use tonic::{service::MessageInterceptor, transport::Channel};
+    /// # struct TestClient<T>(T);
+    /// # impl<T> TestClient<T> {
+    /// #     fn new(channel: T) -> Self { Self(channel) }
+    /// #     fn message_interceptor(self, _: impl MessageInterceptor) -> Self { self }
+    /// # }
+    ///
+    /// struct Validate;
+    ///
+    /// impl MessageInterceptor for Validate {
+    ///     // ...
+    /// }
+    ///
+    /// # async {
+    /// let channel = Channel::builder("127.0.0.1:3000".parse().unwrap())
+    ///     .connect()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let client = TestClient::new(channel).message_interceptor(Validate);
+    /// # };
+    /// ```
+    pub fn message_interceptor(mut self, interceptor: impl MessageInterceptor) -> Self {
+        self.config.message_interceptor = Some(Arc::new(interceptor));
+        self
+    }
+
     /// Check if the inner [`GrpcService`] is able to accept a  new request.
     ///
     /// This will call [`GrpcService::poll_ready`] until it returns ready or
@@ -292,18 +385,26 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        let compression_encoding = match request.extensions().get::<CompressionOverride>() {
+            Some(CompressionOverride(encoding)) => encoding.clone(),
+            None => self.config.send_compression_encodings.clone(),
+        };
+
         let request = request
             .map(|s| {
                 encode_client(
-                    codec.encoder(),
+                    MessageEncoder::new(codec.encoder(), self.config.message_interceptor.clone()),
                     s,
-                    self.config.send_compression_encodings,
+                    compression_encoding.clone(),
+                    &self.config.compression_settings,
                     self.config.max_encoding_message_size,
                 )
             })
             .map(BoxBody::new);
 
-        let request = self.config.prepare_request(request, path);
+        let request = self
+            .config
+            .prepare_request(request, path, compression_encoding.as_ref());
 
         let response = self
             .inner
@@ -311,7 +412,7 @@ impl<T> Grpc<T> {
             .await
             .map_err(Status::from_error_generic)?;
 
-        let decoder = codec.decoder();
+        let decoder = MessageDecoder::new(codec.decoder(), self.config.message_interceptor.clone());
 
         self.create_response(decoder, response)
     }
@@ -330,9 +431,18 @@ impl<T> Grpc<T> {
     {
         let encoding = CompressionEncoding::from_encoding_header(
             response.headers(),
-            self.config.accept_compression_encodings,
+            &self.config.accept_compression_encodings,
         )?;
 
+        let max_message_size = match response.extensions().get::<MaxResponseMessageSize>() {
+            Some(MaxResponseMessageSize(limit)) => Some(
+                self.config
+                    .max_decoding_message_size
+                    .map_or(*limit, |max| max.min(*limit)),
+            ),
+            None => self.config.max_decoding_message_size,
+        };
+
         let status_code = response.status();
         let trailers_only_status = Status::from_header_map(response.headers());
 
@@ -350,13 +460,7 @@ impl<T> Grpc<T> {
 
         let response = response.map(|body| {
             if expect_additional_trailers {
-                Streaming::new_response(
-                    decoder,
-                    body,
-                    status_code,
-                    encoding,
-                    self.config.max_decoding_message_size,
-                )
+                Streaming::new_response(decoder, body, status_code, encoding, max_message_size)
             } else {
                 Streaming::new_empty(decoder, body)
             }
@@ -371,6 +475,7 @@ impl GrpcConfig {
         &self,
         request: Request<BoxBody>,
         path: PathAndQuery,
+        compression_encoding: Option<&CompressionEncoding>,
     ) -> http::Request<BoxBody> {
         let scheme = self.origin.scheme().cloned();
         let authority = self.origin.authority().cloned();
@@ -399,16 +504,16 @@ impl GrpcConfig {
             .headers_mut()
             .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
 
-        if let Some(encoding) = self.send_compression_encodings {
+        if let Some(encoding) = compression_encoding {
             request.headers_mut().insert(
                 crate::codec::compression::ENCODING_HEADER,
-                encoding.into_header_value(),
+                encoding.to_header_value(),
             );
         }
 
         if let Some(header_value) = self
             .accept_compression_encodings
-            .into_accept_encoding_header_value()
+            .to_accept_encoding_header_value()
         {
             request.headers_mut().insert(
                 crate::codec::compression::ACCEPT_ENCODING_HEADER,
@@ -426,10 +531,12 @@ impl<T: Clone> Clone for Grpc<T> {
             inner: self.inner.clone(),
             config: GrpcConfig {
                 origin: self.config.origin.clone(),
-                send_compression_encodings: self.config.send_compression_encodings,
-                accept_compression_encodings: self.config.accept_compression_encodings,
+                send_compression_encodings: self.config.send_compression_encodings.clone(),
+                accept_compression_encodings: self.config.accept_compression_encodings.clone(),
+                compression_settings: self.config.compression_settings.clone(),
                 max_encoding_message_size: self.config.max_encoding_message_size,
                 max_decoding_message_size: self.config.max_decoding_message_size,
+                message_interceptor: self.config.message_interceptor.clone(),
             },
         }
     }
@@ -453,6 +560,8 @@ impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
             &self.config.accept_compression_encodings,
         );
 
+        f.field("compression_settings", &self.config.compression_settings);
+
         f.field(
             "max_decoding_message_size",
             &self.config.max_decoding_message_size,
@@ -463,6 +572,8 @@ impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
             &self.config.max_encoding_message_size,
         );
 
+        f.field("message_interceptor", &self.config.message_interceptor);
+
         f.finish()
     }
 }
@@ -20,4 +20,6 @@ mod grpc;
 mod service;
 
 pub use self::grpc::Grpc;
+#[cfg(feature = "transport")]
+pub(crate) use self::grpc::MaxResponseMessageSize;
 pub use self::service::GrpcService;
@@ -3,88 +3,312 @@ use crate::{metadata::MetadataValue, Status};
 use bytes::{Buf, BytesMut};
 #[cfg(feature = "gzip")]
 use flate2::read::{GzDecoder, GzEncoder};
-use std::fmt;
+use std::{fmt, io, sync::Arc};
+#[cfg(feature = "zstd")]
+use zstd::stream::read::{Decoder as ZstdDecoder, Encoder as ZstdEncoder};
 
 pub(crate) const ENCODING_HEADER: &str = "grpc-encoding";
 pub(crate) const ACCEPT_ENCODING_HEADER: &str = "grpc-accept-encoding";
 
+/// A compression algorithm that can be used for gRPC messages.
+///
+/// Implementations are registered on clients and servers by wrapping them with
+/// [`CompressionEncoding::custom`] and passing them to `accept_compressed` and
+/// `send_compressed`.
+///
+/// # Example
+///
+/// ```rust
+/// use bytes::{BufMut, BytesMut};
+/// use tonic::codec::{CompressionEncoding, CompressionLevel, Compressor};
+///
+/// /// A "compressor" that copies its input verbatim.
+/// struct Noop;
+///
+/// impl Compressor for Noop {
+///     fn name(&self) -> &'static str {
+///         "noop"
+///     }
+///
+///     fn compress(
+///         &self,
+///         input: &[u8],
+///         output: &mut BytesMut,
+///         _level: CompressionLevel,
+///     ) -> std::io::Result<()> {
+///         output.put_slice(input);
+///         Ok(())
+///     }
+///
+///     fn decompress(&self, input: &[u8], output: &mut BytesMut) -> std::io::Result<()> {
+///         output.put_slice(input);
+///         Ok(())
+///     }
+/// }
+///
+/// let encoding = CompressionEncoding::custom(Noop).unwrap();
+/// assert_eq!(encoding.name(), "noop");
+/// ```
+pub trait Compressor: Send + Sync + 'static {
+    /// The name of the algorithm, as used in the `grpc-encoding` and `grpc-accept-encoding`
+    /// headers.
+    ///
+    /// This must be a non-empty HTTP token, so it can't contain a `,` or whitespace, and must not
+    /// be `identity`. It is read once, when the compressor is wrapped with
+    /// [`CompressionEncoding::custom`].
+    fn name(&self) -> &'static str;
+
+    /// Compress `input` at the given `level`, appending the compressed bytes to `output`.
+    fn compress(
+        &self,
+        input: &[u8],
+        output: &mut BytesMut,
+        level: CompressionLevel,
+    ) -> io::Result<()>;
+
+    /// Decompress `input`, appending the decompressed bytes to `output`.
+    fn decompress(&self, input: &[u8], output: &mut BytesMut) -> io::Result<()>;
+}
+
+/// How hard a [`Compressor`] should try to shrink a message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressionLevel {
+    /// The fastest level the algorithm supports, at the cost of compression ratio.
+    Fastest,
+    /// The default level of the algorithm.
+    #[default]
+    Default,
+    /// The best compression ratio the algorithm supports, at the cost of speed.
+    Best,
+    /// An algorithm specific level. Values outside of the range the algorithm supports are
+    /// clamped to it.
+    Precise(i32),
+}
+
+/// Settings that control how outgoing messages are compressed.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "gzip")] {
+/// use tonic::codec::{CompressionEncoding, CompressionLevel, CompressionSettings};
+///
+/// let settings = CompressionSettings::new()
+///     .level(CompressionEncoding::Gzip, CompressionLevel::Fastest)
+///     // Don't bother compressing messages smaller than 1KiB.
+///     .min_message_size(1024);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompressionSettings {
+    levels: Vec<(&'static str, CompressionLevel)>,
+    min_message_size: usize,
+}
+
+impl CompressionSettings {
+    /// Create settings that compress every message at the default level.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the compression level used for the given encoding.
+    ///
+    /// Defaults to [`CompressionLevel::Default`].
+    pub fn level(mut self, encoding: CompressionEncoding, level: CompressionLevel) -> Self {
+        let name = encoding.name();
+        match self.levels.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, l)) => *l = level,
+            None => self.levels.push((name, level)),
+        }
+        self
+    }
+
+    /// Leave messages that are smaller than `size` bytes before compression uncompressed.
+    ///
+    /// Such messages are sent with the compressed flag unset, even though the stream uses a
+    /// compression encoding. Defaults to `0`, compressing every message.
+    pub fn min_message_size(mut self, size: usize) -> Self {
+        self.min_message_size = size;
+        self
+    }
+
+    pub(crate) fn level_for(&self, encoding: &CompressionEncoding) -> CompressionLevel {
+        let name = encoding.name();
+        self.levels
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, level)| *level)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn get_min_message_size(&self) -> usize {
+        self.min_message_size
+    }
+}
+
 /// Struct used to configure which encodings are enabled on a server or channel.
-#[derive(Debug, Default, Clone, Copy)]
+///
+/// Encodings are kept in the order they were enabled in, which is also the order of preference
+/// advertised in the `grpc-accept-encoding` header.
+///
+/// The encodings are shared between clones, so cloning the set for every call is cheap.
+#[derive(Debug, Default, Clone)]
 pub struct EnabledCompressionEncodings {
-    #[cfg(feature = "gzip")]
-    pub(crate) gzip: bool,
+    encodings: Arc<Vec<CompressionEncoding>>,
 }
 
 impl EnabledCompressionEncodings {
     /// Check if a [`CompressionEncoding`] is enabled.
     pub fn is_enabled(&self, encoding: CompressionEncoding) -> bool {
-        match encoding {
-            #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip => self.gzip,
-        }
+        self.get(encoding.name()).is_some()
     }
 
     /// Enable a [`CompressionEncoding`].
+    ///
+    /// Enabling an encoding with the same name as an already enabled one replaces it, without
+    /// changing its position in the order of preference.
     pub fn enable(&mut self, encoding: CompressionEncoding) {
-        match encoding {
-            #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip => self.gzip = true,
+        let encodings = Arc::make_mut(&mut self.encodings);
+        match encodings
+            .iter_mut()
+            .find(|enabled| enabled.name() == encoding.name())
+        {
+            Some(enabled) => *enabled = encoding,
+            None => encodings.push(encoding),
         }
     }
 
-    pub(crate) fn into_accept_encoding_header_value(self) -> Option<http::HeaderValue> {
-        if self.is_gzip_enabled() {
-            Some(http::HeaderValue::from_static("gzip,identity"))
-        } else {
-            None
-        }
+    /// Iterate over the enabled encodings, in order of preference.
+    pub fn iter(&self) -> impl Iterator<Item = &CompressionEncoding> {
+        self.encodings.iter()
     }
 
-    #[cfg(feature = "gzip")]
-    const fn is_gzip_enabled(&self) -> bool {
-        self.gzip
+    fn get(&self, name: &str) -> Option<&CompressionEncoding> {
+        self.encodings
+            .iter()
+            .find(|encoding| encoding.name() == name)
     }
 
-    #[cfg(not(feature = "gzip"))]
-    const fn is_gzip_enabled(&self) -> bool {
-        false
+    fn is_empty(&self) -> bool {
+        self.encodings.is_empty()
+    }
+
+    pub(crate) fn to_accept_encoding_header_value(&self) -> Option<http::HeaderValue> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut value = String::new();
+        for encoding in self.encodings.iter() {
+            value.push_str(encoding.name());
+            value.push(',');
+        }
+        value.push_str("identity");
+
+        http::HeaderValue::from_str(&value).ok()
     }
 }
 
 /// The compression encodings Tonic supports.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone)]
 #[non_exhaustive]
 pub enum CompressionEncoding {
     #[allow(missing_docs)]
     #[cfg(feature = "gzip")]
     #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
     Gzip,
+    #[allow(missing_docs)]
+    #[cfg(feature = "zstd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zstd")))]
+    Zstd,
+    /// A user provided compression algorithm, created with [`CompressionEncoding::custom`].
+    Custom(CustomEncoding),
+}
+
+/// A user provided [`Compressor`] whose name was checked to be a valid encoding name.
+///
+/// Created with [`CompressionEncoding::custom`].
+#[derive(Clone)]
+pub struct CustomEncoding {
+    compressor: Arc<dyn Compressor>,
+    name: &'static str,
+    header_value: http::HeaderValue,
+}
+
+/// Error returned by [`CompressionEncoding::custom`] when the name of the compressor isn't a
+/// valid encoding name.
+#[derive(Debug)]
+pub struct InvalidEncodingName {
+    name: &'static str,
 }
 
 impl CompressionEncoding {
+    /// Create an encoding from a user provided [`Compressor`].
+    ///
+    /// Fails when the name of the compressor is empty, isn't an HTTP token, or is `identity`.
+    pub fn custom<C: Compressor>(compressor: C) -> Result<Self, InvalidEncodingName> {
+        let name = compressor.name();
+        let is_token = !name.is_empty() && name.bytes().all(is_token_char);
+        if !is_token || name.eq_ignore_ascii_case("identity") {
+            return Err(InvalidEncodingName { name });
+        }
+
+        let header_value =
+            http::HeaderValue::from_str(name).map_err(|_| InvalidEncodingName { name })?;
+
+        Ok(Self::Custom(CustomEncoding {
+            compressor: Arc::new(compressor),
+            name,
+            header_value,
+        }))
+    }
+
+    /// The name of the encoding, as used in the `grpc-encoding` header.
+    pub fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "gzip")]
+            CompressionEncoding::Gzip => Gzip.name(),
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd => Zstd.name(),
+            CompressionEncoding::Custom(custom) => custom.name,
+        }
+    }
+
+    fn compressor(&self) -> &dyn Compressor {
+        match self {
+            #[cfg(feature = "gzip")]
+            CompressionEncoding::Gzip => &Gzip,
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd => &Zstd,
+            CompressionEncoding::Custom(custom) => &*custom.compressor,
+        }
+    }
+
     /// Based on the `grpc-accept-encoding` header, pick an encoding to use.
+    ///
+    /// The header lists the encodings the peer accepts in order of preference, so the first one
+    /// that is also enabled locally wins.
     pub(crate) fn from_accept_encoding_header(
         map: &http::HeaderMap,
-        enabled_encodings: EnabledCompressionEncodings,
+        enabled_encodings: &EnabledCompressionEncodings,
     ) -> Option<Self> {
-        if !enabled_encodings.is_gzip_enabled() {
+        if enabled_encodings.is_empty() {
             return None;
         }
 
         let header_value = map.get(ACCEPT_ENCODING_HEADER)?;
         let header_value_str = header_value.to_str().ok()?;
 
-        split_by_comma(header_value_str).find_map(|value| match value {
-            #[cfg(feature = "gzip")]
-            "gzip" => Some(CompressionEncoding::Gzip),
-            _ => None,
-        })
+        split_by_comma(header_value_str)
+            .find_map(|value| enabled_encodings.get(value))
+            .cloned()
     }
 
     /// Get the value of `grpc-encoding` header. Returns an error if the encoding isn't supported.
     pub(crate) fn from_encoding_header(
         map: &http::HeaderMap,
-        enabled_encodings: EnabledCompressionEncodings,
+        enabled_encodings: &EnabledCompressionEncodings,
     ) -> Result<Option<Self>, Status> {
         let header_value = if let Some(value) = map.get(ENCODING_HEADER) {
             value
@@ -98,64 +322,181 @@ impl CompressionEncoding {
             return Ok(None);
         };
 
-        match header_value_str {
-            #[cfg(feature = "gzip")]
-            "gzip" if enabled_encodings.is_enabled(CompressionEncoding::Gzip) => {
-                Ok(Some(CompressionEncoding::Gzip))
-            }
-            "identity" => Ok(None),
-            other => {
-                let mut status = Status::unimplemented(format!(
-                    "Content is compressed with `{}` which isn't supported",
-                    other
-                ));
-
-                let header_value = enabled_encodings
-                    .into_accept_encoding_header_value()
-                    .map(MetadataValue::unchecked_from_header_value)
-                    .unwrap_or_else(|| MetadataValue::from_static("identity"));
-                status
-                    .metadata_mut()
-                    .insert(ACCEPT_ENCODING_HEADER, header_value);
-
-                Err(status)
-            }
+        if header_value_str == "identity" {
+            return Ok(None);
+        }
+
+        if let Some(encoding) = enabled_encodings.get(header_value_str) {
+            return Ok(Some(encoding.clone()));
+        }
+
+        let mut status = Status::unimplemented(format!(
+            "Content is compressed with `{}` which isn't supported",
+            header_value_str
+        ));
+
+        let header_value = enabled_encodings
+            .to_accept_encoding_header_value()
+            .map(MetadataValue::unchecked_from_header_value)
+            .unwrap_or_else(|| MetadataValue::from_static("identity"));
+        status
+            .metadata_mut()
+            .insert(ACCEPT_ENCODING_HEADER, header_value);
+
+        Err(status)
+    }
+
+    /// Whether a peer that sent `accept_encoding` as its `grpc-accept-encoding` header can
+    /// decompress this encoding.
+    pub(crate) fn is_accepted_by(&self, accept_encoding: Option<&http::HeaderValue>) -> bool {
+        match accept_encoding.and_then(|value| value.to_str().ok()) {
+            Some(value) => split_by_comma(value).any(|value| value == self.name()),
+            None => false,
         }
     }
 
-    pub(crate) fn into_header_value(self) -> http::HeaderValue {
+    pub(crate) fn to_header_value(&self) -> http::HeaderValue {
         match self {
-            #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip => http::HeaderValue::from_static("gzip"),
+            CompressionEncoding::Custom(custom) => custom.header_value.clone(),
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            _ => http::HeaderValue::from_str(self.name())
+                .expect("the names of the built-in encodings are valid header values"),
         }
     }
+}
 
-    pub(crate) fn encodings() -> &'static [Self] {
-        &[
-            #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip,
-        ]
+impl PartialEq for CompressionEncoding {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
     }
 }
 
-impl fmt::Display for CompressionEncoding {
-    #[allow(unused_variables)]
+impl Eq for CompressionEncoding {}
+
+impl fmt::Debug for CompressionEncoding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
+        match self {
             #[cfg(feature = "gzip")]
-            CompressionEncoding::Gzip => write!(f, "gzip"),
+            CompressionEncoding::Gzip => f.write_str("Gzip"),
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd => f.write_str("Zstd"),
+            CompressionEncoding::Custom(custom) => f.debug_tuple("Custom").field(custom).finish(),
         }
     }
 }
 
+impl fmt::Debug for CustomEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl fmt::Display for InvalidEncodingName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` isn't a valid compression encoding name", self.name)
+    }
+}
+
+impl std::error::Error for InvalidEncodingName {}
+
+impl fmt::Display for CompressionEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 fn split_by_comma(s: &str) -> impl Iterator<Item = &str> {
     s.trim().split(',').map(|s| s.trim())
 }
 
+/// Whether `b` may appear in an HTTP token, see RFC 9110 section 5.6.2.
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[cfg(feature = "gzip")]
+struct Gzip;
+
+#[cfg(feature = "gzip")]
+impl Compressor for Gzip {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+
+    fn compress(
+        &self,
+        input: &[u8],
+        output: &mut BytesMut,
+        level: CompressionLevel,
+    ) -> io::Result<()> {
+        let level = match level {
+            CompressionLevel::Fastest => flate2::Compression::fast(),
+            CompressionLevel::Default => flate2::Compression::new(6),
+            CompressionLevel::Best => flate2::Compression::best(),
+            CompressionLevel::Precise(level) => flate2::Compression::new(level.clamp(0, 9) as u32),
+        };
+        let mut gzip_encoder = GzEncoder::new(input, level);
+        let mut out_writer = bytes::BufMut::writer(output);
+
+        io::copy(&mut gzip_encoder, &mut out_writer)?;
+
+        Ok(())
+    }
+
+    fn decompress(&self, input: &[u8], output: &mut BytesMut) -> io::Result<()> {
+        let mut gzip_decoder = GzDecoder::new(input);
+        let mut out_writer = bytes::BufMut::writer(output);
+
+        io::copy(&mut gzip_decoder, &mut out_writer)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zstd")]
+struct Zstd;
+
+#[cfg(feature = "zstd")]
+impl Compressor for Zstd {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn compress(
+        &self,
+        input: &[u8],
+        output: &mut BytesMut,
+        level: CompressionLevel,
+    ) -> io::Result<()> {
+        let range = zstd::compression_level_range();
+        let level = match level {
+            CompressionLevel::Fastest => 1,
+            CompressionLevel::Default => zstd::DEFAULT_COMPRESSION_LEVEL,
+            CompressionLevel::Best => *range.end(),
+            CompressionLevel::Precise(level) => level.clamp(*range.start(), *range.end()),
+        };
+        let mut zstd_encoder = ZstdEncoder::new(input, level)?;
+        let mut out_writer = bytes::BufMut::writer(output);
+
+        io::copy(&mut zstd_encoder, &mut out_writer)?;
+
+        Ok(())
+    }
+
+    fn decompress(&self, input: &[u8], output: &mut BytesMut) -> io::Result<()> {
+        let mut zstd_decoder = ZstdDecoder::new(input)?;
+        let mut out_writer = bytes::BufMut::writer(output);
+
+        io::copy(&mut zstd_decoder, &mut out_writer)?;
+
+        Ok(())
+    }
+}
+
 /// Compress `len` bytes from `decompressed_buf` into `out_buf`.
-#[allow(unused_variables, unreachable_code)]
 pub(crate) fn compress(
-    encoding: CompressionEncoding,
+    encoding: &CompressionEncoding,
+    level: CompressionLevel,
     decompressed_buf: &mut BytesMut,
     out_buf: &mut BytesMut,
     len: usize,
@@ -163,19 +504,9 @@ pub(crate) fn compress(
     let capacity = ((len / BUFFER_SIZE) + 1) * BUFFER_SIZE;
     out_buf.reserve(capacity);
 
-    match encoding {
-        #[cfg(feature = "gzip")]
-        CompressionEncoding::Gzip => {
-            let mut gzip_encoder = GzEncoder::new(
-                &decompressed_buf[0..len],
-                // FIXME: support customizing the compression level
-                flate2::Compression::new(6),
-            );
-            let mut out_writer = bytes::BufMut::writer(out_buf);
-
-            std::io::copy(&mut gzip_encoder, &mut out_writer)?;
-        }
-    }
+    encoding
+        .compressor()
+        .compress(&decompressed_buf[0..len], out_buf, level)?;
 
     decompressed_buf.advance(len);
 
@@ -183,9 +514,8 @@ pub(crate) fn compress(
 }
 
 /// Decompress `len` bytes from `compressed_buf` into `out_buf`.
-#[allow(unused_variables, unreachable_code)]
 pub(crate) fn decompress(
-    encoding: CompressionEncoding,
+    encoding: &CompressionEncoding,
     compressed_buf: &mut BytesMut,
     out_buf: &mut BytesMut,
     len: usize,
@@ -194,15 +524,9 @@ pub(crate) fn decompress(
     let capacity = ((estimate_decompressed_len / BUFFER_SIZE) + 1) * BUFFER_SIZE;
     out_buf.reserve(capacity);
 
-    match encoding {
-        #[cfg(feature = "gzip")]
-        CompressionEncoding::Gzip => {
-            let mut gzip_decoder = GzDecoder::new(&compressed_buf[0..len]);
-            let mut out_writer = bytes::BufMut::writer(out_buf);
-
-            std::io::copy(&mut gzip_decoder, &mut out_writer)?;
-        }
-    }
+    encoding
+        .compressor()
+        .decompress(&compressed_buf[0..len], out_buf)?;
 
     compressed_buf.advance(len);
 
@@ -225,3 +549,293 @@ impl Default for SingleMessageCompressionOverride {
         Self::Inherit
     }
 }
+
+/// Compression chosen for a single call with [`Request::set_compression`] or
+/// [`Response::set_compression`], taking precedence over the configured default. `None` means
+/// the call is sent uncompressed.
+///
+/// [`Request::set_compression`]: crate::Request::set_compression
+/// [`Response::set_compression`]: crate::Response::set_compression
+#[derive(Debug, Clone)]
+pub(crate) struct CompressionOverride(pub(crate) Option<CompressionEncoding>);
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bytes::BufMut;
+
+    /// A compressor reversing its input.
+    pub(crate) struct Reverse;
+
+    impl Compressor for Reverse {
+        fn name(&self) -> &'static str {
+            "reverse"
+        }
+
+        fn compress(
+            &self,
+            input: &[u8],
+            output: &mut BytesMut,
+            _level: CompressionLevel,
+        ) -> io::Result<()> {
+            output.extend(input.iter().rev());
+            Ok(())
+        }
+
+        fn decompress(&self, input: &[u8], output: &mut BytesMut) -> io::Result<()> {
+            output.extend(input.iter().rev());
+            Ok(())
+        }
+    }
+
+    /// A compressor leaving its input as is.
+    pub(crate) struct Noop;
+
+    impl Compressor for Noop {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+
+        fn compress(
+            &self,
+            input: &[u8],
+            output: &mut BytesMut,
+            _level: CompressionLevel,
+        ) -> io::Result<()> {
+            output.put_slice(input);
+            Ok(())
+        }
+
+        fn decompress(&self, input: &[u8], output: &mut BytesMut) -> io::Result<()> {
+            output.put_slice(input);
+            Ok(())
+        }
+    }
+
+    fn accept_encoding(value: &'static str) -> http::HeaderMap {
+        let mut map = http::HeaderMap::new();
+        map.insert(
+            ACCEPT_ENCODING_HEADER,
+            http::HeaderValue::from_static(value),
+        );
+        map
+    }
+
+    #[test]
+    fn accept_encoding_header_lists_encodings_in_order() {
+        let mut enabled = EnabledCompressionEncodings::default();
+        assert!(enabled.to_accept_encoding_header_value().is_none());
+
+        enabled.enable(CompressionEncoding::custom(Reverse).unwrap());
+        enabled.enable(CompressionEncoding::custom(Noop).unwrap());
+        enabled.enable(CompressionEncoding::custom(Reverse).unwrap());
+
+        assert_eq!(
+            enabled.to_accept_encoding_header_value().unwrap(),
+            "reverse,noop,identity"
+        );
+    }
+
+    #[test]
+    fn clones_share_encodings_until_changed() {
+        let mut enabled = EnabledCompressionEncodings::default();
+        enabled.enable(CompressionEncoding::custom(Noop).unwrap());
+
+        let mut clone = enabled.clone();
+        assert!(Arc::ptr_eq(&enabled.encodings, &clone.encodings));
+
+        clone.enable(CompressionEncoding::custom(Reverse).unwrap());
+        assert!(!enabled.is_enabled(CompressionEncoding::custom(Reverse).unwrap()));
+        assert!(clone.is_enabled(CompressionEncoding::custom(Reverse).unwrap()));
+    }
+
+    #[test]
+    fn accept_encoding_picks_peers_most_preferred_enabled_encoding() {
+        let mut enabled = EnabledCompressionEncodings::default();
+        enabled.enable(CompressionEncoding::custom(Reverse).unwrap());
+        enabled.enable(CompressionEncoding::custom(Noop).unwrap());
+
+        let picked = CompressionEncoding::from_accept_encoding_header(
+            &accept_encoding("unknown,noop,reverse,identity"),
+            &enabled,
+        );
+        assert_eq!(picked.unwrap().name(), "noop");
+
+        let picked = CompressionEncoding::from_accept_encoding_header(
+            &accept_encoding("unknown,identity"),
+            &enabled,
+        );
+        assert!(picked.is_none());
+    }
+
+    #[test]
+    fn is_accepted_by_peer_accept_encoding() {
+        let encoding = CompressionEncoding::custom(Noop).unwrap();
+        let header = accept_encoding("reverse, noop ,identity");
+
+        assert!(encoding.is_accepted_by(header.get(ACCEPT_ENCODING_HEADER)));
+        assert!(!CompressionEncoding::custom(Reverse)
+            .unwrap()
+            .is_accepted_by(None));
+        assert!(!encoding.is_accepted_by(accept_encoding("identity").get(ACCEPT_ENCODING_HEADER)));
+    }
+
+    #[test]
+    fn unsupported_encoding_lists_enabled_encodings() {
+        let mut enabled = EnabledCompressionEncodings::default();
+        enabled.enable(CompressionEncoding::custom(Noop).unwrap());
+
+        let mut map = http::HeaderMap::new();
+        map.insert(ENCODING_HEADER, http::HeaderValue::from_static("reverse"));
+
+        let status = CompressionEncoding::from_encoding_header(&map, &enabled).unwrap_err();
+        assert_eq!(status.code(), crate::Code::Unimplemented);
+        assert_eq!(
+            status.metadata().get(ACCEPT_ENCODING_HEADER).unwrap(),
+            "noop,identity"
+        );
+    }
+
+    #[test]
+    fn custom_round_trip() {
+        let encoding = CompressionEncoding::custom(Reverse).unwrap();
+        let data = b"hello world".to_vec();
+
+        let mut decompressed = BytesMut::from(&data[..]);
+        let mut compressed = BytesMut::new();
+        compress(
+            &encoding,
+            CompressionLevel::Default,
+            &mut decompressed,
+            &mut compressed,
+            data.len(),
+        )
+        .unwrap();
+        assert_eq!(&compressed[..], b"dlrow olleh");
+
+        let len = compressed.len();
+        let mut out = BytesMut::new();
+        decompress(&encoding, &mut compressed, &mut out, len).unwrap();
+        assert_eq!(&out[..], &data[..]);
+    }
+
+    #[cfg(all(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn accept_encoding_skips_encodings_that_are_not_enabled() {
+        let mut enabled = EnabledCompressionEncodings::default();
+        enabled.enable(CompressionEncoding::Gzip);
+
+        assert_eq!(
+            CompressionEncoding::from_accept_encoding_header(
+                &accept_encoding("zstd,gzip,identity"),
+                &enabled
+            ),
+            Some(CompressionEncoding::Gzip)
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        let data = vec![42u8; 4096];
+        let mut decompressed = BytesMut::from(&data[..]);
+        let mut compressed = BytesMut::new();
+        compress(
+            &CompressionEncoding::Zstd,
+            CompressionLevel::Default,
+            &mut decompressed,
+            &mut compressed,
+            data.len(),
+        )
+        .unwrap();
+        assert!(compressed.len() < data.len());
+
+        let len = compressed.len();
+        let mut out = BytesMut::new();
+        decompress(&CompressionEncoding::Zstd, &mut compressed, &mut out, len).unwrap();
+        assert_eq!(&out[..], &data[..]);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_level_affects_output() {
+        let data = b"tonic ".repeat(1024);
+
+        let compressed_len = |level| {
+            let mut decompressed = BytesMut::from(&data[..]);
+            let mut compressed = BytesMut::new();
+            compress(
+                &CompressionEncoding::Gzip,
+                level,
+                &mut decompressed,
+                &mut compressed,
+                data.len(),
+            )
+            .unwrap();
+            compressed.len()
+        };
+
+        assert!(compressed_len(CompressionLevel::Precise(0)) > data.len());
+        assert!(compressed_len(CompressionLevel::Best) < data.len());
+    }
+
+    #[test]
+    fn settings_level_defaults_per_encoding() {
+        let settings = CompressionSettings::new()
+            .level(
+                CompressionEncoding::custom(Noop).unwrap(),
+                CompressionLevel::Fastest,
+            )
+            .level(
+                CompressionEncoding::custom(Noop).unwrap(),
+                CompressionLevel::Best,
+            );
+
+        assert_eq!(
+            settings.level_for(&CompressionEncoding::custom(Noop).unwrap()),
+            CompressionLevel::Best
+        );
+        assert_eq!(
+            settings.level_for(&CompressionEncoding::custom(Reverse).unwrap()),
+            CompressionLevel::Default
+        );
+    }
+
+    #[test]
+    fn custom_encoding_names_are_validated() {
+        struct Named(&'static str);
+
+        impl Compressor for Named {
+            fn name(&self) -> &'static str {
+                self.0
+            }
+
+            fn compress(&self, _: &[u8], _: &mut BytesMut, _: CompressionLevel) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn decompress(&self, _: &[u8], _: &mut BytesMut) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        for name in [
+            "",
+            "identity",
+            "IDENTITY",
+            "a,b",
+            "with space",
+            "caf\u{e9}",
+            "new\nline",
+        ] {
+            assert!(
+                CompressionEncoding::custom(Named(name)).is_err(),
+                "{:?}",
+                name
+            );
+        }
+
+        let encoding = CompressionEncoding::custom(Named("x-snappy.v2")).unwrap();
+        assert_eq!(encoding.to_header_value(), "x-snappy.v2");
+    }
+}
@@ -40,10 +40,7 @@ impl<T> Unpin for Streaming<T> {}
 #[derive(Debug, Clone, Copy)]
 enum State {
     ReadHeader,
-    ReadBody {
-        compression: Option<CompressionEncoding>,
-        len: usize,
-    },
+    ReadBody { compressed: bool, len: usize },
     Error,
 }
 
@@ -144,12 +141,12 @@ impl StreamingInner {
                 return Ok(None);
             }
 
-            let compression_encoding = match self.buf.get_u8() {
-                0 => None,
+            let is_compressed = match self.buf.get_u8() {
+                0 => false,
                 1 => {
                     {
                         if self.encoding.is_some() {
-                            self.encoding
+                            true
                         } else {
                             // https://grpc.github.io/grpc/core/md_doc_compression.html
                             // An ill-constructed message with its Compressed-Flag bit set but lacking a grpc-encoding
@@ -190,19 +187,19 @@ impl StreamingInner {
             self.buf.reserve(len);
 
             self.state = State::ReadBody {
-                compression: compression_encoding,
+                compressed: is_compressed,
                 len,
             }
         }
 
-        if let State::ReadBody { len, compression } = self.state {
+        if let State::ReadBody { len, compressed } = self.state {
             // if we haven't read enough of the message then return and keep
             // reading
             if self.buf.remaining() < len || self.buf.len() < len {
                 return Ok(None);
             }
 
-            let decode_buf = if let Some(encoding) = compression {
+            let decode_buf = if let (true, Some(encoding)) = (compressed, &self.encoding) {
                 self.decompress_buf.clear();
 
                 if let Err(err) = decompress(encoding, &mut self.buf, &mut self.decompress_buf, len)
@@ -1,4 +1,7 @@
-use super::compression::{compress, CompressionEncoding, SingleMessageCompressionOverride};
+use super::compression::{
+    compress, CompressionEncoding, CompressionLevel, CompressionSettings,
+    SingleMessageCompressionOverride,
+};
 use super::{EncodeBuf, Encoder, DEFAULT_MAX_SEND_MESSAGE_SIZE, HEADER_SIZE};
 use crate::{Code, Status};
 use bytes::{BufMut, Bytes, BytesMut};
@@ -20,6 +23,7 @@ pub(crate) fn encode_server<T, U>(
     encoder: T,
     source: U,
     compression_encoding: Option<CompressionEncoding>,
+    compression_settings: &CompressionSettings,
     compression_override: SingleMessageCompressionOverride,
     max_message_size: Option<usize>,
 ) -> EncodeBody<impl Stream<Item = Result<Bytes, Status>>>
@@ -31,6 +35,7 @@ where
         encoder,
         source,
         compression_encoding,
+        compression_settings,
         compression_override,
         max_message_size,
     );
@@ -42,6 +47,7 @@ pub(crate) fn encode_client<T, U>(
     encoder: T,
     source: U,
     compression_encoding: Option<CompressionEncoding>,
+    compression_settings: &CompressionSettings,
     max_message_size: Option<usize>,
 ) -> EncodeBody<impl Stream<Item = Result<Bytes, Status>>>
 where
@@ -52,6 +58,7 @@ where
         encoder,
         source.map(Ok),
         compression_encoding,
+        compression_settings,
         SingleMessageCompressionOverride::default(),
         max_message_size,
     );
@@ -73,12 +80,21 @@ where
     #[pin]
     source: Fuse<U>,
     encoder: T,
-    compression_encoding: Option<CompressionEncoding>,
+    compression: Option<MessageCompression>,
     max_message_size: Option<usize>,
     buf: BytesMut,
     uncompression_buf: BytesMut,
 }
 
+/// How each message of an [`EncodedBytes`] stream gets compressed.
+#[derive(Debug)]
+struct MessageCompression {
+    encoding: CompressionEncoding,
+    level: CompressionLevel,
+    /// Messages smaller than this are sent uncompressed.
+    min_message_size: usize,
+}
+
 impl<T, U> EncodedBytes<T, U>
 where
     T: Encoder<Error = Status>,
@@ -88,19 +104,23 @@ where
         encoder: T,
         source: U,
         compression_encoding: Option<CompressionEncoding>,
+        compression_settings: &CompressionSettings,
         compression_override: SingleMessageCompressionOverride,
         max_message_size: Option<usize>,
     ) -> Self {
         let buf = BytesMut::with_capacity(BUFFER_SIZE);
 
-        let compression_encoding =
-            if compression_override == SingleMessageCompressionOverride::Disable {
-                None
-            } else {
-                compression_encoding
-            };
+        let compression = if compression_override == SingleMessageCompressionOverride::Disable {
+            None
+        } else {
+            compression_encoding.map(|encoding| MessageCompression {
+                level: compression_settings.level_for(&encoding),
+                min_message_size: compression_settings.get_min_message_size(),
+                encoding,
+            })
+        };
 
-        let uncompression_buf = if compression_encoding.is_some() {
+        let uncompression_buf = if compression.is_some() {
             BytesMut::with_capacity(BUFFER_SIZE)
         } else {
             BytesMut::new()
@@ -109,7 +129,7 @@ where
         return EncodedBytes {
             source: Fuse::new(source),
             encoder,
-            compression_encoding,
+            compression,
             max_message_size,
             buf,
             uncompression_buf,
@@ -128,7 +148,7 @@ where
         let EncodedBytesProj {
             mut source,
             encoder,
-            compression_encoding,
+            compression,
             max_message_size,
             buf,
             uncompression_buf,
@@ -150,7 +170,7 @@ where
                         encoder,
                         buf,
                         uncompression_buf,
-                        *compression_encoding,
+                        compression.as_ref(),
                         *max_message_size,
                         item,
                     ) {
@@ -173,7 +193,7 @@ fn encode_item<T>(
     encoder: &mut T,
     buf: &mut BytesMut,
     uncompression_buf: &mut BytesMut,
-    compression_encoding: Option<CompressionEncoding>,
+    compression: Option<&MessageCompression>,
     max_message_size: Option<usize>,
     item: T::Item,
 ) -> Result<(), Status>
@@ -187,7 +207,7 @@ where
         buf.advance_mut(HEADER_SIZE);
     }
 
-    if let Some(encoding) = compression_encoding {
+    let compressed = if let Some(compression) = compression {
         uncompression_buf.clear();
 
         encoder
@@ -196,20 +216,33 @@ where
 
         let uncompressed_len = uncompression_buf.len();
 
-        compress(encoding, uncompression_buf, buf, uncompressed_len)
+        if uncompressed_len < compression.min_message_size {
+            buf.extend_from_slice(uncompression_buf);
+            false
+        } else {
+            compress(
+                &compression.encoding,
+                compression.level,
+                uncompression_buf,
+                buf,
+                uncompressed_len,
+            )
             .map_err(|err| Status::internal(format!("Error compressing: {}", err)))?;
+            true
+        }
     } else {
         encoder
             .encode(item, &mut EncodeBuf::new(buf))
             .map_err(|err| Status::internal(format!("Error encoding: {}", err)))?;
-    }
+        false
+    };
 
     // now that we know length, we can write the header
-    finish_encoding(compression_encoding, max_message_size, &mut buf[offset..])
+    finish_encoding(compressed, max_message_size, &mut buf[offset..])
 }
 
 fn finish_encoding(
-    compression_encoding: Option<CompressionEncoding>,
+    compressed: bool,
     max_message_size: Option<usize>,
     buf: &mut [u8],
 ) -> Result<(), Status> {
@@ -232,7 +265,7 @@ fn finish_encoding(
     }
     {
         let mut buf = &mut buf[..HEADER_SIZE];
-        buf.put_u8(compression_encoding.is_some() as u8);
+        buf.put_u8(compressed as u8);
         buf.put_u32(len as u32);
     }
 
@@ -16,14 +16,17 @@ use std::io;
 pub(crate) use self::encode::{encode_client, encode_server};
 
 pub use self::buffer::{DecodeBuf, EncodeBuf};
-pub use self::compression::{CompressionEncoding, EnabledCompressionEncodings};
+pub use self::compression::{
+    CompressionEncoding, CompressionLevel, CompressionSettings, Compressor, CustomEncoding,
+    EnabledCompressionEncodings, InvalidEncodingName,
+};
 pub use self::decode::Streaming;
 #[cfg(feature = "prost")]
 #[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
 pub use self::prost::ProstCodec;
 
 // 5 bytes
-const HEADER_SIZE: usize =
+pub(crate) const HEADER_SIZE: usize =
     // compression flag
     std::mem::size_of::<u8>() +
     // data length
@@ -77,7 +77,9 @@ fn from_decode_error(error: prost::DecodeError) -> crate::Status {
 
 #[cfg(test)]
 mod tests {
-    use crate::codec::compression::SingleMessageCompressionOverride;
+    use crate::codec::compression::{
+        tests::Noop, CompressionEncoding, CompressionSettings, SingleMessageCompressionOverride,
+    };
     use crate::codec::{
         encode_server, DecodeBuf, Decoder, EncodeBuf, Encoder, Streaming, HEADER_SIZE,
     };
@@ -161,6 +163,7 @@ mod tests {
             encoder,
             source,
             None,
+            &CompressionSettings::default(),
             SingleMessageCompressionOverride::default(),
             None,
         );
@@ -185,6 +188,7 @@ mod tests {
             encoder,
             source,
             None,
+            &CompressionSettings::default(),
             SingleMessageCompressionOverride::default(),
             Some(MAX_MESSAGE_SIZE),
         );
@@ -219,6 +223,7 @@ mod tests {
             encoder,
             source,
             None,
+            &CompressionSettings::default(),
             SingleMessageCompressionOverride::default(),
             Some(usize::MAX),
         );
@@ -238,6 +243,36 @@ mod tests {
         assert!(body.is_end_stream());
     }
 
+    #[tokio::test]
+    async fn encode_skips_compression_below_min_message_size() {
+        let encoder = MockEncoder::default();
+
+        let messages = vec![Ok::<_, Status>(vec![0u8; 10]), Ok(vec![0u8; 100])];
+        let source = tokio_stream::iter(messages);
+
+        let body = encode_server(
+            encoder,
+            source,
+            Some(CompressionEncoding::custom(Noop).unwrap()),
+            &CompressionSettings::new().min_message_size(50),
+            SingleMessageCompressionOverride::default(),
+            None,
+        );
+
+        tokio::pin!(body);
+
+        let mut buf = BytesMut::new();
+        while let Some(data) = body.data().await {
+            buf.put(data.unwrap());
+        }
+
+        assert_eq!(buf.get_u8(), 0);
+        assert_eq!(buf.get_u32(), 10);
+        buf.advance(10);
+        assert_eq!(buf.get_u8(), 1);
+        assert_eq!(buf.get_u32(), 100);
+    }
+
     #[derive(Debug, Clone, Default)]
     struct MockEncoder;
 
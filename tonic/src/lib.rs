@@ -33,6 +33,10 @@
 //! - `gzip`: Enables compressing requests, responses, and streams.
 //! Depends on [flate2]. Not enabled by default.
 //! Replaces the `compression` flag from earlier versions of `tonic` (<= 0.7).
+//! - `zstd`: Enables compressing requests, responses, and streams.
+//! Depends on [zstd]. Not enabled by default.
+//! - `service-config`: Enables parsing gRPC service configs from JSON and applying them to
+//! channels. Depends on [serde] and [serde_json]. Not enabled by default.
 //!
 //! # Structure
 //!
@@ -76,6 +80,9 @@
 //! [`client`]: client/index.html
 //! [`transport`]: transport/index.html
 //! [flate2]: https://crates.io/crates/flate2
+//! [zstd]: https://crates.io/crates/zstd
+//! [serde]: https://crates.io/crates/serde
+//! [serde_json]: https://crates.io/crates/serde_json
 
 #![recursion_limit = "256"]
 #![allow(clippy::inconsistent_struct_constructor)]
@@ -1,3 +1,4 @@
+use crate::codec::compression::{CompressionEncoding, CompressionOverride};
 use crate::metadata::{MetadataMap, MetadataValue};
 #[cfg(all(feature = "transport", feature = "tls"))]
 use crate::transport::server::TlsConnectInfo;
@@ -17,6 +18,11 @@ pub struct Request<T> {
     extensions: Extensions,
 }
 
+/// Whether a request waits for the channel to be ready, set with [`Request::set_wait_for_ready`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "transport"), allow(dead_code))]
+pub(crate) struct WaitForReady(pub(crate) bool);
+
 /// Trait implemented by RPC request types.
 ///
 /// Types implementing this trait can be used as arguments to client RPC
@@ -325,6 +331,42 @@ impl<T> Request<T> {
             .insert(crate::metadata::GRPC_TIMEOUT_HEADER, value);
     }
 
+    /// Set the compression encoding used for this request's messages.
+    ///
+    /// This takes precedence over the encoding configured on the client with `send_compressed`.
+    /// Passing `None` sends this request uncompressed. The server must support the chosen
+    /// encoding, otherwise it will reject the request with `UNIMPLEMENTED`.
+    ///
+    /// ```rust
+    /// use tonic::Request;
+    ///
+    /// // Small requests aren't worth compressing.
+    /// let mut request = Request::new(());
+    /// request.set_compression(None);
+    /// ```
+    pub fn set_compression(&mut self, encoding: Option<CompressionEncoding>) {
+        self.extensions_mut().insert(CompressionOverride(encoding));
+    }
+
+    /// Set whether this request waits for the channel to be ready.
+    ///
+    /// By default a call fails right away with `UNAVAILABLE` while the channel fails to connect.
+    /// A wait-for-ready call instead waits until a connection is ready or its timeout expires,
+    /// failing with `DEADLINE_EXCEEDED`.
+    ///
+    /// This takes precedence over the `waitForReady` setting of the service config and the
+    /// default set with `Endpoint::wait_for_ready`.
+    ///
+    /// ```rust
+    /// use tonic::Request;
+    ///
+    /// let mut request = Request::new(());
+    /// request.set_wait_for_ready(true);
+    /// ```
+    pub fn set_wait_for_ready(&mut self, wait_for_ready: bool) {
+        self.extensions_mut().insert(WaitForReady(wait_for_ready));
+    }
+
     /// Returns a reference to the associated extensions.
     pub fn extensions(&self) -> &Extensions {
         &self.extensions
@@ -422,7 +464,7 @@ mod sealed {
     pub trait Sealed {}
 }
 
-fn duration_to_grpc_timeout(duration: Duration) -> String {
+pub(crate) fn duration_to_grpc_timeout(duration: Duration) -> String {
     fn try_format<T: Into<u128>>(
         duration: Duration,
         unit: char,
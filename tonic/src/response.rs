@@ -1,3 +1,4 @@
+use crate::codec::compression::{CompressionEncoding, CompressionOverride};
 use crate::{metadata::MetadataMap, Extensions};
 
 /// A gRPC response and metadata from an RPC call.
@@ -110,6 +111,18 @@ impl<T> Response<T> {
         &mut self.extensions
     }
 
+    /// Set the compression encoding used for this response.
+    ///
+    /// This takes precedence over the encodings configured on the server with `send_compressed`,
+    /// and applies to every message of the response, including response streams. Passing `None`
+    /// sends the response uncompressed.
+    ///
+    /// If the client didn't advertise support for `encoding` the server falls back to the
+    /// encoding it would have picked otherwise.
+    pub fn set_compression(&mut self, encoding: Option<CompressionEncoding>) {
+        self.extensions_mut().insert(CompressionOverride(encoding));
+    }
+
     /// Disable compression of the response body.
     ///
     /// This disables compression of the body of this response, even if compression is enabled on
@@ -118,8 +131,6 @@ impl<T> Response<T> {
     /// **Note**: This only has effect on responses to unary requests and responses to client to
     /// server streams. Response streams (server to client stream and bidirectional streams) will
     /// still be compressed according to the configuration of the server.
-    #[cfg(feature = "gzip")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
     pub fn disable_compression(&mut self) {
         self.extensions_mut()
             .insert(crate::codec::compression::SingleMessageCompressionOverride::Disable);
@@ -1,14 +1,16 @@
 use crate::codec::compression::{
-    CompressionEncoding, EnabledCompressionEncodings, SingleMessageCompressionOverride,
+    CompressionEncoding, CompressionOverride, CompressionSettings, EnabledCompressionEncodings,
+    SingleMessageCompressionOverride, ACCEPT_ENCODING_HEADER,
 };
 use crate::{
     body::BoxBody,
     codec::{encode_server, Codec, Streaming},
     server::{ClientStreamingService, ServerStreamingService, StreamingService, UnaryService},
+    service::interceptor::{MessageDecoder, MessageEncoder, MessageInterceptor},
     Code, Request, Status,
 };
 use http_body::Body;
-use std::fmt;
+use std::{fmt, sync::Arc};
 use tokio_stream::{Stream, StreamExt};
 
 macro_rules! t {
@@ -35,10 +37,14 @@ pub struct Grpc<T> {
     accept_compression_encodings: EnabledCompressionEncodings,
     /// Which compression encodings might the server use for responses.
     send_compression_encodings: EnabledCompressionEncodings,
+    /// How responses are compressed.
+    compression_settings: CompressionSettings,
     /// Limits the maximum size of a decoded message.
     max_decoding_message_size: Option<usize>,
     /// Limits the maximum size of an encoded message.
     max_encoding_message_size: Option<usize>,
+    /// Intercepts the messages received and sent.
+    message_interceptor: Option<Arc<dyn MessageInterceptor>>,
 }
 
 impl<T> Grpc<T>
@@ -51,8 +57,10 @@ where
             codec,
             accept_compression_encodings: EnabledCompressionEncodings::default(),
             send_compression_encodings: EnabledCompressionEncodings::default(),
+            compression_settings: CompressionSettings::default(),
             max_decoding_message_size: None,
             max_encoding_message_size: None,
+            message_interceptor: None,
         }
     }
 
@@ -119,6 +127,44 @@ where
         self
     }
 
+    /// Configure the compression level and minimum message size used for compressed responses.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a server generated by tonic-build:
+    ///
+    /// ```rust
+    /// # enum CompressionEncoding { Gzip }
+    /// # struct CompressionSettings;
+    /// # impl CompressionSettings {
+    /// #     fn new() -> Self { Self }
+    /// #     fn min_message_size(self, _: usize) -> Self { self }
+    /// # }
+    /// # struct Svc;
+    /// # struct ExampleServer<T>(T);
+    /// # impl<T> ExampleServer<T> {
+    /// #     fn new(svc: T) -> Self { Self(svc) }
+    /// #     fn send_compressed(self, _: CompressionEncoding) -> Self { self }
+    /// #     fn compression_settings(self, _: CompressionSettings) -> Self { self }
+    /// # }
+    /// # #[tonic::async_trait]
+    /// # trait Example {}
+    ///
+    /// #[tonic::async_trait]
+    /// impl Example for Svc {
+    ///     // ...
+    /// }
+    ///
+    /// // Only compress responses of at least 1KiB.
+    /// let service = ExampleServer::new(Svc)
+    ///     .send_compressed(CompressionEncoding::Gzip)
+    ///     .compression_settings(CompressionSettings::new().min_message_size(1024));
+    /// ```
+    pub fn compression_settings(mut self, settings: CompressionSettings) -> Self {
+        self.compression_settings = settings;
+        self
+    }
+
     /// Limits the maximum size of a decoded message.
     ///
     /// # Example
@@ -179,6 +225,44 @@ where
         self
     }
 
+    /// Intercept every message received by a call once it is decoded, and every message sent
+    /// before it is encoded.
+    ///
+    /// See [`MessageInterceptor`] for more details.
+    ///
+    /// # Example
+    ///
+    /// The most common way of using this is through a server generated by tonic-build:
+    ///
+    /// ```rust
+    /// # use tonic::service::MessageInterceptor;
+    /// # struct Svc;
+    /// # struct ExampleServer<T>(T);
+    /// # impl<T> ExampleServer<T> {
+    /// #     fn new(svc: T) -> Self { Self(svc) }
+    /// #     fn message_interceptor(self, _: impl MessageInterceptor) -> Self { self }
+    /// # }
+    /// # #[tonic::async_trait]
+    /// # trait Example {}
+    ///
+    /// #[tonic::async_trait]
+    /// impl Example for Svc {
+    ///     // ...
+    /// }
+    ///
+    /// struct Validate;
+    ///
+    /// impl MessageInterceptor for Validate {
+    ///     // ...
+    /// }
+    ///
+    /// let service = ExampleServer::new(Svc).message_interceptor(Validate);
+    /// ```
+    pub fn message_interceptor(mut self, interceptor: impl MessageInterceptor) -> Self {
+        self.message_interceptor = Some(Arc::new(interceptor));
+        self
+    }
+
     #[doc(hidden)]
     pub fn apply_compression_config(
         self,
@@ -187,18 +271,21 @@ where
     ) -> Self {
         let mut this = self;
 
-        for &encoding in CompressionEncoding::encodings() {
-            if accept_encodings.is_enabled(encoding) {
-                this = this.accept_compressed(encoding);
-            }
-            if send_encodings.is_enabled(encoding) {
-                this = this.send_compressed(encoding);
-            }
+        for encoding in accept_encodings.iter() {
+            this = this.accept_compressed(encoding.clone());
+        }
+        for encoding in send_encodings.iter() {
+            this = this.send_compressed(encoding.clone());
         }
 
         this
     }
 
+    #[doc(hidden)]
+    pub fn apply_compression_settings(self, settings: CompressionSettings) -> Self {
+        self.compression_settings(settings)
+    }
+
     #[doc(hidden)]
     pub fn apply_max_message_size_config(
         self,
@@ -217,6 +304,15 @@ where
         this
     }
 
+    #[doc(hidden)]
+    pub fn apply_message_interceptor(
+        mut self,
+        interceptor: Option<Arc<dyn MessageInterceptor>>,
+    ) -> Self {
+        self.message_interceptor = interceptor;
+        self
+    }
+
     /// Handle a single unary gRPC request.
     pub async fn unary<S, B>(
         &mut self,
@@ -230,8 +326,9 @@ where
     {
         let accept_encoding = CompressionEncoding::from_accept_encoding_header(
             req.headers(),
-            self.send_compression_encodings,
+            &self.send_compression_encodings,
         );
+        let peer_accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
 
         let request = match self.map_request_unary(req).await {
             Ok(r) => r,
@@ -250,6 +347,9 @@ where
             .await
             .map(|r| r.map(|m| tokio_stream::once(Ok(m))));
 
+        let accept_encoding =
+            response_encoding(&response, accept_encoding, peer_accept_encoding.as_ref());
+
         let compression_override = compression_override_from_response(&response);
 
         self.map_response(
@@ -274,8 +374,9 @@ where
     {
         let accept_encoding = CompressionEncoding::from_accept_encoding_header(
             req.headers(),
-            self.send_compression_encodings,
+            &self.send_compression_encodings,
         );
+        let peer_accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
 
         let request = match self.map_request_unary(req).await {
             Ok(r) => r,
@@ -291,6 +392,9 @@ where
 
         let response = service.call(request).await;
 
+        let accept_encoding =
+            response_encoding(&response, accept_encoding, peer_accept_encoding.as_ref());
+
         self.map_response(
             response,
             accept_encoding,
@@ -314,8 +418,9 @@ where
     {
         let accept_encoding = CompressionEncoding::from_accept_encoding_header(
             req.headers(),
-            self.send_compression_encodings,
+            &self.send_compression_encodings,
         );
+        let peer_accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
 
         let request = t!(self.map_request_streaming(req));
 
@@ -324,6 +429,9 @@ where
             .await
             .map(|r| r.map(|m| tokio_stream::once(Ok(m))));
 
+        let accept_encoding =
+            response_encoding(&response, accept_encoding, peer_accept_encoding.as_ref());
+
         let compression_override = compression_override_from_response(&response);
 
         self.map_response(
@@ -348,13 +456,17 @@ where
     {
         let accept_encoding = CompressionEncoding::from_accept_encoding_header(
             req.headers(),
-            self.send_compression_encodings,
+            &self.send_compression_encodings,
         );
+        let peer_accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
 
         let request = t!(self.map_request_streaming(req));
 
         let response = service.call(request).await;
 
+        let accept_encoding =
+            response_encoding(&response, accept_encoding, peer_accept_encoding.as_ref());
+
         self.map_response(
             response,
             accept_encoding,
@@ -376,7 +488,7 @@ where
         let (parts, body) = request.into_parts();
 
         let stream = Streaming::new_request(
-            self.codec.decoder(),
+            MessageDecoder::new(self.codec.decoder(), self.message_interceptor.clone()),
             body,
             request_compression_encoding,
             self.max_decoding_message_size,
@@ -410,7 +522,7 @@ where
 
         let request = request.map(|body| {
             Streaming::new_request(
-                self.codec.decoder(),
+                MessageDecoder::new(self.codec.decoder(), self.message_interceptor.clone()),
                 body,
                 encoding,
                 self.max_decoding_message_size,
@@ -443,18 +555,19 @@ where
             http::header::HeaderValue::from_static("application/grpc"),
         );
 
-        if let Some(encoding) = accept_encoding {
+        if let Some(encoding) = &accept_encoding {
             // Set the content encoding
             parts.headers.insert(
                 crate::codec::compression::ENCODING_HEADER,
-                encoding.into_header_value(),
+                encoding.to_header_value(),
             );
         }
 
         let body = encode_server(
-            self.codec.encoder(),
+            MessageEncoder::new(self.codec.encoder(), self.message_interceptor.clone()),
             body,
             accept_encoding,
+            &self.compression_settings,
             compression_override,
             max_message_size,
         );
@@ -468,7 +581,7 @@ where
     ) -> Result<Option<CompressionEncoding>, Status> {
         CompressionEncoding::from_encoding_header(
             request.headers(),
-            self.accept_compression_encodings,
+            &self.accept_compression_encodings,
         )
     }
 }
@@ -489,10 +602,35 @@ impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
             &self.send_compression_encodings,
         );
 
+        f.field("compression_settings", &self.compression_settings);
+
+        f.field("message_interceptor", &self.message_interceptor);
+
         f.finish()
     }
 }
 
+fn response_encoding<B, E>(
+    res: &Result<crate::Response<B>, E>,
+    negotiated: Option<CompressionEncoding>,
+    peer_accept_encoding: Option<&http::HeaderValue>,
+) -> Option<CompressionEncoding> {
+    let encoding_override = res
+        .as_ref()
+        .ok()
+        .and_then(|response| response.extensions().get::<CompressionOverride>());
+
+    match encoding_override {
+        Some(CompressionOverride(None)) => None,
+        Some(CompressionOverride(Some(encoding)))
+            if encoding.is_accepted_by(peer_accept_encoding) =>
+        {
+            Some(encoding.clone())
+        }
+        _ => negotiated,
+    }
+}
+
 fn compression_override_from_response<B, E>(
     res: &Result<crate::Response<B>, E>,
 ) -> SingleMessageCompressionOverride {
@@ -4,16 +4,20 @@
 
 use crate::{
     body::{boxed, BoxBody},
+    codec::{DecodeBuf, Decoder, EncodeBuf, Encoder},
+    metadata::MetadataMap,
     request::SanitizeHeaders,
-    Status,
+    Code, Status,
 };
 use bytes::Bytes;
 use pin_project::pin_project;
 use std::{
+    any::Any,
     fmt,
     future::Future,
     pin::Pin,
-    task::{Context, Poll},
+    sync::Arc,
+    task::{ready, Context, Poll},
 };
 use tower_layer::Layer;
 use tower_service::Service;
@@ -57,6 +61,55 @@ where
     }
 }
 
+/// An asynchronous gRPC interceptor.
+///
+/// Like an [`Interceptor`], it can add, remove or check items in the `MetadataMap` and the
+/// extensions of each request, or cancel it with a `Status`, but it does so in a future. This
+/// allows it to fetch a token, call another service or look up a cache without blocking.
+///
+/// Any function that satisfies the bound
+/// `FnMut(Request<()>) -> impl Future<Output = Result<Request<()>, Status>>` can be used as an
+/// `AsyncInterceptor`.
+///
+/// An asynchronous interceptor can be used on both the server and client side with the
+/// [`async_interceptor`] layer, with [`InterceptedService::new_async`] or through the `tonic-build`
+/// crate's generated `with_async_interceptor` constructors. The wrapped service has to be
+/// [`Clone`] since the request is only sent to it once the interceptor completes.
+///
+/// ```
+/// use tonic::{Request, Status};
+///
+/// async fn authorize(request: Request<()>) -> Result<Request<()>, Status> {
+///     // Check the token with an authorization service.
+///     match request.metadata().get("authorization") {
+///         Some(_) => Ok(request),
+///         None => Err(Status::unauthenticated("missing token")),
+///     }
+/// }
+///
+/// let layer = tonic::service::async_interceptor(authorize);
+/// # drop(layer);
+/// ```
+pub trait AsyncInterceptor {
+    /// The future intercepting the request.
+    type Future: Future<Output = Result<crate::Request<()>, Status>>;
+
+    /// Intercept a request before it is sent, optionally cancelling it.
+    fn call(&mut self, request: crate::Request<()>) -> Self::Future;
+}
+
+impl<F, U> AsyncInterceptor for F
+where
+    F: FnMut(crate::Request<()>) -> U,
+    U: Future<Output = Result<crate::Request<()>, Status>>,
+{
+    type Future = U;
+
+    fn call(&mut self, request: crate::Request<()>) -> Self::Future {
+        self(request)
+    }
+}
+
 /// Create a new interceptor layer.
 ///
 /// See [`Interceptor`] for more details.
@@ -87,6 +140,41 @@ where
     }
 }
 
+/// Create a new asynchronous interceptor layer.
+///
+/// See [`AsyncInterceptor`] for more details.
+pub fn async_interceptor<F>(f: F) -> AsyncInterceptorLayer<F>
+where
+    F: AsyncInterceptor,
+{
+    AsyncInterceptorLayer { f }
+}
+
+/// An asynchronous gRPC interceptor that can be used as a [`Layer`],
+/// created by calling [`async_interceptor`].
+///
+/// See [`AsyncInterceptor`] for more details.
+#[derive(Debug, Clone, Copy)]
+pub struct AsyncInterceptorLayer<F> {
+    f: F,
+}
+
+impl<S, F> Layer<S> for AsyncInterceptorLayer<F>
+where
+    F: AsyncInterceptor + Clone,
+{
+    type Service = InterceptedService<S, Async<F>>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        InterceptedService::new_async(service, self.f.clone())
+    }
+}
+
+/// An [`AsyncInterceptor`] used by an [`InterceptedService`],
+/// created by [`InterceptedService::new_async`].
+#[derive(Debug, Clone, Copy)]
+pub struct Async<F>(F);
+
 /// A service wrapped in an interceptor middleware.
 ///
 /// See [`Interceptor`] for more details.
@@ -107,6 +195,20 @@ impl<S, F> InterceptedService<S, F> {
     }
 }
 
+impl<S, F> InterceptedService<S, Async<F>> {
+    /// Create a new `InterceptedService` that wraps `S` and intercepts each request with the
+    /// asynchronous function `F`.
+    pub fn new_async(service: S, f: F) -> Self
+    where
+        F: AsyncInterceptor,
+    {
+        Self {
+            inner: service,
+            f: Async(f),
+        }
+    }
+}
+
 impl<S, F> fmt::Debug for InterceptedService<S, F>
 where
     S: fmt::Debug,
@@ -164,6 +266,54 @@ where
     }
 }
 
+impl<S, F, ReqBody, ResBody> Service<http::Request<ReqBody>> for InterceptedService<S, Async<F>>
+where
+    ResBody: Default + http_body::Body<Data = Bytes> + Send + 'static,
+    F: AsyncInterceptor,
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>> + Clone,
+    S::Error: Into<crate::Error>,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = AsyncResponseFuture<S, F::Future, ReqBody>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        // See `InterceptedService::call` for why the body is kept away from the interceptor. The
+        // service which was driven to readiness is taken along with the request until the
+        // interceptor completes, and replaced by a clone.
+        let uri = req.uri().clone();
+        let method = req.method().clone();
+        let version = req.version();
+        let req = crate::Request::from_http(req);
+        let (metadata, extensions, msg) = req.into_parts();
+
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+
+        AsyncResponseFuture {
+            kind: AsyncKind::Intercepting {
+                future: self
+                    .f
+                    .0
+                    .call(crate::Request::from_parts(metadata, extensions, ())),
+                pending: Some(PendingRequest {
+                    inner,
+                    msg,
+                    uri,
+                    method,
+                    version,
+                }),
+            },
+        }
+    }
+}
+
 // required to use `InterceptedService` with `Router`
 impl<S, F> crate::server::NamedService for InterceptedService<S, F>
 where
@@ -228,6 +378,478 @@ where
     }
 }
 
+/// Response future for [`InterceptedService`] with an [`AsyncInterceptor`].
+#[pin_project]
+pub struct AsyncResponseFuture<S, F, B>
+where
+    S: Service<http::Request<B>>,
+{
+    #[pin]
+    kind: AsyncKind<S, F, B>,
+}
+
+#[pin_project(project = AsyncKindProj)]
+enum AsyncKind<S, F, B>
+where
+    S: Service<http::Request<B>>,
+{
+    Intercepting {
+        #[pin]
+        future: F,
+        pending: Option<PendingRequest<S, B>>,
+    },
+    Calling(#[pin] ResponseFuture<S::Future>),
+}
+
+struct PendingRequest<S, B> {
+    inner: S,
+    msg: B,
+    uri: http::Uri,
+    method: http::Method,
+    version: http::Version,
+}
+
+impl<S, F, B> fmt::Debug for AsyncResponseFuture<S, F, B>
+where
+    S: Service<http::Request<B>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncResponseFuture").finish()
+    }
+}
+
+impl<S, F, B, ResBody> Future for AsyncResponseFuture<S, F, B>
+where
+    S: Service<http::Request<B>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    F: Future<Output = Result<crate::Request<()>, Status>>,
+    ResBody: Default + http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Output = Result<http::Response<BoxBody>, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let mut kind = self.as_mut().project().kind;
+            let calling = match kind.as_mut().project() {
+                AsyncKindProj::Intercepting { future, pending } => {
+                    let result = ready!(future.poll(cx));
+                    let PendingRequest {
+                        mut inner,
+                        msg,
+                        uri,
+                        method,
+                        version,
+                    } = pending.take().expect("polled after completion");
+
+                    match result {
+                        Ok(req) => {
+                            let (metadata, extensions, _) = req.into_parts();
+                            let req = crate::Request::from_parts(metadata, extensions, msg);
+                            let req = req.into_http(uri, method, version, SanitizeHeaders::No);
+                            ResponseFuture::future(inner.call(req))
+                        }
+                        Err(status) => ResponseFuture::status(status),
+                    }
+                }
+                AsyncKindProj::Calling(future) => return future.poll(cx),
+            };
+            kind.set(AsyncKind::Calling(calling));
+        }
+    }
+}
+
+/// A gRPC response interceptor.
+///
+/// Where an [`Interceptor`] only sees the requests, a response interceptor observes or modifies
+/// the responses of a service: their metadata, each set of trailers and the final `Status` of
+/// the call, whether it's sent in the trailers of a streaming response or in the headers of a
+/// response without a body. This is useful for audit logging, redacting errors or adding
+/// headers such as `server-timing`.
+///
+/// Every method has a default implementation leaving the response as is. The interceptor is
+/// cloned for each call, so it can keep state across the methods called for a response.
+///
+/// A response interceptor can be used on both the server and client side, for unary and
+/// streaming calls, with the [`response_interceptor`] layer.
+///
+/// ```
+/// use tonic::{service::ResponseInterceptor, Code, Status};
+///
+/// #[derive(Clone)]
+/// struct RedactInternalErrors;
+///
+/// impl ResponseInterceptor for RedactInternalErrors {
+///     fn on_status(&mut self, status: &mut Status) {
+///         if status.code() == Code::Internal {
+///             *status = Status::internal("internal error");
+///         }
+///     }
+/// }
+///
+/// let layer = tonic::service::response_interceptor(RedactInternalErrors);
+/// # drop(layer);
+/// ```
+pub trait ResponseInterceptor {
+    /// Intercept the metadata of a response before it is returned.
+    fn on_metadata(&mut self, _metadata: &mut MetadataMap) {}
+
+    /// Intercept a set of trailers of a response before it is returned.
+    fn on_trailers(&mut self, _trailers: &mut MetadataMap) {}
+
+    /// Intercept the final status of a call before it is returned, after the metadata or the
+    /// trailers it is sent in.
+    ///
+    /// A call that fails before any response, such as a client call whose connection fails or
+    /// times out, gets the status of its error, see [`Status::from_error`].
+    ///
+    /// Metadata added to the status is sent along with it.
+    fn on_status(&mut self, _status: &mut Status) {}
+}
+
+/// Create a new response interceptor layer.
+///
+/// See [`ResponseInterceptor`] for more details.
+pub fn response_interceptor<F>(f: F) -> ResponseInterceptorLayer<F>
+where
+    F: ResponseInterceptor,
+{
+    ResponseInterceptorLayer { f }
+}
+
+/// A gRPC response interceptor that can be used as a [`Layer`],
+/// created by calling [`response_interceptor`].
+///
+/// See [`ResponseInterceptor`] for more details.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseInterceptorLayer<F> {
+    f: F,
+}
+
+impl<S, F> Layer<S> for ResponseInterceptorLayer<F>
+where
+    F: ResponseInterceptor + Clone,
+{
+    type Service = ResponseInterceptedService<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        ResponseInterceptedService::new(service, self.f.clone())
+    }
+}
+
+/// A service whose responses are wrapped in a response interceptor middleware.
+///
+/// See [`ResponseInterceptor`] for more details.
+#[derive(Clone, Copy)]
+pub struct ResponseInterceptedService<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F> ResponseInterceptedService<S, F> {
+    /// Create a new `ResponseInterceptedService` that wraps `S` and intercepts each response
+    /// with a clone of `F`.
+    pub fn new(service: S, f: F) -> Self
+    where
+        F: ResponseInterceptor,
+    {
+        Self { inner: service, f }
+    }
+}
+
+impl<S, F> fmt::Debug for ResponseInterceptedService<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseInterceptedService")
+            .field("inner", &self.inner)
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, F, ReqBody, ResBody> Service<http::Request<ReqBody>> for ResponseInterceptedService<S, F>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    F: ResponseInterceptor + Clone,
+{
+    type Response = http::Response<InterceptedBody<ResBody, F>>;
+    type Error = crate::Error;
+    type Future = InterceptedResponseFuture<S::Future, F>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        InterceptedResponseFuture {
+            inner: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+// required to use `ResponseInterceptedService` with `Router`
+impl<S, F> crate::server::NamedService for ResponseInterceptedService<S, F>
+where
+    S: crate::server::NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+/// Response future for [`ResponseInterceptedService`].
+#[pin_project]
+#[derive(Debug)]
+pub struct InterceptedResponseFuture<Fut, F> {
+    #[pin]
+    inner: Fut,
+    f: Option<F>,
+}
+
+impl<Fut, F, B, E> Future for InterceptedResponseFuture<Fut, F>
+where
+    Fut: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<crate::Error>,
+    F: ResponseInterceptor,
+{
+    type Output = Result<http::Response<InterceptedBody<B, F>>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx));
+        let mut f = this.f.take().expect("polled after completion");
+
+        let res = match res {
+            Ok(res) => res,
+            Err(error) => {
+                let mut status = Status::from_error(error.into());
+                f.on_status(&mut status);
+                return Poll::Ready(Err(status.into()));
+            }
+        };
+
+        let (mut parts, body) = res.into_parts();
+        let mut metadata = MetadataMap::from_headers(std::mem::take(&mut parts.headers));
+        f.on_metadata(&mut metadata);
+        parts.headers = metadata.into_headers();
+
+        // A response without a body sends its status in the headers.
+        let has_status = intercept_status(&mut f, &mut parts.headers);
+
+        let body = InterceptedBody {
+            inner: body,
+            f,
+            has_status,
+        };
+        Poll::Ready(Ok(http::Response::from_parts(parts, body)))
+    }
+}
+
+/// Response body for [`ResponseInterceptedService`], intercepting its trailers.
+#[pin_project]
+#[derive(Debug)]
+pub struct InterceptedBody<B, F> {
+    #[pin]
+    inner: B,
+    f: F,
+    has_status: bool,
+}
+
+impl<B, F> http_body::Body for InterceptedBody<B, F>
+where
+    B: http_body::Body,
+    F: ResponseInterceptor,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx))?.map(|trailers| {
+            let mut trailers = MetadataMap::from_headers(trailers);
+            this.f.on_trailers(&mut trailers);
+            let mut trailers = trailers.into_headers();
+
+            if !*this.has_status {
+                *this.has_status = intercept_status(this.f, &mut trailers);
+            }
+            trailers
+        });
+
+        Poll::Ready(Ok(trailers))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Pass the status in `headers`, if any, to the interceptor and write it back, returning whether
+/// there was one.
+fn intercept_status<F>(f: &mut F, headers: &mut http::HeaderMap) -> bool
+where
+    F: ResponseInterceptor,
+{
+    let mut status = match Status::from_header_map(headers) {
+        Some(status) => status,
+        None => return false,
+    };
+    *headers = std::mem::take(status.metadata_mut()).into_headers();
+    f.on_status(&mut status);
+
+    if let Err(error) = status.add_header(headers) {
+        // The error is an `Internal` status explaining which header couldn't be written.
+        if error.add_header(headers).is_err() {
+            let code = http::HeaderValue::from(Code::Internal as i32);
+            headers.insert("grpc-status", code);
+        }
+    }
+    true
+}
+
+/// A gRPC message interceptor.
+///
+/// A message interceptor is called with every message a call receives, once it is decoded, and
+/// every message it sends, before it is encoded. It can observe or modify them, for example to
+/// count, measure or validate them, or end the stream with a `Status`.
+///
+/// The messages are passed as [`Any`] so that the same interceptor can be used for all methods
+/// of a service, and can be downcast to the message types of the service.
+///
+/// Like other errors encoding the requests of a client, an error returned for a message sent by
+/// a client resets the stream of the call, which then fails with an `Internal` status rather than
+/// the returned one.
+///
+/// A message interceptor can be used on both the server and client side, with
+/// [`server::Grpc::message_interceptor`](crate::server::Grpc::message_interceptor),
+/// [`client::Grpc::message_interceptor`](crate::client::Grpc::message_interceptor) or through
+/// the `message_interceptor` methods of the `tonic-build` crate's generated structs.
+///
+/// ```
+/// use std::{
+///     any::Any,
+///     sync::atomic::{AtomicUsize, Ordering},
+/// };
+/// use tonic::{service::MessageInterceptor, Status};
+///
+/// #[derive(Default)]
+/// struct CountMessages {
+///     received: AtomicUsize,
+///     sent: AtomicUsize,
+/// }
+///
+/// impl MessageInterceptor for CountMessages {
+///     fn on_inbound(&self, _message: &mut dyn Any) -> Result<(), Status> {
+///         self.received.fetch_add(1, Ordering::Relaxed);
+///         Ok(())
+///     }
+///
+///     fn on_outbound(&self, message: &mut dyn Any) -> Result<(), Status> {
+///         // Downcast to the message types of the service to inspect them.
+///         if let Some(message) = message.downcast_ref::<String>() {
+///             if message.is_empty() {
+///                 return Err(Status::internal("empty message"));
+///             }
+///         }
+///         self.sent.fetch_add(1, Ordering::Relaxed);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait MessageInterceptor: Send + Sync + 'static {
+    /// Intercept a message received by a call, after it is decoded, optionally ending the stream
+    /// with an error.
+    fn on_inbound(&self, _message: &mut dyn Any) -> Result<(), Status> {
+        Ok(())
+    }
+
+    /// Intercept a message sent by a call, before it is encoded, optionally ending the stream with
+    /// an error.
+    fn on_outbound(&self, _message: &mut dyn Any) -> Result<(), Status> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for dyn MessageInterceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageInterceptor").finish_non_exhaustive()
+    }
+}
+
+/// An [`Encoder`] passing each message to a [`MessageInterceptor`] before encoding it.
+pub(crate) struct MessageEncoder<E> {
+    inner: E,
+    interceptor: Option<Arc<dyn MessageInterceptor>>,
+}
+
+impl<E> MessageEncoder<E> {
+    pub(crate) fn new(inner: E, interceptor: Option<Arc<dyn MessageInterceptor>>) -> Self {
+        Self { inner, interceptor }
+    }
+}
+
+impl<E> Encoder for MessageEncoder<E>
+where
+    E: Encoder<Error = Status>,
+    E::Item: 'static,
+{
+    type Item = E::Item;
+    type Error = Status;
+
+    fn encode(&mut self, mut item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        if let Some(interceptor) = &self.interceptor {
+            interceptor.on_outbound(&mut item)?;
+        }
+        self.inner.encode(item, dst)
+    }
+}
+
+/// A [`Decoder`] passing each message to a [`MessageInterceptor`] once it is decoded.
+pub(crate) struct MessageDecoder<D> {
+    inner: D,
+    interceptor: Option<Arc<dyn MessageInterceptor>>,
+}
+
+impl<D> MessageDecoder<D> {
+    pub(crate) fn new(inner: D, interceptor: Option<Arc<dyn MessageInterceptor>>) -> Self {
+        Self { inner, interceptor }
+    }
+}
+
+impl<D> Decoder for MessageDecoder<D>
+where
+    D: Decoder<Error = Status>,
+    D::Item: 'static,
+{
+    type Item = D::Item;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Status> {
+        let mut item = self.inner.decode(src)?;
+        if let (Some(interceptor), Some(item)) = (&self.interceptor, &mut item) {
+            interceptor.on_inbound(item)?;
+        }
+        Ok(item)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[allow(unused_imports)]
@@ -333,4 +955,203 @@ mod tests {
 
         svc.oneshot(request).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn async_interceptor_modifies_requests() {
+        let svc = tower::service_fn(|request: http::Request<TestBody>| async move {
+            assert_eq!(
+                request
+                    .headers()
+                    .get("authorization")
+                    .expect("missing in leaf service"),
+                "Bearer token"
+            );
+
+            Ok::<_, Status>(http::Response::new(TestBody))
+        });
+
+        let svc = InterceptedService::new_async(svc, |mut request: crate::Request<()>| async {
+            tokio::task::yield_now().await;
+            let token = "Bearer token".parse().unwrap();
+            request.metadata_mut().insert("authorization", token);
+            Ok(request)
+        });
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        svc.oneshot(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_interceptor_handles_status_as_response() {
+        let message = "Blocked by the interceptor";
+        let expected = Status::permission_denied(message).to_http();
+
+        let svc = tower::service_fn(|_: http::Request<TestBody>| async {
+            Ok::<_, Status>(http::Response::new(TestBody))
+        });
+
+        let svc = async_interceptor(|_: crate::Request<()>| async {
+            Err(Status::permission_denied(message))
+        })
+        .layer(svc);
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        let response = svc.oneshot(request).await.unwrap();
+
+        assert_eq!(expected.status(), response.status());
+        assert_eq!(expected.headers(), response.headers());
+    }
+
+    #[derive(Clone, Default)]
+    struct Redact {
+        trailers: usize,
+    }
+
+    impl ResponseInterceptor for Redact {
+        fn on_metadata(&mut self, metadata: &mut MetadataMap) {
+            metadata.insert("server-timing", "total;dur=1".parse().unwrap());
+        }
+
+        fn on_trailers(&mut self, _trailers: &mut MetadataMap) {
+            self.trailers += 1;
+        }
+
+        fn on_status(&mut self, status: &mut Status) {
+            assert_eq!(status.code(), crate::Code::DataLoss);
+            assert_eq!(self.trailers, 1);
+            *status = Status::internal("redacted");
+            status
+                .metadata_mut()
+                .insert("x-redacted", "true".parse().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn response_interceptor_modifies_status_in_trailers() {
+        let svc = tower::service_fn(|_: http::Request<TestBody>| async {
+            let (mut tx, body) = hyper::Body::channel();
+            tokio::spawn(async move {
+                let mut trailers = Status::data_loss("secret").to_header_map().unwrap();
+                trailers.insert("x-trailer", "1".parse().unwrap());
+                tx.send_trailers(trailers).await.unwrap();
+            });
+            Ok::<_, Status>(http::Response::new(body))
+        });
+
+        let svc = response_interceptor(Redact::default()).layer(svc);
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        let response = svc.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()["server-timing"], "total;dur=1");
+
+        let trailers = hyper::body::HttpBody::trailers(&mut response.into_body())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(trailers["x-trailer"], "1");
+        assert_eq!(trailers["x-redacted"], "true");
+        let status = Status::from_header_map(&trailers).unwrap();
+        assert_eq!(status.code(), crate::Code::Internal);
+        assert_eq!(status.message(), "redacted");
+    }
+
+    #[tokio::test]
+    async fn response_interceptor_modifies_status_in_headers() {
+        let svc = tower::service_fn(|_: http::Request<TestBody>| async {
+            let mut response = Status::data_loss("secret").to_http();
+            response
+                .headers_mut()
+                .insert("x-trailer", "1".parse().unwrap());
+            Ok::<_, Status>(response)
+        });
+
+        let svc = ResponseInterceptedService::new(
+            svc,
+            Redact {
+                trailers: 1,
+                ..Redact::default()
+            },
+        );
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        let response = svc.oneshot(request).await.unwrap();
+
+        let headers = response.headers();
+        assert_eq!(headers["content-type"], "application/grpc");
+        assert_eq!(headers["server-timing"], "total;dur=1");
+        assert_eq!(headers["x-trailer"], "1");
+        assert_eq!(headers["x-redacted"], "true");
+        let status = Status::from_header_map(headers).unwrap();
+        assert_eq!(status.code(), crate::Code::Internal);
+        assert_eq!(status.message(), "redacted");
+    }
+
+    #[tokio::test]
+    async fn response_interceptor_sees_errors_without_response() {
+        #[derive(Debug, Clone, Default)]
+        struct Audit(Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl ResponseInterceptor for Audit {
+            fn on_status(&mut self, status: &mut Status) {
+                self.0.lock().unwrap().push(status.message().to_owned());
+                *status = Status::unavailable("redacted");
+            }
+        }
+
+        let svc = tower::service_fn(|_: http::Request<TestBody>| async {
+            Err::<http::Response<hyper::Body>, crate::Error>("connection refused".into())
+        });
+        let audit = Audit::default();
+        let svc = response_interceptor(audit.clone()).layer(svc);
+
+        let request = http::Request::builder().body(TestBody).unwrap();
+        let error = svc.oneshot(request).await.unwrap_err();
+        let status = Status::from_error(error);
+        assert_eq!(status.code(), crate::Code::Unavailable);
+        assert_eq!(status.message(), "redacted");
+        assert_eq!(*audit.0.lock().unwrap(), ["connection refused"]);
+    }
+
+    #[test]
+    #[cfg(feature = "prost")]
+    fn message_interceptor_modifies_and_rejects_messages() {
+        use crate::codec::{Codec, ProstCodec};
+        use bytes::BytesMut;
+
+        struct Shout;
+
+        impl MessageInterceptor for Shout {
+            fn on_inbound(&self, message: &mut dyn Any) -> Result<(), Status> {
+                message.downcast_mut::<String>().unwrap().push('!');
+                Ok(())
+            }
+
+            fn on_outbound(&self, message: &mut dyn Any) -> Result<(), Status> {
+                let message = message.downcast_mut::<String>().unwrap();
+                if message.is_empty() {
+                    return Err(Status::invalid_argument("empty message"));
+                }
+                *message = message.to_uppercase();
+                Ok(())
+            }
+        }
+
+        let interceptor: Arc<dyn MessageInterceptor> = Arc::new(Shout);
+        let mut codec = ProstCodec::<String, String>::default();
+        let mut encoder = MessageEncoder::new(codec.encoder(), Some(interceptor.clone()));
+        let mut decoder = MessageDecoder::new(codec.decoder(), Some(interceptor));
+
+        let mut buf = BytesMut::new();
+        encoder
+            .encode("hello".to_string(), &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        let len = buf.len();
+        let message = decoder.decode(&mut DecodeBuf::new(&mut buf, len)).unwrap();
+        assert_eq!(message.unwrap(), "HELLO!");
+
+        let status = encoder
+            .encode(String::new(), &mut EncodeBuf::new(&mut buf))
+            .unwrap_err();
+        assert_eq!(status.code(), crate::Code::InvalidArgument);
+    }
 }
@@ -3,4 +3,7 @@
 pub mod interceptor;
 
 #[doc(inline)]
-pub use self::interceptor::{interceptor, Interceptor};
+pub use self::interceptor::{
+    async_interceptor, interceptor, response_interceptor, AsyncInterceptor, Interceptor,
+    MessageInterceptor, ResponseInterceptor,
+};
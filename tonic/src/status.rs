@@ -325,6 +325,16 @@ impl Status {
         })
     }
 
+    /// The code of the `Status` [`Status::from_error`] would create from `err`.
+    #[cfg(feature = "transport")]
+    pub(crate) fn code_from_error(err: &(dyn Error + 'static)) -> Code {
+        if let Some(h2) = err.downcast_ref::<h2::Error>() {
+            return Self::code_from_h2(h2);
+        }
+
+        find_status_in_source_chain(err).map_or(Code::Unknown, |status| status.code)
+    }
+
     /// Create a `Status` from various types of `Error`.
     ///
     /// Returns the error if a status could not be created.
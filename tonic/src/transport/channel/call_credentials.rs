@@ -0,0 +1,466 @@
+use super::{Endpoint, ResponseBody};
+use crate::{
+    body::BoxBody,
+    metadata::{errors::InvalidMetadataValue, Ascii, MetadataMap, MetadataValue},
+    transport::BoxFuture,
+    Status,
+};
+use http::{Request, Response};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::Notify, time::Instant};
+use tower::Service;
+
+/// How long before a token expires [`RefreshingToken`] refreshes it by default.
+const DEFAULT_REFRESH_BEFORE: Duration = Duration::from_secs(60);
+
+/// A future resolving to the metadata that [`CallCredentials`] attach to a call.
+pub type MetadataFuture =
+    Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'static>>;
+
+/// Credentials attached to every call of a channel, such as an OAuth2 or JWT access token.
+///
+/// The metadata of the credentials is requested before each call is sent, so it may come from a
+/// cache refreshed in the background, see [`RefreshingToken`]. When the future fails, the call
+/// fails with its status without being sent.
+///
+/// Credentials are set with [`Endpoint::call_credentials`], and are only sent over TLS
+/// connections unless [`Endpoint::allow_insecure_call_credentials`] allows them over plaintext.
+///
+/// ```
+/// # use tonic::transport::channel::{CallCredentials, MetadataFuture};
+/// # use tonic::metadata::MetadataMap;
+/// /// Sends an API key to every service.
+/// struct ApiKey(&'static str);
+///
+/// impl CallCredentials for ApiKey {
+///     fn get_request_metadata(&self, _service_url: &str, _method: &str) -> MetadataFuture {
+///         let mut metadata = MetadataMap::new();
+///         metadata.insert("x-api-key", self.0.parse().unwrap());
+///         Box::pin(async move { Ok(metadata) })
+///     }
+/// }
+/// ```
+pub trait CallCredentials: Send + Sync + 'static {
+    /// Produce the metadata to attach to a call of `method` on the service at `service_url`.
+    ///
+    /// The service URL is the scheme and the authority of the channel followed by the fully
+    /// qualified name of the service, such as `https://example.com/helloworld.Greeter`, which
+    /// can serve as the audience of a JWT. The method is the name of the method, such as
+    /// `SayHello`.
+    fn get_request_metadata(&self, service_url: &str, method: &str) -> MetadataFuture;
+}
+
+impl fmt::Debug for dyn CallCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallCredentials").finish_non_exhaustive()
+    }
+}
+
+/// Credentials attaching the same bearer token to every call.
+///
+/// ```
+/// # use tonic::transport::{channel::StaticToken, Endpoint};
+/// let endpoint = Endpoint::from_static("https://example.com")
+///     .call_credentials(StaticToken::bearer("my-secret-token").unwrap());
+/// ```
+#[derive(Clone)]
+pub struct StaticToken {
+    value: MetadataValue<Ascii>,
+}
+
+impl StaticToken {
+    /// Create credentials sending `token` in an `authorization: Bearer` header.
+    ///
+    /// Fails when the token isn't a valid header value.
+    pub fn bearer(token: impl AsRef<str>) -> Result<Self, InvalidMetadataValue> {
+        Ok(StaticToken {
+            value: bearer_value(token.as_ref())?,
+        })
+    }
+}
+
+impl CallCredentials for StaticToken {
+    fn get_request_metadata(&self, _service_url: &str, _method: &str) -> MetadataFuture {
+        let metadata = authorization(self.value.clone());
+        Box::pin(async move { Ok(metadata) })
+    }
+}
+
+impl fmt::Debug for StaticToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticToken").finish_non_exhaustive()
+    }
+}
+
+/// A bearer token fetched by a [`RefreshingToken`], along with when it expires.
+#[derive(Clone)]
+pub struct Token {
+    value: MetadataValue<Ascii>,
+    expires_at: Option<Instant>,
+}
+
+impl Token {
+    /// Create a token sent in an `authorization: Bearer` header, which never expires.
+    ///
+    /// Fails when the token isn't a valid header value.
+    pub fn bearer(token: impl AsRef<str>) -> Result<Self, InvalidMetadataValue> {
+        Ok(Token {
+            value: bearer_value(token.as_ref())?,
+            expires_at: None,
+        })
+    }
+
+    /// Sets the token to expire once `duration` elapses, such as the `expires_in` of an OAuth2
+    /// token response.
+    pub fn expires_in(self, duration: Duration) -> Self {
+        Token {
+            expires_at: Some(Instant::now() + duration),
+            ..self
+        }
+    }
+
+    fn is_valid_at(&self, now: Instant) -> bool {
+        !matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+
+    fn expires_within(&self, now: Instant, duration: Duration) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now + duration)
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Credentials attaching a bearer token that expires, fetched again before it does.
+///
+/// The token is fetched when the first call is made, and calls wait for it. Once the token is
+/// about to expire, see [`RefreshingToken::refresh_before`], the next call starts to fetch a new
+/// token in the background and the calls keep using the current token until it is replaced. Calls
+/// only wait for the token when it expired. Only one fetch runs at a time, and the calls waiting
+/// for a fetch that fails fail with its status.
+///
+/// Clones share the same token.
+///
+/// ```
+/// # use tonic::transport::{channel::{RefreshingToken, Token}, Endpoint};
+/// # use tonic::Status;
+/// # use std::time::Duration;
+/// # async fn fetch_access_token() -> Result<(String, Duration), Status> { todo!() }
+/// let credentials = RefreshingToken::new(|| async {
+///     let (access_token, expires_in) = fetch_access_token().await?;
+///     let token = Token::bearer(access_token)
+///         .map_err(|_| Status::unauthenticated("invalid access token"))?;
+///     Ok(token.expires_in(expires_in))
+/// });
+///
+/// let endpoint = Endpoint::from_static("https://example.com").call_credentials(credentials);
+/// ```
+#[derive(Clone)]
+pub struct RefreshingToken {
+    inner: Arc<Refresher>,
+    refresh_before: Duration,
+}
+
+struct Refresher {
+    fetch: Box<dyn Fn() -> BoxFuture<'static, Result<Token, Status>> + Send + Sync>,
+    state: Mutex<RefreshState>,
+    /// Notified each time a fetch finishes.
+    refreshed: Notify,
+}
+
+#[derive(Default)]
+struct RefreshState {
+    token: Option<Token>,
+    fetching: bool,
+    /// The number of fetches that finished, so that the calls waiting for a fetch know when it
+    /// failed.
+    fetches: u64,
+    /// The error of the last fetch, `None` when it succeeded.
+    error: Option<Status>,
+}
+
+impl RefreshingToken {
+    /// Create credentials attaching the tokens `fetch` resolves to.
+    pub fn new<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Token, Status>> + Send + 'static,
+    {
+        RefreshingToken {
+            inner: Arc::new(Refresher {
+                fetch: Box::new(move || Box::pin(fetch())),
+                state: Mutex::new(RefreshState::default()),
+                refreshed: Notify::new(),
+            }),
+            refresh_before: DEFAULT_REFRESH_BEFORE,
+        }
+    }
+
+    /// Sets how long before the token expires a new token is fetched.
+    ///
+    /// Default is 60 seconds.
+    pub fn refresh_before(self, duration: Duration) -> Self {
+        RefreshingToken {
+            refresh_before: duration,
+            ..self
+        }
+    }
+}
+
+impl CallCredentials for RefreshingToken {
+    fn get_request_metadata(&self, _service_url: &str, _method: &str) -> MetadataFuture {
+        let (refresher, refresh_before) = (self.inner.clone(), self.refresh_before);
+        Box::pin(async move { refresher.token(refresh_before).await.map(authorization) })
+    }
+}
+
+impl fmt::Debug for RefreshingToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshingToken")
+            .field("refresh_before", &self.refresh_before)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Refresher {
+    /// The current token, fetching a new one when it is about to expire.
+    async fn token(
+        self: Arc<Self>,
+        refresh_before: Duration,
+    ) -> Result<MetadataValue<Ascii>, Status> {
+        let mut waited_for = None;
+
+        loop {
+            let refreshed = self.refreshed.notified();
+
+            {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+
+                if let Some(token) = &state.token {
+                    if token.is_valid_at(now) {
+                        let value = token.value.clone();
+                        if token.expires_within(now, refresh_before) {
+                            self.fetch(&mut state);
+                        }
+                        return Ok(value);
+                    }
+                }
+
+                if matches!(waited_for, Some(fetches) if state.fetches > fetches) {
+                    return Err(match &state.error {
+                        Some(error) => Status::new(error.code(), error.message()),
+                        None => Status::unauthenticated("the fetched token already expired"),
+                    });
+                }
+
+                waited_for = Some(state.fetches);
+                self.fetch(&mut state);
+            }
+
+            refreshed.await;
+        }
+    }
+
+    /// Fetch a new token in the background, unless a fetch is already running.
+    fn fetch(self: &Arc<Self>, state: &mut RefreshState) {
+        if state.fetching {
+            return;
+        }
+        state.fetching = true;
+
+        let refresher = self.clone();
+        let fetch = (self.fetch)();
+        tokio::spawn(async move {
+            let result = fetch.await;
+
+            let mut state = refresher.state.lock().unwrap();
+            state.fetching = false;
+            state.fetches += 1;
+            match result {
+                Ok(token) => {
+                    state.token = Some(token);
+                    state.error = None;
+                }
+                Err(error) => {
+                    tracing::debug!("failed to fetch the token: {}", error);
+                    state.error = Some(error);
+                }
+            }
+            drop(state);
+
+            refresher.refreshed.notify_waiters();
+        });
+    }
+}
+
+fn bearer_value(token: &str) -> Result<MetadataValue<Ascii>, InvalidMetadataValue> {
+    let mut value: MetadataValue<Ascii> = format!("Bearer {}", token).parse()?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+fn authorization(value: MetadataValue<Ascii>) -> MetadataMap {
+    let mut metadata = MetadataMap::new();
+    metadata.insert("authorization", value);
+    metadata
+}
+
+/// The call credentials of a channel, attached to each of its calls.
+#[derive(Debug, Clone)]
+pub(crate) struct AttachCredentials {
+    credentials: Arc<dyn CallCredentials>,
+    /// The scheme and the authority of the service URLs.
+    base_url: String,
+}
+
+impl AttachCredentials {
+    pub(crate) fn new(endpoint: &Endpoint) -> Option<Self> {
+        let credentials = endpoint.call_credentials.clone()?;
+        let origin = endpoint.origin.as_ref().unwrap_or(&endpoint.uri);
+        let scheme = if endpoint.uses_tls() { "https" } else { "http" };
+        let authority = origin
+            .authority()
+            .map_or("", |authority| authority.as_str());
+
+        Some(AttachCredentials {
+            credentials,
+            base_url: format!("{}://{}", scheme, authority),
+        })
+    }
+
+    /// Send `request` on `svc` once the metadata of the credentials is attached to it.
+    ///
+    /// The TLS requirement is enforced by the connections, which refuse every call when they
+    /// may not send the credentials of their endpoint.
+    pub(crate) fn call<S>(
+        &self,
+        mut request: Request<BoxBody>,
+        mut svc: S,
+    ) -> BoxFuture<'static, Result<Response<ResponseBody>, crate::Error>>
+    where
+        S: Service<Request<BoxBody>, Response = Response<ResponseBody>> + Send + 'static,
+        S::Error: Into<crate::Error>,
+        S::Future: Send,
+    {
+        let path = request.uri().path().trim_start_matches('/');
+        let (service, method) = path.rsplit_once('/').unwrap_or((path, ""));
+        let service_url = format!("{}/{}", self.base_url, service);
+        let metadata = self.credentials.get_request_metadata(&service_url, method);
+
+        Box::pin(async move {
+            let metadata = metadata.await?;
+            request
+                .headers_mut()
+                .extend(metadata.into_sanitized_headers());
+            svc.call(request).await.map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting(fetches: Arc<AtomicUsize>, expires_in: Duration) -> RefreshingToken {
+        RefreshingToken::new(move || {
+            let n = fetches.fetch_add(1, Ordering::SeqCst);
+            let token = Token::bearer(format!("token-{}", n)).unwrap();
+            async move { Ok(token.expires_in(expires_in)) }
+        })
+    }
+
+    async fn authorization_of(credentials: impl CallCredentials) -> Result<String, Status> {
+        let metadata = credentials
+            .get_request_metadata("https://example.com/test.Test", "Call")
+            .await?;
+        Ok(metadata
+            .get("authorization")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned())
+    }
+
+    #[tokio::test]
+    async fn static_token() {
+        let credentials = StaticToken::bearer("secret").unwrap();
+        assert_eq!(
+            authorization_of(credentials).await.unwrap(),
+            "Bearer secret"
+        );
+        assert!(StaticToken::bearer("new\nline").is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refreshes_before_expiry() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let credentials = counting(fetches.clone(), Duration::from_secs(120))
+            .refresh_before(Duration::from_secs(30));
+
+        let token = authorization_of(credentials.clone()).await.unwrap();
+        assert_eq!(token, "Bearer token-0");
+        tokio::time::advance(Duration::from_secs(60)).await;
+        let token = authorization_of(credentials.clone()).await.unwrap();
+        assert_eq!(token, "Bearer token-0");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // About to expire, so the call keeps the current token while a new one is fetched.
+        tokio::time::advance(Duration::from_secs(45)).await;
+        let token = authorization_of(credentials.clone()).await.unwrap();
+        assert_eq!(token, "Bearer token-0");
+        tokio::task::yield_now().await;
+        let token = authorization_of(credentials).await.unwrap();
+        assert_eq!(token, "Bearer token-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_a_single_fetch() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let credentials = counting(fetches.clone(), Duration::from_secs(120));
+
+        let calls: Vec<_> = (0..5)
+            .map(|_| tokio::spawn(authorization_of(credentials.clone())))
+            .collect();
+        for call in calls {
+            assert_eq!(call.await.unwrap().unwrap(), "Bearer token-0");
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        tokio::time::advance(Duration::from_secs(121)).await;
+        let token = authorization_of(credentials).await.unwrap();
+        assert_eq!(token, "Bearer token-1");
+    }
+
+    #[tokio::test]
+    async fn fetch_errors_fail_the_waiting_calls() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let credentials = RefreshingToken::new(move || {
+            let result = match fetches.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Status::unavailable("token server down")),
+                _ => Ok(Token::bearer("recovered").unwrap()),
+            };
+            async move { result }
+        });
+
+        let err = authorization_of(credentials.clone()).await.unwrap_err();
+        assert_eq!(err.code(), crate::Code::Unavailable);
+        assert_eq!(err.message(), "token server down");
+
+        let token = authorization_of(credentials).await.unwrap();
+        assert_eq!(token, "Bearer recovered");
+    }
+}
@@ -0,0 +1,36 @@
+use super::Channel;
+use std::pin::Pin;
+use tokio_stream::Stream;
+
+/// A stream of the health of a subchannel, yielding whether it serves calls.
+pub type HealthStream = Pin<Box<dyn Stream<Item = bool> + Send + 'static>>;
+
+/// Watches the health of the subchannels of a balanced [`Channel`].
+///
+/// Once a balanced channel connects to an endpoint with a health check, see
+/// [`Endpoint::health_check`](super::Endpoint::health_check), it watches the health of the
+/// subchannel with a channel sending its calls on the connection of the subchannel. The
+/// subchannel takes calls once the health check reports it serving, and none while it reports it
+/// not serving. Load balancing policies see a connected subchannel that isn't serving in the
+/// [`TransientFailure`](super::ConnectivityState::TransientFailure) state.
+///
+/// `tonic-health` implements a health check watching the `grpc.health.v1.Health` service.
+///
+/// ```
+/// # use tonic::transport::{channel::{HealthCheck, HealthStream}, Channel};
+/// /// Reports every subchannel as serving.
+/// struct AlwaysServing;
+///
+/// impl HealthCheck for AlwaysServing {
+///     fn watch(&self, _: Channel) -> HealthStream {
+///         Box::pin(tokio_stream::iter([true]))
+///     }
+/// }
+/// ```
+pub trait HealthCheck: Send + Sync + 'static {
+    /// Watch the health of the subchannel `channel` sends its calls on, yielding whether it is
+    /// serving each time its health changes.
+    ///
+    /// The subchannel keeps the last health reported once the stream ends.
+    fn watch(&self, channel: Channel) -> HealthStream;
+}
@@ -0,0 +1,497 @@
+use super::{ConnectivityState, ServiceConfig};
+use crate::{body::BoxBody, metadata::AsciiMetadataKey};
+use http::{HeaderMap, Request, Uri};
+use std::{cell::Cell, fmt, sync::Arc};
+
+/// Decides which endpoints of a balanced [`Channel`](super::Channel) it connects to and which
+/// endpoint each call is sent on.
+///
+/// The endpoints of the channel are called subchannels. Each time the channel needs a ready
+/// subchannel it calls [`connect`](LoadBalancingPolicy::connect), and connects the subchannels on
+/// which [`Subchannel::connect`] was called, calling `connect` again as long as the policy asks
+/// for new connections. Once at least one subchannel is ready, [`pick`](LoadBalancingPolicy::pick)
+/// chooses the one a call is sent on.
+///
+/// Subchannels are listed in the order their endpoints were added to the channel.
+///
+/// ```
+/// # use tonic::{body::BoxBody, transport::channel::{LoadBalancingPolicy, Subchannel}};
+/// # use http::Request;
+/// /// Sends every call on the ready subchannel that was added last.
+/// struct Newest;
+///
+/// impl LoadBalancingPolicy for Newest {
+///     fn pick(&mut self, _: &Request<BoxBody>, subchannels: &[Subchannel<'_>]) -> Option<usize> {
+///         subchannels.iter().rposition(Subchannel::is_ready)
+///     }
+/// }
+/// ```
+pub trait LoadBalancingPolicy: Send + 'static {
+    /// Ask for connections to the subchannels the policy wants to use.
+    ///
+    /// The default implementation connects every subchannel.
+    fn connect(&mut self, subchannels: &[Subchannel<'_>]) {
+        for subchannel in subchannels {
+            subchannel.connect();
+        }
+    }
+
+    /// Choose the index of the subchannel `request` is sent on.
+    ///
+    /// The subchannel must be ready. When the policy returns `None` or a subchannel that isn't
+    /// ready, the first ready subchannel is used.
+    fn pick(&mut self, request: &Request<BoxBody>, subchannels: &[Subchannel<'_>])
+        -> Option<usize>;
+
+    /// Observe the end of a call sent on `subchannel`, the subchannel the policy picked.
+    ///
+    /// The returned tracker receives the trailers of the call, which carry the load reports of
+    /// backends. The default implementation doesn't track calls.
+    fn track_call(&mut self, subchannel: &Subchannel<'_>) -> Option<Box<dyn CallTracker>> {
+        let _ = subchannel;
+        None
+    }
+}
+
+/// Receives the end of a call picked by a [`LoadBalancingPolicy`], see
+/// [`LoadBalancingPolicy::track_call`].
+pub trait CallTracker: Send + 'static {
+    /// Called once the call ends, with its trailers, or `None` when the call failed without
+    /// receiving any.
+    ///
+    /// The trailers of a call that fails right away are the headers of its response, and those of
+    /// a call dropped by the caller before its end carry a `Cancelled` status.
+    fn finish(self: Box<Self>, trailers: Option<&HeaderMap>);
+}
+
+impl LoadBalancingPolicy for Box<dyn LoadBalancingPolicy> {
+    fn connect(&mut self, subchannels: &[Subchannel<'_>]) {
+        (**self).connect(subchannels)
+    }
+
+    fn pick(
+        &mut self,
+        request: &Request<BoxBody>,
+        subchannels: &[Subchannel<'_>],
+    ) -> Option<usize> {
+        (**self).pick(request, subchannels)
+    }
+
+    fn track_call(&mut self, subchannel: &Subchannel<'_>) -> Option<Box<dyn CallTracker>> {
+        (**self).track_call(subchannel)
+    }
+}
+
+/// A view of one endpoint of a balanced channel, given to a [`LoadBalancingPolicy`].
+#[derive(Clone, Copy)]
+pub struct Subchannel<'a> {
+    pub(crate) uri: &'a Uri,
+    pub(crate) state: ConnectivityState,
+    pub(crate) is_ready: bool,
+    pub(crate) connect: &'a Cell<bool>,
+}
+
+impl<'a> Subchannel<'a> {
+    /// The URI of the endpoint.
+    pub fn uri(&self) -> &'a Uri {
+        self.uri
+    }
+
+    /// The connectivity state of the subchannel.
+    pub fn state(&self) -> ConnectivityState {
+        self.state
+    }
+
+    /// Whether the subchannel can send a call right away.
+    pub fn is_ready(&self) -> bool {
+        self.is_ready
+    }
+
+    /// Ask the channel to connect the subchannel, and keep it connected.
+    pub fn connect(&self) {
+        self.connect.set(true);
+    }
+}
+
+impl fmt::Debug for Subchannel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subchannel")
+            .field("uri", &self.uri)
+            .field("state", &self.state)
+            .field("is_ready", &self.is_ready)
+            .finish()
+    }
+}
+
+/// The gRPC `pick_first` policy: sends every call on the first subchannel that connects.
+///
+/// Subchannels are connected one at a time, in order. The policy moves on to the next subchannel
+/// when the current one fails to connect, and sticks to a subchannel for as long as it stays
+/// connected.
+#[derive(Debug, Clone, Default)]
+pub struct PickFirst {
+    _p: (),
+}
+
+impl PickFirst {
+    /// Create a `pick_first` policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LoadBalancingPolicy for PickFirst {
+    fn connect(&mut self, subchannels: &[Subchannel<'_>]) {
+        for subchannel in subchannels {
+            subchannel.connect();
+            if subchannel.state() != ConnectivityState::TransientFailure {
+                break;
+            }
+        }
+    }
+
+    fn pick(&mut self, _: &Request<BoxBody>, subchannels: &[Subchannel<'_>]) -> Option<usize> {
+        subchannels.iter().position(Subchannel::is_ready)
+    }
+}
+
+/// The gRPC `round_robin` policy: connects every subchannel and sends calls on the ready ones in
+/// turn.
+#[derive(Debug, Clone, Default)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+impl RoundRobin {
+    /// Create a `round_robin` policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LoadBalancingPolicy for RoundRobin {
+    fn pick(&mut self, _: &Request<BoxBody>, subchannels: &[Subchannel<'_>]) -> Option<usize> {
+        let len = subchannels.len();
+        let index = (0..len)
+            .map(|offset| (self.next + offset) % len)
+            .find(|index| subchannels[*index].is_ready())?;
+
+        self.next = index + 1;
+        Some(index)
+    }
+}
+
+/// The hash of a request for the [`RingHash`] policy.
+///
+/// An interceptor can insert it into the extensions of a request to choose the endpoint of the
+/// call, it takes precedence over the metadata hashed by the policy.
+///
+/// ```
+/// # use tonic::{transport::channel::RequestHash, Request, Status};
+/// fn by_tenant(mut request: Request<()>) -> Result<Request<()>, Status> {
+///     request.extensions_mut().insert(RequestHash(42));
+///     Ok(request)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestHash(pub u64);
+
+/// The gRPC `ring_hash` policy: sends the calls with the same hash to the same endpoint.
+///
+/// Each endpoint owns several points of a hash ring, and a call goes to the endpoint owning the
+/// first point at or after its hash, or to the next ready endpoint on the ring when that one is
+/// not ready. Adding or removing an endpoint only moves the calls whose hash falls next to its
+/// points.
+///
+/// The hash of a call is its [`RequestHash`] extension, else the hash of the metadata value set
+/// with [`RingHash::hash_metadata`]. Calls with neither go to a random endpoint.
+///
+/// ```
+/// # use tonic::transport::{channel::RingHash, Channel};
+/// # async fn f() {
+/// let policy = RingHash::new().hash_metadata("x-user-id".parse().unwrap());
+/// let (channel, tx) = Channel::balance_channel_with_policy::<String, _>(10, policy);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RingHash {
+    key: Option<AsciiMetadataKey>,
+    points_per_endpoint: usize,
+    /// The URIs of the subchannels the ring was built for.
+    uris: Vec<Uri>,
+    /// The points of the ring, sorted by hash, with the index of the subchannel owning them.
+    ring: Vec<(u64, usize)>,
+}
+
+const DEFAULT_POINTS_PER_ENDPOINT: usize = 256;
+
+impl RingHash {
+    /// Create a `ring_hash` policy hashing the [`RequestHash`] extension of calls.
+    pub fn new() -> Self {
+        RingHash {
+            key: None,
+            points_per_endpoint: DEFAULT_POINTS_PER_ENDPOINT,
+            uris: Vec::new(),
+            ring: Vec::new(),
+        }
+    }
+
+    /// Hash the value of the metadata `key` of calls without a [`RequestHash`] extension.
+    pub fn hash_metadata(self, key: AsciiMetadataKey) -> Self {
+        RingHash {
+            key: Some(key),
+            ..self
+        }
+    }
+
+    /// Set the number of points each endpoint owns on the ring.
+    ///
+    /// More points spread the calls more evenly over the endpoints. Default is 256.
+    pub fn points_per_endpoint(self, points: usize) -> Self {
+        RingHash {
+            points_per_endpoint: points.max(1),
+            ..self
+        }
+    }
+
+    fn update_ring(&mut self, subchannels: &[Subchannel<'_>]) {
+        if self.uris.len() == subchannels.len()
+            && self
+                .uris
+                .iter()
+                .zip(subchannels)
+                .all(|(uri, s)| uri == s.uri())
+        {
+            return;
+        }
+
+        self.uris = subchannels.iter().map(|s| s.uri().clone()).collect();
+        self.ring.clear();
+        for (index, uri) in self.uris.iter().enumerate() {
+            for point in 0..self.points_per_endpoint {
+                let hash = hash(format!("{}_{}", uri, point).as_bytes());
+                self.ring.push((hash, index));
+            }
+        }
+        self.ring.sort_unstable();
+    }
+
+    fn request_hash(&self, request: &Request<BoxBody>) -> u64 {
+        if let Some(RequestHash(hash)) = request.extensions().get() {
+            return *hash;
+        }
+
+        match self
+            .key
+            .as_ref()
+            .and_then(|key| request.headers().get(key.as_str()))
+        {
+            Some(value) => hash(value.as_bytes()),
+            None => rand::random(),
+        }
+    }
+}
+
+impl Default for RingHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoadBalancingPolicy for RingHash {
+    fn pick(
+        &mut self,
+        request: &Request<BoxBody>,
+        subchannels: &[Subchannel<'_>],
+    ) -> Option<usize> {
+        self.update_ring(subchannels);
+
+        let hash = self.request_hash(request);
+        let start = self.ring.partition_point(|(point, _)| *point < hash);
+        let len = self.ring.len();
+        (0..len)
+            .map(|offset| self.ring[(start + offset) % len].1)
+            .find(|index| subchannels[*index].is_ready())
+    }
+}
+
+/// A stable 64-bit hash: FNV-1a, with the finalizer of SplitMix64 to spread similar inputs.
+fn hash(bytes: &[u8]) -> u64 {
+    let mut hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Creates the policy of each channel an [`Endpoint`](super::Endpoint) connects.
+pub(crate) type PolicyFactory = Arc<dyn Fn() -> Box<dyn LoadBalancingPolicy> + Send + Sync>;
+
+/// The built-in policy named `name` in a service config.
+fn builtin_policy(name: &str) -> Option<Box<dyn LoadBalancingPolicy>> {
+    match name {
+        "pick_first" => Some(Box::new(PickFirst::new())),
+        "round_robin" => Some(Box::new(RoundRobin::new())),
+        _ => None,
+    }
+}
+
+/// Select the policy of a channel: the first built-in policy of the service config, else the
+/// policy of the endpoint, else `round_robin`.
+pub(crate) fn select_policy(
+    service_config: Option<&ServiceConfig>,
+    policy: Option<&PolicyFactory>,
+) -> Box<dyn LoadBalancingPolicy> {
+    service_config
+        .and_then(|config| config.load_balancing_policies().find_map(builtin_policy))
+        .or_else(|| policy.map(|policy| policy()))
+        .unwrap_or_else(|| Box::new(RoundRobin::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Subchannels {
+        uris: Vec<Uri>,
+        states: Vec<ConnectivityState>,
+        ready: Vec<bool>,
+        connect: Vec<Cell<bool>>,
+    }
+
+    impl Subchannels {
+        fn new(states: &[ConnectivityState]) -> Self {
+            Subchannels {
+                uris: (0..states.len())
+                    .map(|i| format!("http://10.0.0.{}", i).parse().unwrap())
+                    .collect(),
+                states: states.to_vec(),
+                ready: states
+                    .iter()
+                    .map(|state| *state == ConnectivityState::Ready)
+                    .collect(),
+                connect: states.iter().map(|_| Cell::new(false)).collect(),
+            }
+        }
+
+        fn views(&self) -> Vec<Subchannel<'_>> {
+            (0..self.uris.len())
+                .map(|i| Subchannel {
+                    uri: &self.uris[i],
+                    state: self.states[i],
+                    is_ready: self.ready[i],
+                    connect: &self.connect[i],
+                })
+                .collect()
+        }
+
+        fn connected(&self) -> Vec<bool> {
+            self.connect.iter().map(Cell::get).collect()
+        }
+    }
+
+    fn request() -> Request<BoxBody> {
+        Request::new(crate::body::empty_body())
+    }
+
+    #[test]
+    fn pick_first_fails_over_in_order() {
+        use ConnectivityState::*;
+
+        let subchannels = Subchannels::new(&[TransientFailure, Idle, Idle]);
+        PickFirst::new().connect(&subchannels.views());
+        assert_eq!(subchannels.connected(), [true, true, false]);
+
+        let subchannels = Subchannels::new(&[TransientFailure, Ready, Ready]);
+        let mut policy = PickFirst::new();
+        assert_eq!(policy.pick(&request(), &subchannels.views()), Some(1));
+        assert_eq!(policy.pick(&request(), &subchannels.views()), Some(1));
+    }
+
+    #[test]
+    fn round_robin_spreads_over_ready_subchannels() {
+        use ConnectivityState::*;
+
+        let subchannels = Subchannels::new(&[Ready, TransientFailure, Ready, Ready]);
+        let mut policy = RoundRobin::new();
+        policy.connect(&subchannels.views());
+        assert_eq!(subchannels.connected(), [true; 4]);
+
+        let picks: Vec<_> = (0..6)
+            .map(|_| policy.pick(&request(), &subchannels.views()).unwrap())
+            .collect();
+        assert_eq!(picks, [0, 2, 3, 0, 2, 3]);
+    }
+
+    fn request_with_user(user: &str) -> Request<BoxBody> {
+        let mut request = request();
+        request
+            .headers_mut()
+            .insert("x-user-id", user.parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn ring_hash_is_consistent() {
+        let mut policy = RingHash::new().hash_metadata(AsciiMetadataKey::from_static("x-user-id"));
+        let three = Subchannels::new(&[ConnectivityState::Ready; 3]);
+        let four = Subchannels::new(&[ConnectivityState::Ready; 4]);
+
+        let users: Vec<_> = (0..1000).map(|i| format!("user-{}", i)).collect();
+        let mut pick = |subchannels: &Subchannels, user: &str| {
+            policy
+                .pick(&request_with_user(user), &subchannels.views())
+                .unwrap()
+        };
+
+        let before: Vec<_> = users.iter().map(|user| pick(&three, user)).collect();
+        assert_eq!(
+            before,
+            users
+                .iter()
+                .map(|user| pick(&three, user))
+                .collect::<Vec<_>>()
+        );
+        for index in 0..3 {
+            let share = before.iter().filter(|i| **i == index).count();
+            assert!(share > 200, "subchannel {} got {} users", index, share);
+        }
+
+        let after: Vec<_> = users.iter().map(|user| pick(&four, user)).collect();
+        let moved: Vec<_> = before.iter().zip(&after).filter(|(b, a)| b != a).collect();
+        assert!(moved.len() < 400, "{} users moved", moved.len());
+        assert!(moved.iter().all(|(_, after)| **after == 3));
+    }
+
+    #[test]
+    fn ring_hash_falls_back_to_next_ready_subchannel() {
+        use ConnectivityState::*;
+
+        let mut policy = RingHash::new();
+        let ready = Subchannels::new(&[Ready, Ready, Ready]);
+        let mut request = request();
+        request.extensions_mut().insert(RequestHash(7));
+        let first = policy.pick(&request, &ready.views()).unwrap();
+
+        let mut states = [Ready; 3];
+        states[first] = TransientFailure;
+        let failing = Subchannels::new(&states);
+        let fallback = policy.pick(&request, &failing.views()).unwrap();
+        assert_ne!(fallback, first);
+        assert_eq!(policy.pick(&request, &failing.views()), Some(fallback));
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn service_config_selects_builtin_policy() {
+        let config: ServiceConfig =
+            r#"{ "loadBalancingConfig": [{ "unknown": {} }, { "pick_first": {} }] }"#
+                .parse()
+                .unwrap();
+        let subchannels = Subchannels::new(&[ConnectivityState::Idle, ConnectivityState::Idle]);
+
+        select_policy(Some(&config), None).connect(&subchannels.views());
+        assert_eq!(subchannels.connected(), [true, false]);
+    }
+}
@@ -1,17 +1,52 @@
 //! Client implementation and builder.
 
+mod call_credentials;
 mod endpoint;
+mod health_check;
+mod load_balancing;
+mod outlier_detection;
+mod resolver;
+mod response_body;
+mod retry;
+mod service_config;
+mod state;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 mod tls;
 
+pub(crate) use call_credentials::AttachCredentials;
+pub use call_credentials::{CallCredentials, MetadataFuture, RefreshingToken, StaticToken, Token};
 pub use endpoint::Endpoint;
+pub use health_check::{HealthCheck, HealthStream};
+pub use load_balancing::{
+    CallTracker, LoadBalancingPolicy, PickFirst, RequestHash, RingHash, RoundRobin, Subchannel,
+};
+pub use outlier_detection::{FailurePercentageEjection, OutlierDetection, SuccessRateEjection};
+pub(crate) use resolver::TargetResolver;
+pub use resolver::{Address, Resolver, Target};
+pub use response_body::ResponseBody;
+pub(crate) use retry::{CallPolicy, RetryConfig};
+pub use retry::{HedgingPolicy, RetryPolicy};
+#[cfg(not(feature = "service-config"))]
+pub(crate) use service_config::ServiceConfig;
+#[cfg(feature = "service-config")]
+#[cfg_attr(docsrs, doc(cfg(feature = "service-config")))]
+pub use service_config::{MethodConfig, ServiceConfig};
+pub use state::ConnectivityState;
+pub(crate) use state::{ChannelState, ConnectionState, ConnectionStateReader, OpenConnection};
 #[cfg(feature = "tls")]
 pub use tls::ClientTlsConfig;
 
-use super::service::{Connection, DynamicServiceStream, SharedExec};
+use super::service::{
+    self, grpc_timeout::try_parse_grpc_timeout, Balance, Connection, DynamicServiceStream, Retry,
+    SharedExec,
+};
+use super::TimeoutExpired;
 use crate::body::BoxBody;
-use crate::transport::Executor;
+use crate::client::MaxResponseMessageSize;
+use crate::request::WaitForReady;
+use crate::transport::{BoxFuture, Executor};
+use crate::Status;
 use bytes::Bytes;
 use http::{
     uri::{InvalidUri, Uri},
@@ -23,14 +58,19 @@ use std::{
     future::Future,
     hash::Hash,
     pin::Pin,
+    sync::Arc,
     task::{ready, Context, Poll},
+    time::Duration,
 };
 use tokio::{
     io::{AsyncRead, AsyncWrite},
-    sync::mpsc::{channel, Sender},
+    sync::{
+        mpsc::{channel, Sender},
+        watch,
+    },
+    time::{sleep, Instant, Sleep},
 };
 
-use tower::balance::p2c::Balance;
 use tower::{
     buffer::{self, Buffer},
     discover::{Change, Discover},
@@ -38,7 +78,15 @@ use tower::{
     Service,
 };
 
-type Svc = Either<Connection, BoxService<Request<BoxBody>, Response<hyper::Body>, crate::Error>>;
+type Svc = Either<Connection, BoxService<Request<BoxBody>, Response<ResponseBody>, crate::Error>>;
+
+type RetryFuture = service::retry::ResponseFuture<
+    buffer::future::ResponseFuture<<Svc as Service<Request<BoxBody>>>::Future>,
+>;
+
+/// The future of a call, which waits for the call credentials of the channel when it has any.
+type CallFuture =
+    Either<RetryFuture, BoxFuture<'static, Result<Response<ResponseBody>, crate::Error>>>;
 
 const DEFAULT_BUFFER_SIZE: usize = 1024;
 
@@ -67,14 +115,31 @@ const DEFAULT_BUFFER_SIZE: usize = 1024;
 /// cloning the `Channel` type is cheap and encouraged.
 #[derive(Clone)]
 pub struct Channel {
-    svc: Buffer<Svc, Request<BoxBody>>,
+    svc: Retry<Buffer<Svc, Request<BoxBody>>>,
+    service_config: Option<Arc<ServiceConfig>>,
+    state: Arc<ChannelState>,
+    /// The timeout of the endpoint.
+    timeout: Option<Duration>,
+    /// Whether calls wait for the channel to be ready by default.
+    wait_for_ready: bool,
+    call_credentials: Option<AttachCredentials>,
 }
 
 /// A future that resolves to an HTTP response.
 ///
 /// This is returned by the `Service::call` on [`Channel`].
 pub struct ResponseFuture {
-    inner: buffer::future::ResponseFuture<<Svc as Service<Request<BoxBody>>>::Future>,
+    /// The response of the call, `None` when the channel shut down before it was sent.
+    inner: Option<CallFuture>,
+    /// Expires when the timeout of the call elapses, even while it waits for the channel.
+    timeout: Option<Pin<Box<Sleep>>>,
+    /// Resolves when the channel is backing off, for calls that don't wait for it to be ready.
+    fail_fast: Option<BoxFuture<'static, ()>>,
+    /// The maximum size of the response messages set by the service config.
+    max_response_message_size: Option<usize>,
+    /// Whether the call fails with `DEADLINE_EXCEEDED` once its timeout expires, rather than
+    /// with the `CANCELLED` of the timeouts of the endpoint and of the request.
+    deadline_exceeded: bool,
 }
 
 impl Channel {
@@ -109,8 +174,60 @@ impl Channel {
     ///
     /// This creates a [`Channel`] that will load balance across all the
     /// provided endpoints.
+    ///
+    /// The retry and hedging policies, the service config, the timeout, the wait-for-ready default
+    /// and the call credentials of the first endpoint apply to the whole channel, so that further
+    /// attempts of a call can be sent to other endpoints. So does its load balancing policy, see
+    /// [`Endpoint::load_balancing_policy`]. The call credentials are shared by the clones of an
+    /// endpoint, and the endpoints with other call credentials are ignored.
     pub fn balance_list(list: impl Iterator<Item = Endpoint>) -> Self {
-        let (channel, tx) = Self::balance_channel(DEFAULT_BUFFER_SIZE);
+        let mut list = list.peekable();
+        let policy = match list.peek() {
+            Some(first) => load_balancing::select_policy(
+                first.service_config.as_deref(),
+                first.load_balancing_policy.as_ref(),
+            ),
+            None => load_balancing::select_policy(None, None),
+        };
+
+        Self::balance_list_with_policy(list, policy)
+    }
+
+    /// Balance a list of [`Endpoint`]'s following the given load balancing policy.
+    ///
+    /// ```
+    /// # use tonic::transport::{channel::PickFirst, Channel, Endpoint};
+    /// # async fn f() {
+    /// let endpoints = ["http://[::1]:50051", "http://[::1]:50052"]
+    ///     .iter()
+    ///     .map(|uri| Endpoint::from_static(uri));
+    /// let channel = Channel::balance_list_with_policy(endpoints, PickFirst::new());
+    /// # }
+    /// ```
+    pub fn balance_list_with_policy(
+        list: impl Iterator<Item = Endpoint>,
+        policy: impl LoadBalancingPolicy,
+    ) -> Self {
+        let mut list = list.peekable();
+        let first = list.peek().cloned();
+
+        let (tx, rx) = channel(DEFAULT_BUFFER_SIZE);
+        let state = ChannelState::new();
+        let mut discover = DynamicServiceStream::new(rx, state.clone());
+        if let Some(first) = &first {
+            discover = discover.with_call_credentials(first);
+        }
+        let mut channel = Self::balance(
+            discover,
+            policy,
+            state,
+            DEFAULT_BUFFER_SIZE,
+            SharedExec::tokio(),
+        );
+        if let Some(first) = first {
+            channel = channel.configure(first);
+        }
+
         list.for_each(|endpoint| {
             tx.try_send(Change::Insert(endpoint.uri.clone(), endpoint))
                 .unwrap();
@@ -119,9 +236,29 @@ impl Channel {
         channel
     }
 
+    /// Create an [`Endpoint`] that balances over the addresses of a gRPC [`Target`].
+    ///
+    /// See [`Endpoint::from_target`].
+    ///
+    /// ```
+    /// # use tonic::transport::Channel;
+    /// # async fn f() {
+    /// let channel = Channel::from_target("dns:///example.com:50051")
+    ///     .unwrap()
+    ///     .connect_lazy();
+    /// # }
+    /// ```
+    pub fn from_target(target: impl AsRef<str>) -> Result<Endpoint, super::Error> {
+        Endpoint::from_target(target)
+    }
+
     /// Balance a list of [`Endpoint`]'s.
     ///
     /// This creates a [`Channel`] that will listen to a stream of change events and will add or remove provided endpoints.
+    ///
+    /// Calls are spread over the endpoints with the [`RoundRobin`] policy. Calls aren't retried
+    /// and carry no call credentials, so the endpoints with call credentials are ignored, see
+    /// [`Endpoint::balance_channel`] to apply the settings of an endpoint to the channel.
     pub fn balance_channel<K>(capacity: usize) -> (Self, Sender<Change<K, Endpoint>>)
     where
         K: Hash + Eq + Send + Clone + 'static,
@@ -129,11 +266,41 @@ impl Channel {
         Self::balance_channel_with_executor(capacity, SharedExec::tokio())
     }
 
+    /// Balance a list of [`Endpoint`]'s following the given load balancing policy.
+    ///
+    /// This creates a [`Channel`] that will listen to a stream of change events and will add or remove provided endpoints.
+    ///
+    /// Calls aren't retried, see [`Endpoint::balance_channel`] to apply the settings of an
+    /// endpoint to the channel.
+    pub fn balance_channel_with_policy<K, P>(
+        capacity: usize,
+        policy: P,
+    ) -> (Self, Sender<Change<K, Endpoint>>)
+    where
+        K: Hash + Eq + Send + Clone + 'static,
+        P: LoadBalancingPolicy,
+    {
+        let (tx, rx) = channel(capacity);
+        let state = ChannelState::new();
+        let list = DynamicServiceStream::new(rx, state.clone());
+        (
+            Self::balance(
+                list,
+                policy,
+                state,
+                DEFAULT_BUFFER_SIZE,
+                SharedExec::tokio(),
+            ),
+            tx,
+        )
+    }
+
     /// Balance a list of [`Endpoint`]'s.
     ///
     /// This creates a [`Channel`] that will listen to a stream of change events and will add or remove provided endpoints.
     ///
-    /// The [`Channel`] will use the given executor to spawn async tasks.
+    /// The [`Channel`] will use the given executor to spawn async tasks. Calls aren't retried,
+    /// see [`Endpoint::balance_channel`] to apply the settings of an endpoint to the channel.
     pub fn balance_channel_with_executor<K, E>(
         capacity: usize,
         executor: E,
@@ -143,8 +310,107 @@ impl Channel {
         E: Executor<Pin<Box<dyn Future<Output = ()> + Send>>> + Send + Sync + 'static,
     {
         let (tx, rx) = channel(capacity);
-        let list = DynamicServiceStream::new(rx);
-        (Self::balance(list, DEFAULT_BUFFER_SIZE, executor), tx)
+        let state = ChannelState::new();
+        let list = DynamicServiceStream::new(rx, state.clone());
+        let policy = RoundRobin::new();
+        (
+            Self::balance(list, policy, state, DEFAULT_BUFFER_SIZE, executor),
+            tx,
+        )
+    }
+
+    /// Balance the endpoints sent through the returned sender with the settings of `endpoint`.
+    pub(crate) fn balance_channel_with_endpoint<K>(
+        capacity: usize,
+        endpoint: Endpoint,
+    ) -> (Self, Sender<Change<K, Endpoint>>)
+    where
+        K: Hash + Eq + Send + Clone + 'static,
+    {
+        let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+        let executor = endpoint.executor.clone();
+
+        let (tx, rx) = channel(capacity);
+        let state = ChannelState::new();
+        let list = DynamicServiceStream::new(rx, state.clone()).with_call_credentials(&endpoint);
+        let policy = load_balancing::select_policy(
+            endpoint.service_config.as_deref(),
+            endpoint.load_balancing_policy.as_ref(),
+        );
+        let channel = Self::balance(list, policy, state, buffer_size, executor).configure(endpoint);
+        (channel, tx)
+    }
+
+    /// Get the connectivity state of the channel.
+    ///
+    /// ```
+    /// # use tonic::transport::{Channel, ConnectivityState};
+    /// # async fn f() {
+    /// let channel = Channel::from_static("https://example.com").connect_lazy();
+    /// assert_eq!(channel.state(), ConnectivityState::Idle);
+    /// # }
+    /// ```
+    pub fn state(&self) -> ConnectivityState {
+        self.state.get()
+    }
+
+    /// Watch the connectivity state of the channel.
+    ///
+    /// The receiver is notified of each transition of the state. The state of a channel only
+    /// changes while the channel is used, so an idle channel stays `Idle` until it sends a call.
+    ///
+    /// ```
+    /// # use tonic::transport::{Channel, ConnectivityState};
+    /// # async fn f(channel: Channel) {
+    /// let mut state = channel.watch_state();
+    /// while state.changed().await.is_ok() {
+    ///     if *state.borrow() == ConnectivityState::TransientFailure {
+    ///         eprintln!("channel failed to connect");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn watch_state(&self) -> watch::Receiver<ConnectivityState> {
+        self.state.watch()
+    }
+
+    /// Shut down the channel, waiting for the calls running on it to finish.
+    ///
+    /// From now on, new calls on the channel and on its clones fail with `UNAVAILABLE`. The calls
+    /// already running finish on their connections, which then close with a `GOAWAY`. Returns
+    /// once every connection of the channel is closed.
+    ///
+    /// ```
+    /// # use tonic::transport::Channel;
+    /// # async fn f(channel: Channel) {
+    /// channel.shutdown().await;
+    /// # }
+    /// ```
+    pub async fn shutdown(&self) {
+        self.state.shutdown();
+        self.state.closed().await;
+    }
+
+    /// Shut down the channel like [`Channel::shutdown`], closing its connections once `timeout`
+    /// elapses even though calls still run on them, which then fail.
+    ///
+    /// ```
+    /// # use tonic::transport::Channel;
+    /// # use std::time::Duration;
+    /// # async fn f(channel: Channel) {
+    /// channel.shutdown_with_timeout(Duration::from_secs(10)).await;
+    /// # }
+    /// ```
+    pub async fn shutdown_with_timeout(&self, timeout: Duration) {
+        self.state.shutdown();
+        if tokio::time::timeout(timeout, self.state.closed())
+            .await
+            .is_err()
+        {
+            tracing::debug!("closing the connections of the channel after the shutdown timeout");
+            self.state.close_connections();
+            self.state.closed().await;
+        }
     }
 
     pub(crate) fn new<C>(connector: C, endpoint: Endpoint) -> Self
@@ -156,12 +422,25 @@ impl Channel {
     {
         let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
         let executor = endpoint.executor.clone();
-
-        let svc = Connection::lazy(connector, endpoint);
+        let retry = endpoint.retry.clone();
+        let service_config = endpoint.service_config.clone();
+        let timeout = endpoint.timeout;
+        let wait_for_ready = endpoint.wait_for_ready;
+        let call_credentials = AttachCredentials::new(&endpoint);
+
+        let state = ChannelState::new();
+        let svc = Connection::lazy(connector, endpoint, state.connection(true));
         let (svc, worker) = Buffer::pair(Either::A(svc), buffer_size);
         executor.execute(Box::pin(worker));
 
-        Channel { svc }
+        Channel {
+            svc: Retry::new(svc, retry),
+            service_config,
+            state,
+            timeout,
+            wait_for_ready,
+            call_credentials,
+        }
     }
 
     pub(crate) async fn connect<C>(connector: C, endpoint: Endpoint) -> Result<Self, super::Error>
@@ -173,55 +452,266 @@ impl Channel {
     {
         let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
         let executor = endpoint.executor.clone();
-
-        let svc = Connection::connect(connector, endpoint)
+        let retry = endpoint.retry.clone();
+        let service_config = endpoint.service_config.clone();
+        let timeout = endpoint.timeout;
+        let wait_for_ready = endpoint.wait_for_ready;
+        let call_credentials = AttachCredentials::new(&endpoint);
+
+        let state = ChannelState::new();
+        let svc = Connection::connect(connector, endpoint, state.connection(true))
             .await
             .map_err(super::Error::from_source)?;
         let (svc, worker) = Buffer::pair(Either::A(svc), buffer_size);
         executor.execute(Box::pin(worker));
 
-        Ok(Channel { svc })
+        Ok(Channel {
+            svc: Retry::new(svc, retry),
+            service_config,
+            state,
+            timeout,
+            wait_for_ready,
+            call_credentials,
+        })
     }
 
-    pub(crate) fn balance<D, E>(discover: D, buffer_size: usize, executor: E) -> Self
+    /// Create a channel balancing over the addresses of `target`, resolved again in the
+    /// background for as long as the channel is alive.
+    pub(crate) fn resolve(
+        endpoint: Endpoint,
+        target: TargetResolver,
+        addresses: Option<Vec<Address>>,
+    ) -> Self {
+        let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+        let executor = endpoint.executor.clone();
+
+        let (tx, rx) = channel(buffer_size);
+        let state = ChannelState::new();
+        let resolution =
+            resolver::resolve_target(endpoint.clone(), target, addresses, tx, state.clone());
+        executor.execute(Box::pin(resolution));
+
+        let list = DynamicServiceStream::new(rx, state.clone()).with_call_credentials(&endpoint);
+        let policy = load_balancing::select_policy(
+            endpoint.service_config.as_deref(),
+            endpoint.load_balancing_policy.as_ref(),
+        );
+        Self::balance(list, policy, state, buffer_size, executor).configure(endpoint)
+    }
+
+    /// Apply the retry and hedging policies, the service config, the timeout, the wait-for-ready
+    /// default and the call credentials of `endpoint` to every call of the channel.
+    fn configure(self, endpoint: Endpoint) -> Self {
+        Channel {
+            call_credentials: AttachCredentials::new(&endpoint),
+            svc: Retry::new(self.svc.into_inner(), endpoint.retry),
+            service_config: endpoint.service_config,
+            timeout: endpoint.timeout,
+            wait_for_ready: endpoint.wait_for_ready,
+            ..self
+        }
+    }
+
+    pub(crate) fn balance<D, P, E>(
+        discover: D,
+        policy: P,
+        state: Arc<ChannelState>,
+        buffer_size: usize,
+        executor: E,
+    ) -> Self
     where
         D: Discover<Service = Connection> + Unpin + Send + 'static,
         D::Error: Into<crate::Error>,
         D::Key: Hash + Send + Clone,
+        P: LoadBalancingPolicy,
         E: Executor<crate::transport::BoxFuture<'static, ()>> + Send + Sync + 'static,
     {
-        let svc = Balance::new(discover);
+        let executor = SharedExec::new(executor);
+        let svc = Balance::new(discover, policy, executor.clone());
 
         let svc = BoxService::new(svc);
         let (svc, worker) = Buffer::pair(Either::B(svc), buffer_size);
         executor.execute(Box::pin(worker));
 
-        Channel { svc }
+        Channel {
+            svc: Retry::new(svc, RetryConfig::default()),
+            service_config: None,
+            state,
+            timeout: None,
+            wait_for_ready: false,
+            call_credentials: None,
+        }
+    }
+
+    /// Create a channel sending its calls on `svc`, along with a handle to `svc` shared with the
+    /// channel.
+    pub(crate) fn share(
+        svc: BoxService<Request<BoxBody>, Response<ResponseBody>, crate::Error>,
+        executor: &SharedExec,
+    ) -> (Buffer<Svc, Request<BoxBody>>, Self) {
+        let (svc, worker) = Buffer::pair(Either::B(svc), DEFAULT_BUFFER_SIZE);
+        executor.execute(Box::pin(worker));
+
+        let channel = Channel {
+            svc: Retry::new(svc.clone(), RetryConfig::default()),
+            service_config: None,
+            state: ChannelState::new(),
+            timeout: None,
+            wait_for_ready: false,
+            call_credentials: None,
+        };
+        (svc, channel)
     }
 }
 
 impl Service<http::Request<BoxBody>> for Channel {
-    type Response = http::Response<super::Body>;
+    type Response = http::Response<ResponseBody>;
     type Error = super::Error;
     type Future = ResponseFuture;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Calls on a channel that shut down fail once they are made.
+        if self.state.get() == ConnectivityState::Shutdown {
+            return Poll::Ready(Ok(()));
+        }
+
         Service::poll_ready(&mut self.svc, cx).map_err(super::Error::from_source)
     }
 
-    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
-        let inner = Service::call(&mut self.svc, request);
-
-        ResponseFuture { inner }
+    fn call(&mut self, mut request: http::Request<BoxBody>) -> Self::Future {
+        if self.state.get() == ConnectivityState::Shutdown {
+            return ResponseFuture {
+                inner: None,
+                timeout: None,
+                fail_fast: None,
+                max_response_message_size: None,
+                deadline_exceeded: false,
+            };
+        }
+
+        let max_response_message_size = self
+            .service_config
+            .as_ref()
+            .and_then(|config| config.apply(&mut request));
+
+        let timeout = match try_parse_grpc_timeout(request.headers()) {
+            Ok(Some(deadline)) => Some(self.timeout.map_or(deadline, |t| t.min(deadline))),
+            _ => self.timeout,
+        };
+
+        let wait_for_ready = request
+            .extensions()
+            .get::<WaitForReady>()
+            .map(|wait_for_ready| wait_for_ready.0)
+            .or_else(|| {
+                self.service_config
+                    .as_ref()
+                    .and_then(|config| config.wait_for_ready(request.uri().path()))
+            })
+            .unwrap_or(self.wait_for_ready);
+
+        // Calls waiting for the channel and calls bounded by the service config fail with
+        // `DEADLINE_EXCEEDED` as gRPC specifies.
+        let deadline_exceeded = wait_for_ready
+            || self
+                .service_config
+                .as_ref()
+                .and_then(|config| config.timeout(request.uri().path()))
+                .is_some();
+
+        let fail_fast = if wait_for_ready {
+            None
+        } else {
+            let backoff = self.state.watch_backoff();
+            Some(Box::pin(backing_off(backoff)) as BoxFuture<'static, ()>)
+        };
+
+        let inner = match &self.call_credentials {
+            Some(credentials) => {
+                // The credentials are attached before the call is sent on the service that is
+                // ready, so a clone takes its place.
+                let svc = self.svc.clone();
+                let svc = std::mem::replace(&mut self.svc, svc);
+                Either::B(credentials.call(request, svc))
+            }
+            None => Either::A(Service::call(&mut self.svc, request)),
+        };
+
+        ResponseFuture {
+            inner: Some(inner),
+            timeout: timeout.map(|timeout| Box::pin(sleep(timeout))),
+            fail_fast,
+            max_response_message_size,
+            deadline_exceeded,
+        }
     }
 }
 
 impl Future for ResponseFuture {
-    type Output = Result<Response<hyper::Body>, super::Error>;
+    type Output = Result<Response<ResponseBody>, super::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let val = ready!(Pin::new(&mut self.inner).poll(cx)).map_err(super::Error::from_source)?;
-        Ok(val).into()
+        let inner = match &mut self.inner {
+            Some(inner) => inner,
+            None => {
+                let status = Status::unavailable("channel shut down");
+                return Poll::Ready(Err(super::Error::from_source(status)));
+            }
+        };
+        match Pin::new(inner).poll(cx) {
+            Poll::Ready(Ok(mut response)) => {
+                if let Some(limit) = self.max_response_message_size {
+                    response
+                        .extensions_mut()
+                        .insert(MaxResponseMessageSize(limit));
+                }
+                return Poll::Ready(Ok(response));
+            }
+            // The connection enforces the `grpc-timeout` of the call as well.
+            Poll::Ready(Err(err)) if self.deadline_exceeded && err.is::<TimeoutExpired>() => {
+                return Poll::Ready(Err(deadline_exceeded()));
+            }
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(super::Error::from_source(err))),
+            Poll::Pending => {}
+        }
+
+        if let Some(fail_fast) = &mut self.fail_fast {
+            if fail_fast.as_mut().poll(cx).is_ready() {
+                let status = Status::unavailable("failed to connect, waiting to reconnect");
+                return Poll::Ready(Err(super::Error::from_source(status)));
+            }
+        }
+
+        if let Some(timeout) = &mut self.timeout {
+            ready!(timeout.as_mut().poll(cx));
+            if self.deadline_exceeded {
+                return Poll::Ready(Err(deadline_exceeded()));
+            }
+            return Poll::Ready(Err(super::Error::from_source(TimeoutExpired::new())));
+        }
+
+        Poll::Pending
+    }
+}
+
+/// The error of a call whose deadline expired on the client.
+fn deadline_exceeded() -> super::Error {
+    let status = Status::deadline_exceeded(TimeoutExpired::new().to_string());
+    super::Error::from_source(status)
+}
+
+/// Resolve once every connection of the channel is backing off after a failed connection attempt.
+async fn backing_off(mut backoff: watch::Receiver<Option<Instant>>) {
+    loop {
+        let until = *backoff.borrow_and_update();
+        if matches!(until, Some(until) if Instant::now() < until) {
+            return;
+        }
+
+        if backoff.changed().await.is_err() {
+            // The channel is gone, so the call fails with its error.
+            return std::future::pending().await;
+        }
     }
 }
 
@@ -0,0 +1,755 @@
+use super::{CallTracker, ConnectivityState, LoadBalancingPolicy, Subchannel};
+use crate::{body::BoxBody, Code};
+use http::{HeaderMap, Request, Uri};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Ejects the endpoints of a balanced channel that fail more calls than the others, on top of
+/// another [`LoadBalancingPolicy`].
+///
+/// Follows the outlier detection of Envoy and gRPC. The outcome of each call is its gRPC status,
+/// known once its trailers are received, and calls failing without a status count as
+/// `Unavailable`. The status codes counted as failures are set with
+/// [`failure_codes`](OutlierDetection::failure_codes). An endpoint is ejected:
+///
+/// - right away, after [`consecutive_failures`](OutlierDetection::consecutive_failures) failed
+///   calls in a row,
+/// - every [`interval`](OutlierDetection::interval), when its success rate is an outlier among the
+///   success rates of the endpoints, see [`SuccessRateEjection`],
+/// - every interval, when its failure percentage is over a threshold, see
+///   [`FailurePercentageEjection`].
+///
+/// An ejected endpoint is hidden from the wrapped policy, which sees it in the
+/// [`TransientFailure`](ConnectivityState::TransientFailure) state, and is only used when no
+/// other endpoint is ready. It comes back after the base ejection time multiplied by the number
+/// of times it was ejected in a row, up to the maximum ejection time. No more than the maximum
+/// ejection percentage of the endpoints are ejected at once.
+///
+/// ```
+/// # use tonic::transport::{channel::{OutlierDetection, RoundRobin}, Endpoint};
+/// # use std::time::Duration;
+/// # let mut builder = Endpoint::from_static("https://example.com");
+/// builder.load_balancing_policy(
+///     OutlierDetection::new(RoundRobin::new()).base_ejection_time(Duration::from_secs(10)),
+/// );
+/// ```
+pub struct OutlierDetection<P> {
+    policy: P,
+    config: Arc<Config>,
+    endpoints: Arc<Mutex<Endpoints>>,
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    interval: Duration,
+    base_ejection_time: Duration,
+    max_ejection_time: Duration,
+    max_ejection_percent: u32,
+    consecutive_failures: Option<u32>,
+    success_rate: Option<SuccessRateEjection>,
+    failure_percentage: Option<FailurePercentageEjection>,
+    failure_codes: Vec<Code>,
+}
+
+/// Ejects the endpoints whose success rate is lower than the mean success rate of the endpoints
+/// by more than a number of standard deviations, see [`OutlierDetection`].
+#[derive(Debug, Clone)]
+pub struct SuccessRateEjection {
+    stdev_factor: f64,
+    enforcement_percentage: u32,
+    minimum_hosts: usize,
+    request_volume: u64,
+}
+
+impl SuccessRateEjection {
+    /// Create a success rate ejection with the default settings.
+    pub fn new() -> Self {
+        SuccessRateEjection {
+            stdev_factor: 1.9,
+            enforcement_percentage: 100,
+            minimum_hosts: 5,
+            request_volume: 100,
+        }
+    }
+
+    /// Set the number of standard deviations below the mean under which a success rate is an
+    /// outlier. Default is 1.9.
+    pub fn stdev_factor(self, factor: f64) -> Self {
+        SuccessRateEjection {
+            stdev_factor: factor,
+            ..self
+        }
+    }
+
+    /// Set the chance, as a percentage, that an outlier is ejected. Default is 100.
+    pub fn enforcement_percentage(self, percentage: u32) -> Self {
+        SuccessRateEjection {
+            enforcement_percentage: percentage,
+            ..self
+        }
+    }
+
+    /// Set the number of endpoints with enough calls in an interval needed to look for outliers.
+    /// Default is 5.
+    pub fn minimum_hosts(self, hosts: usize) -> Self {
+        SuccessRateEjection {
+            minimum_hosts: hosts,
+            ..self
+        }
+    }
+
+    /// Set the number of calls an endpoint needs in an interval for its success rate to count.
+    /// Default is 100.
+    pub fn request_volume(self, volume: u64) -> Self {
+        SuccessRateEjection {
+            request_volume: volume,
+            ..self
+        }
+    }
+}
+
+impl Default for SuccessRateEjection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Ejects the endpoints failing more than a percentage of their calls, see [`OutlierDetection`].
+#[derive(Debug, Clone)]
+pub struct FailurePercentageEjection {
+    threshold: u32,
+    enforcement_percentage: u32,
+    minimum_hosts: usize,
+    request_volume: u64,
+}
+
+impl FailurePercentageEjection {
+    /// Create a failure percentage ejection with the default settings.
+    pub fn new() -> Self {
+        FailurePercentageEjection {
+            threshold: 85,
+            enforcement_percentage: 100,
+            minimum_hosts: 5,
+            request_volume: 50,
+        }
+    }
+
+    /// Set the percentage of failed calls over which an endpoint is ejected. Default is 85.
+    pub fn threshold(self, percentage: u32) -> Self {
+        FailurePercentageEjection {
+            threshold: percentage,
+            ..self
+        }
+    }
+
+    /// Set the chance, as a percentage, that an endpoint over the threshold is ejected. Default
+    /// is 100.
+    pub fn enforcement_percentage(self, percentage: u32) -> Self {
+        FailurePercentageEjection {
+            enforcement_percentage: percentage,
+            ..self
+        }
+    }
+
+    /// Set the number of endpoints with enough calls in an interval needed to eject any.
+    /// Default is 5.
+    pub fn minimum_hosts(self, hosts: usize) -> Self {
+        FailurePercentageEjection {
+            minimum_hosts: hosts,
+            ..self
+        }
+    }
+
+    /// Set the number of calls an endpoint needs in an interval for its failure percentage to
+    /// count. Default is 50.
+    pub fn request_volume(self, volume: u64) -> Self {
+        FailurePercentageEjection {
+            request_volume: volume,
+            ..self
+        }
+    }
+}
+
+impl Default for FailurePercentageEjection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> OutlierDetection<P> {
+    /// Detect outliers among the endpoints `policy` balances over.
+    ///
+    /// By default, endpoints are ejected after 5 consecutive failures and by their success rate
+    /// every 10 seconds, for a base ejection time of 30 seconds and a maximum of 300 seconds, and
+    /// at most 50 percent of the endpoints are ejected.
+    pub fn new(policy: P) -> Self {
+        let config = Config {
+            interval: Duration::from_secs(10),
+            base_ejection_time: Duration::from_secs(30),
+            max_ejection_time: Duration::from_secs(300),
+            max_ejection_percent: 50,
+            consecutive_failures: Some(5),
+            success_rate: Some(SuccessRateEjection::new()),
+            failure_percentage: None,
+            failure_codes: vec![
+                Code::Unknown,
+                Code::DeadlineExceeded,
+                Code::ResourceExhausted,
+                Code::Internal,
+                Code::Unavailable,
+                Code::DataLoss,
+            ],
+        };
+
+        OutlierDetection {
+            policy,
+            config: Arc::new(config),
+            endpoints: Arc::default(),
+        }
+    }
+
+    /// Set how often the success rate and failure percentage of the endpoints are checked, and
+    /// ejected endpoints are brought back. Default is 10 seconds.
+    pub fn interval(self, interval: Duration) -> Self {
+        self.configure(|config| config.interval = interval)
+    }
+
+    /// Set the time an endpoint ejected for the first time stays ejected. Default is 30 seconds.
+    pub fn base_ejection_time(self, time: Duration) -> Self {
+        self.configure(|config| config.base_ejection_time = time)
+    }
+
+    /// Set the longest time an endpoint stays ejected, unless it is shorter than the base
+    /// ejection time. Default is 300 seconds.
+    pub fn max_ejection_time(self, time: Duration) -> Self {
+        self.configure(|config| config.max_ejection_time = time)
+    }
+
+    /// Set the largest percentage of the endpoints ejected at once. Default is 50.
+    pub fn max_ejection_percent(self, percentage: u32) -> Self {
+        self.configure(|config| config.max_ejection_percent = percentage)
+    }
+
+    /// Set the number of failed calls in a row after which an endpoint is ejected, or `None` to
+    /// not eject endpoints for consecutive failures. Default is 5.
+    pub fn consecutive_failures(self, failures: Option<u32>) -> Self {
+        self.configure(|config| config.consecutive_failures = failures)
+    }
+
+    /// Set the success rate ejection, or `None` to not eject endpoints by their success rate.
+    /// Default is [`SuccessRateEjection::new`].
+    pub fn success_rate_ejection(self, ejection: Option<SuccessRateEjection>) -> Self {
+        self.configure(|config| config.success_rate = ejection)
+    }
+
+    /// Set the failure percentage ejection, or `None` to not eject endpoints by their failure
+    /// percentage. Default is `None`.
+    pub fn failure_percentage_ejection(self, ejection: Option<FailurePercentageEjection>) -> Self {
+        self.configure(|config| config.failure_percentage = ejection)
+    }
+
+    /// Set the status codes of the calls counted as failures. Default is `Unknown`,
+    /// `DeadlineExceeded`, `ResourceExhausted`, `Internal`, `Unavailable` and `DataLoss`.
+    pub fn failure_codes(self, codes: impl IntoIterator<Item = Code>) -> Self {
+        let codes = codes.into_iter().collect();
+        self.configure(|config| config.failure_codes = codes)
+    }
+
+    fn configure(self, f: impl FnOnce(&mut Config)) -> Self {
+        let mut config = (*self.config).clone();
+        f(&mut config);
+        OutlierDetection {
+            config: Arc::new(config),
+            ..self
+        }
+    }
+
+    /// The views of `subchannels` the wrapped policy sees, with the ejected ones failing.
+    fn views<'a>(&self, subchannels: &[Subchannel<'a>]) -> Vec<Subchannel<'a>> {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        endpoints.update(subchannels.iter().map(Subchannel::uri));
+        endpoints.evaluate(&self.config, Instant::now());
+
+        subchannels
+            .iter()
+            .map(|subchannel| {
+                if endpoints.is_ejected(subchannel.uri()) {
+                    Subchannel {
+                        state: ConnectivityState::TransientFailure,
+                        is_ready: false,
+                        ..*subchannel
+                    }
+                } else {
+                    *subchannel
+                }
+            })
+            .collect()
+    }
+}
+
+impl<P: LoadBalancingPolicy> LoadBalancingPolicy for OutlierDetection<P> {
+    fn connect(&mut self, subchannels: &[Subchannel<'_>]) {
+        let views = self.views(subchannels);
+        self.policy.connect(&views);
+    }
+
+    fn pick(
+        &mut self,
+        request: &Request<BoxBody>,
+        subchannels: &[Subchannel<'_>],
+    ) -> Option<usize> {
+        let views = self.views(subchannels);
+        self.policy.pick(request, &views)
+    }
+
+    fn track_call(&mut self, subchannel: &Subchannel<'_>) -> Option<Box<dyn CallTracker>> {
+        Some(Box::new(OutcomeTracker {
+            uri: subchannel.uri().clone(),
+            config: self.config.clone(),
+            endpoints: self.endpoints.clone(),
+            inner: self.policy.track_call(subchannel),
+        }))
+    }
+}
+
+/// Clones start with no call recorded.
+impl<P: Clone> Clone for OutlierDetection<P> {
+    fn clone(&self) -> Self {
+        OutlierDetection {
+            policy: self.policy.clone(),
+            config: self.config.clone(),
+            endpoints: Arc::default(),
+        }
+    }
+}
+
+impl<P: fmt::Debug> fmt::Debug for OutlierDetection<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutlierDetection")
+            .field("policy", &self.policy)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Records the outcome of a call, and passes it on to the tracker of the wrapped policy.
+struct OutcomeTracker {
+    uri: Uri,
+    config: Arc<Config>,
+    endpoints: Arc<Mutex<Endpoints>>,
+    inner: Option<Box<dyn CallTracker>>,
+}
+
+impl CallTracker for OutcomeTracker {
+    fn finish(self: Box<Self>, trailers: Option<&HeaderMap>) {
+        let code = trailers
+            .and_then(|trailers| trailers.get("grpc-status"))
+            .map_or(Code::Unavailable, |status| {
+                Code::from_bytes(status.as_bytes())
+            });
+
+        self.endpoints
+            .lock()
+            .unwrap()
+            .record(&self.config, &self.uri, code, Instant::now());
+
+        if let Some(inner) = self.inner {
+            inner.finish(trailers);
+        }
+    }
+}
+
+/// The call outcomes and ejections of the endpoints of a channel.
+#[derive(Debug, Default)]
+struct Endpoints {
+    endpoints: HashMap<Uri, Endpoint>,
+    next_evaluation: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Endpoint {
+    /// The calls of the current interval, by status code.
+    calls: HashMap<Code, u64>,
+    /// The calls of the last interval, by status code.
+    last_calls: HashMap<Code, u64>,
+    consecutive_failures: u32,
+    ejected_at: Option<Instant>,
+    /// The number of times the endpoint was ejected in a row.
+    ejections: u32,
+}
+
+impl Endpoint {
+    fn volume(&self) -> u64 {
+        self.last_calls.values().sum()
+    }
+
+    fn failures(&self, config: &Config) -> u64 {
+        config
+            .failure_codes
+            .iter()
+            .filter_map(|code| self.last_calls.get(code))
+            .sum()
+    }
+
+    fn eject(&mut self, now: Instant) {
+        self.ejected_at = Some(now);
+        self.ejections += 1;
+        self.consecutive_failures = 0;
+    }
+}
+
+impl Endpoints {
+    /// Track the endpoints at `uris` and forget the others.
+    fn update<'a>(&mut self, uris: impl Iterator<Item = &'a Uri>) {
+        let uris: Vec<_> = uris.collect();
+        self.endpoints.retain(|uri, _| uris.contains(&uri));
+        for uri in uris {
+            self.endpoints.entry(uri.clone()).or_default();
+        }
+    }
+
+    fn is_ejected(&self, uri: &Uri) -> bool {
+        matches!(self.endpoints.get(uri), Some(endpoint) if endpoint.ejected_at.is_some())
+    }
+
+    /// Whether one more endpoint can be ejected without going over the maximum percentage.
+    fn can_eject(&self, config: &Config) -> bool {
+        let ejected = self
+            .endpoints
+            .values()
+            .filter(|endpoint| endpoint.ejected_at.is_some())
+            .count();
+        (ejected + 1) * 100 <= config.max_ejection_percent as usize * self.endpoints.len()
+    }
+
+    fn record(&mut self, config: &Config, uri: &Uri, code: Code, now: Instant) {
+        let can_eject = self.can_eject(config);
+        let endpoint = match self.endpoints.get_mut(uri) {
+            Some(endpoint) => endpoint,
+            None => return,
+        };
+
+        *endpoint.calls.entry(code).or_default() += 1;
+        if !config.failure_codes.contains(&code) {
+            endpoint.consecutive_failures = 0;
+            return;
+        }
+
+        endpoint.consecutive_failures += 1;
+        if matches!(config.consecutive_failures, Some(max) if endpoint.consecutive_failures >= max)
+            && endpoint.ejected_at.is_none()
+            && can_eject
+        {
+            debug!("ejecting {} after consecutive failures", uri);
+            endpoint.eject(now);
+        }
+    }
+
+    /// Eject the outliers of the last interval and bring back the endpoints ejected long enough,
+    /// once per interval.
+    fn evaluate(&mut self, config: &Config, now: Instant) {
+        match self.next_evaluation {
+            Some(next) if next > now => return,
+            Some(_) => {}
+            None => {
+                self.next_evaluation = Some(now + config.interval);
+                return;
+            }
+        }
+        self.next_evaluation = Some(now + config.interval);
+
+        for endpoint in self.endpoints.values_mut() {
+            endpoint.last_calls = std::mem::take(&mut endpoint.calls);
+        }
+
+        if let Some(ejection) = &config.success_rate {
+            self.eject_by_success_rate(config, ejection, now);
+        }
+        if let Some(ejection) = &config.failure_percentage {
+            self.eject_by_failure_percentage(config, ejection, now);
+        }
+
+        for (uri, endpoint) in &mut self.endpoints {
+            match endpoint.ejected_at {
+                Some(ejected_at) => {
+                    let ejection_time = (config.base_ejection_time * endpoint.ejections)
+                        .min(config.max_ejection_time.max(config.base_ejection_time));
+                    if now >= ejected_at + ejection_time {
+                        debug!("bringing back {}", uri);
+                        endpoint.ejected_at = None;
+                    }
+                }
+                None => endpoint.ejections = endpoint.ejections.saturating_sub(1),
+            }
+        }
+    }
+
+    fn eject_by_success_rate(
+        &mut self,
+        config: &Config,
+        ejection: &SuccessRateEjection,
+        now: Instant,
+    ) {
+        let rates: Vec<(Uri, f64)> = self
+            .endpoints
+            .iter()
+            .filter(|(_, endpoint)| endpoint.volume() >= ejection.request_volume.max(1))
+            .map(|(uri, endpoint)| {
+                let volume = endpoint.volume();
+                let successes = volume - endpoint.failures(config);
+                (uri.clone(), successes as f64 / volume as f64)
+            })
+            .collect();
+        if rates.is_empty() || rates.len() < ejection.minimum_hosts {
+            return;
+        }
+
+        let mean = rates.iter().map(|(_, rate)| rate).sum::<f64>() / rates.len() as f64;
+        let variance = rates
+            .iter()
+            .map(|(_, rate)| (rate - mean).powi(2))
+            .sum::<f64>()
+            / rates.len() as f64;
+        let threshold = mean - variance.sqrt() * ejection.stdev_factor;
+
+        for (uri, rate) in rates {
+            if rate < threshold {
+                debug!("ejecting {} for its success rate {}", uri, rate);
+                self.try_eject(config, &uri, ejection.enforcement_percentage, now);
+            }
+        }
+    }
+
+    fn eject_by_failure_percentage(
+        &mut self,
+        config: &Config,
+        ejection: &FailurePercentageEjection,
+        now: Instant,
+    ) {
+        let percentages: Vec<(Uri, u64)> = self
+            .endpoints
+            .iter()
+            .filter(|(_, endpoint)| endpoint.volume() >= ejection.request_volume.max(1))
+            .map(|(uri, endpoint)| {
+                (
+                    uri.clone(),
+                    endpoint.failures(config) * 100 / endpoint.volume(),
+                )
+            })
+            .collect();
+        if percentages.is_empty() || percentages.len() < ejection.minimum_hosts {
+            return;
+        }
+
+        for (uri, percentage) in percentages {
+            if percentage > u64::from(ejection.threshold) {
+                debug!("ejecting {} for its failure percentage {}", uri, percentage);
+                self.try_eject(config, &uri, ejection.enforcement_percentage, now);
+            }
+        }
+    }
+
+    fn try_eject(&mut self, config: &Config, uri: &Uri, enforcement_percentage: u32, now: Instant) {
+        if !self.can_eject(config) || rand::random::<u32>() % 100 >= enforcement_percentage {
+            return;
+        }
+        if let Some(endpoint) = self.endpoints.get_mut(uri) {
+            if endpoint.ejected_at.is_none() {
+                endpoint.eject(now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::channel::RoundRobin;
+    use std::cell::Cell;
+
+    fn uris(n: usize) -> Vec<Uri> {
+        (0..n)
+            .map(|i| format!("http://10.0.0.{}", i).parse().unwrap())
+            .collect()
+    }
+
+    fn trailers(code: Code) -> HeaderMap {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", (code as i32).into());
+        trailers
+    }
+
+    /// Send `calls` calls on the endpoint `index`, failing the calls for which `fail` is true.
+    fn send<P: LoadBalancingPolicy>(
+        policy: &mut OutlierDetection<P>,
+        uris: &[Uri],
+        index: usize,
+        calls: usize,
+        fail: impl Fn(usize) -> bool,
+    ) {
+        let connect: Vec<_> = uris.iter().map(|_| Cell::new(false)).collect();
+        for call in 0..calls {
+            let subchannel = Subchannel {
+                uri: &uris[index],
+                state: ConnectivityState::Ready,
+                is_ready: true,
+                connect: &connect[index],
+            };
+            let code = if fail(call) {
+                Code::Unavailable
+            } else {
+                Code::Ok
+            };
+            let tracker = policy.track_call(&subchannel).unwrap();
+            tracker.finish(Some(&trailers(code)));
+        }
+    }
+
+    fn ejected<P>(policy: &OutlierDetection<P>, uris: &[Uri]) -> Vec<bool> {
+        let endpoints = policy.endpoints.lock().unwrap();
+        uris.iter().map(|uri| endpoints.is_ejected(uri)).collect()
+    }
+
+    fn evaluate<P>(policy: &OutlierDetection<P>, now: Instant) {
+        policy
+            .endpoints
+            .lock()
+            .unwrap()
+            .evaluate(&policy.config, now);
+    }
+
+    fn track<P>(policy: &OutlierDetection<P>, uris: &[Uri]) {
+        policy.endpoints.lock().unwrap().update(uris.iter());
+    }
+
+    #[test]
+    fn ejects_after_consecutive_failures() {
+        let uris = uris(4);
+        let mut policy = OutlierDetection::new(RoundRobin::new()).consecutive_failures(Some(3));
+        track(&policy, &uris);
+
+        send(&mut policy, &uris, 0, 2, |_| true);
+        send(&mut policy, &uris, 0, 1, |_| false);
+        send(&mut policy, &uris, 0, 2, |_| true);
+        assert_eq!(ejected(&policy, &uris), [false, false, false, false]);
+
+        send(&mut policy, &uris, 0, 1, |_| true);
+        assert_eq!(ejected(&policy, &uris), [true, false, false, false]);
+    }
+
+    #[test]
+    fn only_counts_failure_codes() {
+        let uris = uris(2);
+        let mut policy = OutlierDetection::new(RoundRobin::new())
+            .consecutive_failures(Some(1))
+            .failure_codes([Code::Internal]);
+        track(&policy, &uris);
+
+        send(&mut policy, &uris, 0, 10, |_| true);
+        assert_eq!(ejected(&policy, &uris), [false, false]);
+    }
+
+    #[test]
+    fn ejects_success_rate_outliers_for_growing_times() {
+        let uris = uris(5);
+        let base = Duration::from_secs(30);
+        let mut policy = OutlierDetection::new(RoundRobin::new())
+            .consecutive_failures(None)
+            .max_ejection_percent(20);
+        track(&policy, &uris);
+
+        let start = Instant::now();
+        evaluate(&policy, start);
+        for index in 0..5 {
+            send(&mut policy, &uris, index, 100, |call| {
+                index == 0 && call % 2 == 0
+            });
+        }
+        evaluate(&policy, start + Duration::from_secs(10));
+        assert_eq!(ejected(&policy, &uris), [true, false, false, false, false]);
+
+        evaluate(&policy, start + Duration::from_secs(20) + base);
+        assert_eq!(ejected(&policy, &uris), [false; 5]);
+
+        // Ejected again right away, for twice as long.
+        let ejected_at = start + Duration::from_secs(30) + base;
+        for index in 0..5 {
+            send(&mut policy, &uris, index, 100, |call| {
+                index == 0 && call % 2 == 0
+            });
+        }
+        evaluate(&policy, ejected_at);
+        assert_eq!(ejected(&policy, &uris), [true, false, false, false, false]);
+        evaluate(&policy, ejected_at + base + Duration::from_secs(10));
+        assert_eq!(ejected(&policy, &uris), [true, false, false, false, false]);
+        evaluate(&policy, ejected_at + base * 2 + Duration::from_secs(20));
+        assert_eq!(ejected(&policy, &uris), [false; 5]);
+    }
+
+    #[test]
+    fn ejects_by_failure_percentage() {
+        let uris = uris(3);
+        let mut policy = OutlierDetection::new(RoundRobin::new())
+            .consecutive_failures(None)
+            .success_rate_ejection(None)
+            .failure_percentage_ejection(Some(
+                FailurePercentageEjection::new()
+                    .minimum_hosts(3)
+                    .request_volume(10),
+            ))
+            .max_ejection_percent(100);
+        track(&policy, &uris);
+
+        let start = Instant::now();
+        evaluate(&policy, start);
+        send(&mut policy, &uris, 0, 10, |_| true);
+        send(&mut policy, &uris, 1, 10, |call| call < 8);
+        send(&mut policy, &uris, 2, 10, |call| call < 9);
+        evaluate(&policy, start + Duration::from_secs(10));
+        assert_eq!(ejected(&policy, &uris), [true, false, true]);
+    }
+
+    #[test]
+    fn never_ejects_more_than_max_percent() {
+        let uris = uris(4);
+        let mut policy = OutlierDetection::new(RoundRobin::new())
+            .consecutive_failures(Some(1))
+            .max_ejection_percent(50);
+        track(&policy, &uris);
+
+        for index in 0..4 {
+            send(&mut policy, &uris, index, 1, |_| true);
+        }
+        assert_eq!(ejected(&policy, &uris), [true, true, false, false]);
+    }
+
+    #[test]
+    fn hides_ejected_subchannels_from_the_policy() {
+        let uris = uris(2);
+        let connect: Vec<_> = uris.iter().map(|_| Cell::new(false)).collect();
+        let views: Vec<_> = uris
+            .iter()
+            .zip(&connect)
+            .map(|(uri, connect)| Subchannel {
+                uri,
+                state: ConnectivityState::Ready,
+                is_ready: true,
+                connect,
+            })
+            .collect();
+        let mut policy = OutlierDetection::new(RoundRobin::new()).consecutive_failures(Some(1));
+        let request = || Request::new(crate::body::empty_body());
+
+        assert_eq!(policy.pick(&request(), &views), Some(0));
+        send(&mut policy, &uris, 0, 1, |_| true);
+        for _ in 0..4 {
+            assert_eq!(policy.pick(&request(), &views), Some(1));
+        }
+    }
+}
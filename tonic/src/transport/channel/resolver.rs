@@ -0,0 +1,620 @@
+use super::{ChannelState, Endpoint};
+use crate::transport::Error;
+use http::{uri::Scheme, Uri};
+use std::{
+    collections::HashSet,
+    fmt,
+    future::{poll_fn, Future},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::Sender,
+    time::{sleep, sleep_until, Instant},
+};
+use tower::discover::Change;
+use tracing::debug;
+
+const DEFAULT_PORT: u16 = 443;
+// Re-resolving after a connection failure, or after the resolution failed, waits at least this
+// long since the last resolution.
+const MIN_RERESOLUTION_DELAY: Duration = Duration::from_secs(1);
+
+/// Resolves the [`Target`] of a [`Channel`](super::Channel) to the addresses it balances over.
+///
+/// The channel resolves its target when it is created, again every
+/// [`resolution_interval`](Endpoint::resolution_interval), and whenever one of its connections
+/// fails to connect. Addresses that appear in a resolution are connected to and addresses that
+/// disappear are dropped.
+///
+/// ```
+/// # use tonic::transport::{channel::{Address, Resolver, Target}, Endpoint};
+/// # use std::{future::Future, pin::Pin};
+/// struct Fixed(Vec<Address>);
+///
+/// impl Resolver for Fixed {
+///     fn resolve(
+///         &self,
+///         _target: &Target,
+///     ) -> Pin<Box<dyn Future<Output = Result<Vec<Address>, tonic::transport::Error>> + Send>> {
+///         let addresses = self.0.clone();
+///         Box::pin(async move { Ok(addresses) })
+///     }
+/// }
+///
+/// let resolver = Fixed(vec![Address::Tcp("127.0.0.1:50051".parse().unwrap())]);
+/// let endpoint = Endpoint::from_target_with_resolver("fixed:///backends", resolver).unwrap();
+/// ```
+pub trait Resolver: Send + Sync + 'static {
+    /// Resolve `target` to the addresses it currently has.
+    fn resolve(
+        &self,
+        target: &Target,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Address>, Error>> + Send>>;
+}
+
+/// An address a [`Resolver`] resolves a target to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Address {
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Tcp(addr) => addr.fmt(f),
+            Address::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// The name of the service a [`Channel`](super::Channel) connects to, following the gRPC
+/// [name syntax].
+///
+/// A target is either of the form `scheme://authority/endpoint`, where the authority may be
+/// empty, or of the form `scheme:endpoint`. Strings that have neither form, or whose scheme isn't
+/// one of `dns`, `ipv4`, `ipv6` or `unix`, use the `dns` scheme with the whole string as the
+/// endpoint, so that `example.com:50051` is the same target as `dns:///example.com:50051`.
+///
+/// ```
+/// # use tonic::transport::channel::Target;
+/// let target: Target = "dns:///example.com:50051".parse().unwrap();
+/// assert_eq!(target.scheme(), "dns");
+/// assert_eq!(target.authority(), Some(""));
+/// assert_eq!(target.endpoint(), "example.com:50051");
+///
+/// let target: Target = "ipv4:10.0.0.1:50051,10.0.0.2:50051".parse().unwrap();
+/// assert_eq!(target.authority(), None);
+/// assert_eq!(target.endpoint(), "10.0.0.1:50051,10.0.0.2:50051");
+/// ```
+///
+/// [name syntax]: https://github.com/grpc/grpc/blob/master/doc/naming.md
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Target {
+    scheme: String,
+    authority: Option<String>,
+    endpoint: String,
+}
+
+impl Target {
+    /// The scheme of the target, which selects how it is resolved.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// The authority of the target, `None` when the target is of the form `scheme:endpoint`.
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// The endpoint of the target, without the slash that follows the authority.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn dns(endpoint: &str) -> Self {
+        Target {
+            scheme: "dns".to_owned(),
+            authority: None,
+            endpoint: endpoint.to_owned(),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(Error::new_invalid_target("the target is empty"));
+        }
+
+        let (scheme, rest) = match s.split_once(':') {
+            Some((scheme, rest)) if is_scheme(scheme) => (scheme, rest),
+            _ => return Ok(Target::dns(s)),
+        };
+
+        if let Some(rest) = rest.strip_prefix("//") {
+            let (authority, endpoint) = rest.split_once('/').unwrap_or((rest, ""));
+            return Ok(Target {
+                scheme: scheme.to_ascii_lowercase(),
+                authority: Some(authority.to_owned()),
+                endpoint: endpoint.to_owned(),
+            });
+        }
+
+        let scheme = scheme.to_ascii_lowercase();
+        if !matches!(scheme.as_str(), "dns" | "ipv4" | "ipv6" | "unix") {
+            return Ok(Target::dns(s));
+        }
+
+        Ok(Target {
+            scheme,
+            authority: None,
+            endpoint: rest.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.authority {
+            Some(authority) => write!(f, "{}://{}/{}", self.scheme, authority, self.endpoint),
+            None => write!(f, "{}:{}", self.scheme, self.endpoint),
+        }
+    }
+}
+
+fn is_scheme(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// The target of an [`Endpoint`] and the resolver of its addresses.
+#[derive(Clone)]
+pub(crate) struct TargetResolver {
+    pub(crate) target: Target,
+    pub(crate) resolver: Arc<dyn Resolver>,
+}
+
+impl TargetResolver {
+    /// Select the built-in resolver of `target`, returning it with the URI the endpoint uses as
+    /// its origin.
+    pub(crate) fn builtin(target: Target) -> Result<(Self, Uri), Error> {
+        let (resolver, authority): (Arc<dyn Resolver>, String) = match target.scheme() {
+            "dns" => {
+                if !target.authority().unwrap_or_default().is_empty() {
+                    return Err(Error::new_invalid_target(
+                        "DNS targets with an authority are not supported",
+                    ));
+                }
+
+                let (host, port) = split_host_port(target.endpoint())?;
+                let authority = match host.parse::<Ipv6Addr>() {
+                    Ok(_) => format!("[{}]:{}", host, port),
+                    Err(_) => format!("{}:{}", host, port),
+                };
+                (Arc::new(Dns { host, port }), authority)
+            }
+            "ipv4" | "ipv6" => {
+                let addresses = parse_ip_addresses(&target)?;
+                let authority = addresses[0].to_string();
+                (Arc::new(Static(addresses)), authority)
+            }
+            "unix" => {
+                let path = match target.authority() {
+                    Some("") => format!("/{}", target.endpoint()),
+                    Some(_) => {
+                        return Err(Error::new_invalid_target(
+                            "unix targets with an authority are not supported",
+                        ))
+                    }
+                    None => target.endpoint().to_owned(),
+                };
+                if path.is_empty() {
+                    return Err(Error::new_invalid_target("the socket path is empty"));
+                }
+
+                let addresses = vec![Address::Unix(path.into())];
+                (Arc::new(Static(addresses)), "localhost".to_owned())
+            }
+            scheme => {
+                return Err(Error::new_invalid_target(format!(
+                    "no resolver for the `{}` scheme",
+                    scheme
+                )))
+            }
+        };
+
+        let uri = format!("http://{}", authority)
+            .parse()
+            .map_err(|_| Error::new_invalid_target("the target isn't a valid authority"))?;
+
+        Ok((TargetResolver { target, resolver }, uri))
+    }
+
+    pub(crate) async fn resolve(&self) -> Result<Vec<Address>, Error> {
+        self.resolver.resolve(&self.target).await
+    }
+}
+
+/// Split `host:port`, `[v6]:port` or a bare host.
+fn split_host_port(s: &str) -> Result<(String, u16), Error> {
+    let parse_port = |port: &str| {
+        port.parse::<u16>()
+            .map_err(|_| Error::new_invalid_target(format!("invalid port `{}`", port)))
+    };
+
+    let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((host, "")) => (host, DEFAULT_PORT),
+            Some((host, port)) => match port.strip_prefix(':') {
+                Some(port) => (host, parse_port(port)?),
+                None => return Err(Error::new_invalid_target("invalid IPv6 host")),
+            },
+            None => return Err(Error::new_invalid_target("invalid IPv6 host")),
+        }
+    } else {
+        match s.split_once(':') {
+            // More than one colon is a bare IPv6 address.
+            Some((_, port)) if port.contains(':') => (s, DEFAULT_PORT),
+            Some((host, port)) => (host, parse_port(port)?),
+            None => (s, DEFAULT_PORT),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(Error::new_invalid_target("the host is empty"));
+    }
+
+    Ok((host.to_owned(), port))
+}
+
+fn parse_ip_addresses(target: &Target) -> Result<Vec<Address>, Error> {
+    let is_v4 = target.scheme() == "ipv4";
+
+    target
+        .endpoint()
+        .split(',')
+        .map(|address| {
+            let parsed = if is_v4 {
+                address
+                    .parse::<SocketAddrV4>()
+                    .map(SocketAddr::V4)
+                    .or_else(|_| {
+                        address
+                            .parse::<Ipv4Addr>()
+                            .map(|ip| SocketAddr::from((ip, DEFAULT_PORT)))
+                    })
+            } else {
+                address
+                    .parse::<SocketAddrV6>()
+                    .map(SocketAddr::V6)
+                    .or_else(|_| {
+                        address
+                            .parse::<Ipv6Addr>()
+                            .map(|ip| SocketAddr::from((ip, DEFAULT_PORT)))
+                    })
+            };
+
+            parsed.map(Address::Tcp).map_err(|_| {
+                Error::new_invalid_target(format!(
+                    "invalid {} address `{}`",
+                    target.scheme(),
+                    address
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Resolves a host through the system resolver, to every A and AAAA record it has.
+struct Dns {
+    host: String,
+    port: u16,
+}
+
+impl Resolver for Dns {
+    fn resolve(
+        &self,
+        _target: &Target,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Address>, Error>> + Send>> {
+        let host = self.host.clone();
+        let port = self.port;
+
+        Box::pin(async move {
+            let addresses = tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(Error::from_source)?;
+            Ok(addresses.map(Address::Tcp).collect())
+        })
+    }
+}
+
+/// Resolves to the addresses written in the target.
+struct Static(Vec<Address>);
+
+impl Resolver for Static {
+    fn resolve(
+        &self,
+        _target: &Target,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Address>, Error>> + Send>> {
+        let addresses = self.0.clone();
+        Box::pin(async move { Ok(addresses) })
+    }
+}
+
+/// Keep the endpoints of a channel in sync with the addresses of its target, until the channel
+/// is dropped.
+pub(crate) async fn resolve_target(
+    template: Endpoint,
+    resolver: TargetResolver,
+    mut next: Option<Vec<Address>>,
+    changes: Sender<Change<Address, Endpoint>>,
+    state: Arc<ChannelState>,
+) {
+    let mut current = HashSet::new();
+
+    loop {
+        let resolved_at = Instant::now();
+        let result = match next.take() {
+            Some(addresses) => Ok(addresses),
+            None => resolver.resolve().await,
+        };
+
+        let delay = match result {
+            Ok(addresses) => {
+                let addresses: HashSet<Address> = addresses.into_iter().collect();
+
+                for address in current.difference(&addresses) {
+                    debug!(%address, "removing endpoint");
+                    if changes.send(Change::Remove(address.clone())).await.is_err() {
+                        return;
+                    }
+                }
+
+                for address in addresses.difference(&current) {
+                    debug!(%address, "adding endpoint");
+                    let endpoint = endpoint_for(&template, address);
+                    if changes
+                        .send(Change::Insert(address.clone(), endpoint))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+
+                current = addresses;
+                template.resolution_interval
+            }
+            Err(error) => {
+                debug!(target = %resolver.target, "failed to resolve target: {}", error);
+                MIN_RERESOLUTION_DELAY
+            }
+        };
+
+        let mut interval = Box::pin(sleep(delay));
+        let mut failed = Box::pin(state.connection_failed());
+        let failed = poll_fn(|cx| {
+            if interval.as_mut().poll(cx).is_ready() {
+                Poll::Ready(false)
+            } else if failed.as_mut().poll(cx).is_ready() {
+                Poll::Ready(true)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        if failed {
+            sleep_until(resolved_at + MIN_RERESOLUTION_DELAY).await;
+        }
+
+        if changes.is_closed() {
+            return;
+        }
+    }
+}
+
+/// The endpoint connecting to `address` with the settings of `template`.
+fn endpoint_for(template: &Endpoint, address: &Address) -> Endpoint {
+    #[cfg(feature = "tls")]
+    let scheme = if template.tls.is_some() {
+        Scheme::HTTPS
+    } else {
+        Scheme::HTTP
+    };
+
+    #[cfg(not(feature = "tls"))]
+    let scheme = Scheme::HTTP;
+
+    let mut endpoint = template.clone();
+    let origin = with_scheme(&template.uri, scheme.clone());
+    endpoint.origin = Some(template.origin.clone().unwrap_or_else(|| origin.clone()));
+    endpoint.uri = match address {
+        Address::Tcp(addr) => format!("{}://{}", scheme, addr)
+            .parse()
+            .expect("a socket address is a valid authority"),
+        Address::Unix(_) => origin,
+    };
+    endpoint.address = Some(address.clone());
+    endpoint.target = None;
+    endpoint
+}
+
+fn with_scheme(uri: &Uri, scheme: Scheme) -> Uri {
+    let mut parts = uri.clone().into_parts();
+    parts.scheme = Some(scheme);
+    Uri::from_parts(parts).expect("the URI has an authority")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(s: &str) -> Target {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_targets() {
+        let t = target("dns:///example.com:50051");
+        assert_eq!(
+            (t.scheme(), t.authority(), t.endpoint()),
+            ("dns", Some(""), "example.com:50051")
+        );
+
+        let t = target("unix:///tmp/socket");
+        assert_eq!(
+            (t.scheme(), t.authority(), t.endpoint()),
+            ("unix", Some(""), "tmp/socket")
+        );
+
+        let t = target("custom://authority/name");
+        assert_eq!(
+            (t.scheme(), t.authority(), t.endpoint()),
+            ("custom", Some("authority"), "name")
+        );
+
+        let t = target("ipv6:[::1]:50051");
+        assert_eq!(
+            (t.scheme(), t.authority(), t.endpoint()),
+            ("ipv6", None, "[::1]:50051")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_dns() {
+        assert_eq!(target("example.com:50051"), target("dns:example.com:50051"));
+        assert_eq!(target("localhost"), target("dns:localhost"));
+        assert_eq!(target("[::1]:50051"), target("dns:[::1]:50051"));
+    }
+
+    #[test]
+    fn splits_host_and_port() {
+        let split = |s| split_host_port(s).unwrap();
+        assert_eq!(
+            split("example.com:50051"),
+            ("example.com".to_owned(), 50051)
+        );
+        assert_eq!(split("example.com"), ("example.com".to_owned(), 443));
+        assert_eq!(split("[::1]:50051"), ("::1".to_owned(), 50051));
+        assert_eq!(split("::1"), ("::1".to_owned(), 443));
+
+        assert!(split_host_port(":50051").is_err());
+        assert!(split_host_port("example.com:port").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolves_ip_targets() {
+        let (resolver, uri) = TargetResolver::builtin(target("ipv4:10.0.0.1,10.0.0.2:80")).unwrap();
+        assert_eq!(uri, "http://10.0.0.1:443");
+        assert_eq!(
+            resolver.resolve().await.unwrap(),
+            [
+                Address::Tcp("10.0.0.1:443".parse().unwrap()),
+                Address::Tcp("10.0.0.2:80".parse().unwrap()),
+            ]
+        );
+
+        let (resolver, _) = TargetResolver::builtin(target("ipv6:[::1]:80,::2")).unwrap();
+        assert_eq!(
+            resolver.resolve().await.unwrap(),
+            [
+                Address::Tcp("[::1]:80".parse().unwrap()),
+                Address::Tcp("[::2]:443".parse().unwrap()),
+            ]
+        );
+
+        assert!(TargetResolver::builtin(target("ipv4:[::1]:80")).is_err());
+    }
+
+    #[tokio::test]
+    async fn resolves_unix_targets() {
+        for s in ["unix:///tmp/socket", "unix:/tmp/socket"] {
+            let (resolver, uri) = TargetResolver::builtin(target(s)).unwrap();
+            assert_eq!(uri, "http://localhost");
+            assert_eq!(
+                resolver.resolve().await.unwrap(),
+                [Address::Unix("/tmp/socket".into())]
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_schemes() {
+        assert!(TargetResolver::builtin(target("http://example.com:80")).is_err());
+        assert!(TargetResolver::builtin(target("dns://8.8.8.8/example.com")).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn follows_resolved_addresses() {
+        use std::sync::Mutex;
+
+        struct Scripted(Mutex<Vec<Vec<Address>>>);
+
+        impl Resolver for Scripted {
+            fn resolve(
+                &self,
+                _target: &Target,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<Address>, Error>> + Send>> {
+                let mut script = self.0.lock().unwrap();
+                let addresses = if script.is_empty() {
+                    Vec::new()
+                } else {
+                    script.remove(0)
+                };
+                Box::pin(async move { Ok(addresses) })
+            }
+        }
+
+        let a = Address::Tcp("10.0.0.1:80".parse().unwrap());
+        let b = Address::Tcp("10.0.0.2:80".parse().unwrap());
+        let resolver = Scripted(Mutex::new(vec![
+            vec![a.clone(), b.clone()],
+            vec![b.clone()],
+            vec![],
+        ]));
+        let template = Endpoint::from_target_with_resolver("scripted:///", resolver).unwrap();
+        let resolver = template.target.clone().unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        tokio::spawn(resolve_target(
+            template,
+            resolver,
+            None,
+            tx,
+            ChannelState::new(),
+        ));
+
+        let mut inserted = Vec::new();
+        for _ in 0..2 {
+            match rx.recv().await.unwrap() {
+                Change::Insert(address, endpoint) => {
+                    assert_eq!(endpoint.address.as_ref(), Some(&address));
+                    inserted.push(address);
+                }
+                Change::Remove(_) => panic!("nothing to remove"),
+            }
+        }
+        inserted.sort_by_key(|address| address.to_string());
+        assert_eq!(inserted, [a.clone(), b.clone()]);
+
+        // The next resolution happens after the resolution interval.
+        let start = Instant::now();
+        assert!(matches!(rx.recv().await.unwrap(), Change::Remove(address) if address == a));
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+
+        assert!(matches!(rx.recv().await.unwrap(), Change::Remove(address) if address == b));
+    }
+}
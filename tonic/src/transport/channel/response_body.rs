@@ -0,0 +1,205 @@
+use super::CallTracker;
+use crate::transport::service::RunningCall;
+use crate::Status;
+use bytes::Bytes;
+use http::HeaderMap;
+use http_body::{Body, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{Mutex, PoisonError},
+    task::{ready, Context, Poll},
+};
+
+/// The body of the responses of a [`Channel`](super::Channel).
+///
+/// It yields the data, the trailers and the errors of the HTTP/2 stream of the call as they are
+/// received. Dropping the body before its end cancels the call.
+#[pin_project]
+pub struct ResponseBody {
+    #[pin]
+    inner: hyper::Body,
+    /// Keeps the connection of the call from being idle until the body ends.
+    call: Option<RunningCall>,
+    tracker: Tracker,
+}
+
+/// Hands the outcome of the call to the [`CallTracker`] of the load balancing policy, the call
+/// being cancelled if the body is dropped before its end.
+///
+/// The tracker is only ever reached through `&mut self`; the mutex only keeps the body `Sync`.
+#[derive(Default)]
+struct Tracker(Mutex<Option<Box<dyn CallTracker>>>);
+
+impl Tracker {
+    fn new(tracker: Box<dyn CallTracker>) -> Self {
+        Tracker(Mutex::new(Some(tracker)))
+    }
+
+    fn take(&mut self) -> Option<Box<dyn CallTracker>> {
+        self.0
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    fn finish(&mut self, trailers: Option<&HeaderMap>) {
+        if let Some(tracker) = self.take() {
+            tracker.finish(trailers);
+        }
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        if let Some(tracker) = self.take() {
+            tracker.finish(Some(&Status::cancelled("").to_header_map().unwrap()));
+        }
+    }
+}
+
+impl ResponseBody {
+    pub(crate) fn new(inner: hyper::Body) -> Self {
+        ResponseBody {
+            inner,
+            call: None,
+            tracker: Tracker::default(),
+        }
+    }
+
+    /// Hold `call` until the body ends, fails or is dropped.
+    pub(crate) fn hold(&mut self, call: RunningCall) {
+        if !self.inner.is_end_stream() {
+            self.call = Some(call);
+        }
+    }
+
+    /// Hand the trailers of the call to `tracker` once they are received, or `None` if the body
+    /// fails first.
+    pub(crate) fn track(&mut self, tracker: Box<dyn CallTracker>) {
+        self.tracker = Tracker::new(tracker);
+    }
+}
+
+impl Body for ResponseBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(this.inner.poll_data(cx));
+        if let Some(Err(_)) = data {
+            *this.call = None;
+            this.tracker.finish(None);
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        *this.call = None;
+        match &trailers {
+            Ok(trailers) => this.tracker.finish(trailers.as_ref()),
+            Err(_) => this.tracker.finish(None),
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl fmt::Debug for ResponseBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseBody")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use std::sync::Arc;
+
+    /// Records the status of the trailers a call finished with.
+    struct Record(Arc<Mutex<Vec<Option<Code>>>>);
+
+    impl CallTracker for Record {
+        fn finish(self: Box<Self>, trailers: Option<&HeaderMap>) {
+            let code = trailers.map(|trailers| {
+                Status::from_header_map(trailers)
+                    .map(|status| status.code())
+                    .unwrap_or(Code::Ok)
+            });
+            self.0.lock().unwrap().push(code);
+        }
+    }
+
+    fn tracked() -> (
+        hyper::body::Sender,
+        ResponseBody,
+        Arc<Mutex<Vec<Option<Code>>>>,
+    ) {
+        let (sender, body) = hyper::Body::channel();
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let mut body = ResponseBody::new(body);
+        body.track(Box::new(Record(finished.clone())));
+        (sender, body, finished)
+    }
+
+    #[test]
+    fn body_is_send_and_sync() {
+        fn is_send_and_sync<T: Send + Sync>() {}
+        is_send_and_sync::<ResponseBody>();
+    }
+
+    #[tokio::test]
+    async fn tracker_gets_trailers() {
+        let (mut sender, mut body, finished) = tracked();
+
+        sender.try_send_data(Bytes::from_static(b"data")).unwrap();
+        assert_eq!(body.data().await.unwrap().unwrap(), "data");
+        assert!(finished.lock().unwrap().is_empty());
+
+        let trailers = Status::unavailable("").to_header_map().unwrap();
+        sender.send_trailers(trailers).await.unwrap();
+        drop(sender);
+        assert!(body.data().await.is_none());
+        assert!(body.trailers().await.unwrap().is_some());
+        drop(body);
+        assert_eq!(*finished.lock().unwrap(), [Some(Code::Unavailable)]);
+    }
+
+    #[tokio::test]
+    async fn tracker_gets_nothing_when_body_fails() {
+        let (sender, mut body, finished) = tracked();
+
+        sender.abort();
+        assert!(body.data().await.unwrap().is_err());
+        drop(body);
+        assert_eq!(*finished.lock().unwrap(), [None]);
+    }
+
+    #[tokio::test]
+    async fn dropped_body_is_cancelled() {
+        let (_sender, body, finished) = tracked();
+
+        drop(body);
+        assert_eq!(*finished.lock().unwrap(), [Some(Code::Cancelled)]);
+    }
+}
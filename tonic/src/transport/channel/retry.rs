@@ -0,0 +1,310 @@
+use crate::Code;
+use std::{collections::HashMap, time::Duration};
+
+// gRPC caps the number of attempts of a call, including the original one, to 5.
+const MAX_ATTEMPTS_LIMIT: usize = 5;
+const DEFAULT_RETRY_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Configures how a [`Channel`](super::Channel) retries failed calls.
+///
+/// Follows the [gRPC retry design]: a call is retried when it fails with one of the retryable
+/// status codes before the server sent any response headers. The delay before retry attempt `n`
+/// is chosen uniformly at random between zero and
+/// `min(initial_backoff * backoff_multiplier^(n - 1), max_backoff)`, unless the server asks for a
+/// specific delay with the `grpc-retry-pushback-ms` trailer.
+///
+/// Extensions can't be cloned, so the extensions a caller adds to a request only reach its first
+/// attempt. Retry attempts carry the extensions tonic sets, such as the [`GrpcMethod`] and the
+/// [`RequestHash`].
+///
+/// ```
+/// # use tonic::transport::{Endpoint, RetryPolicy};
+/// # use tonic::Code;
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::new()
+///     .max_attempts(4)
+///     .initial_backoff(Duration::from_millis(50))
+///     .retryable_status_codes([Code::Unavailable, Code::ResourceExhausted]);
+///
+/// let endpoint = Endpoint::from_static("https://example.com").retry_policy(policy);
+/// ```
+///
+/// [gRPC retry design]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+/// [`GrpcMethod`]: crate::GrpcMethod
+/// [`RequestHash`]: super::RequestHash
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub(crate) max_attempts: usize,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) backoff_multiplier: f64,
+    pub(crate) retryable_status_codes: Vec<Code>,
+}
+
+impl RetryPolicy {
+    /// Create a retry policy with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of attempts, including the original call.
+    ///
+    /// Values above 5 are treated as 5. Default is 3.
+    pub fn max_attempts(self, max_attempts: usize) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.min(MAX_ATTEMPTS_LIMIT),
+            ..self
+        }
+    }
+
+    /// Set the upper bound of the delay before the first retry.
+    ///
+    /// Default is 100 milliseconds.
+    pub fn initial_backoff(self, initial_backoff: Duration) -> Self {
+        RetryPolicy {
+            initial_backoff,
+            ..self
+        }
+    }
+
+    /// Set the maximum delay between two attempts.
+    ///
+    /// Default is 10 seconds.
+    pub fn max_backoff(self, max_backoff: Duration) -> Self {
+        RetryPolicy {
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// Set the factor the backoff grows by after each attempt.
+    ///
+    /// Default is 2.
+    pub fn backoff_multiplier(self, backoff_multiplier: f64) -> Self {
+        RetryPolicy {
+            backoff_multiplier,
+            ..self
+        }
+    }
+
+    /// Set the status codes that cause a call to be retried.
+    ///
+    /// Default is only [`Code::Unavailable`].
+    pub fn retryable_status_codes(self, codes: impl IntoIterator<Item = Code>) -> Self {
+        RetryPolicy {
+            retryable_status_codes: codes.into_iter().collect(),
+            ..self
+        }
+    }
+
+    pub(crate) fn is_retryable(&self, code: Code) -> bool {
+        self.retryable_status_codes.contains(&code)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            backoff_multiplier: 2.0,
+            retryable_status_codes: vec![Code::Unavailable],
+        }
+    }
+}
+
+/// Configures how a [`Channel`](super::Channel) hedges calls.
+///
+/// Follows the [gRPC retry design]: the first attempt of a call is sent right away, and further
+/// attempts are sent every `hedging_delay` until `max_attempts` attempts are in flight. The first
+/// attempt that succeeds, or fails with a status code that isn't one of the non-fatal status
+/// codes, is handed to the caller and the other attempts are cancelled. When an attempt fails
+/// with a non-fatal status code the next attempt is sent right away.
+///
+/// Hedging is meant for idempotent calls. On a channel balancing several endpoints, attempts
+/// can be sent to different endpoints. As with retries, only the first attempt carries the
+/// extensions added by the caller, see [`RetryPolicy`].
+///
+/// ```
+/// # use tonic::transport::{Endpoint, HedgingPolicy};
+/// # use std::time::Duration;
+/// let policy = HedgingPolicy::new()
+///     .max_attempts(3)
+///     .hedging_delay(Duration::from_millis(20));
+///
+/// let endpoint = Endpoint::from_static("https://example.com")
+///     .method_hedging_policy("helloworld.Greeter/SayHello", policy);
+/// ```
+///
+/// [gRPC retry design]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+#[derive(Debug, Clone)]
+pub struct HedgingPolicy {
+    pub(crate) max_attempts: usize,
+    pub(crate) hedging_delay: Duration,
+    pub(crate) non_fatal_status_codes: Vec<Code>,
+}
+
+impl HedgingPolicy {
+    /// Create a hedging policy with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of attempts, including the original call.
+    ///
+    /// Values above 5 are treated as 5. Default is 2.
+    pub fn max_attempts(self, max_attempts: usize) -> Self {
+        HedgingPolicy {
+            max_attempts: max_attempts.min(MAX_ATTEMPTS_LIMIT),
+            ..self
+        }
+    }
+
+    /// Set the delay between sending two attempts.
+    ///
+    /// Default is 0, which sends all attempts at once.
+    pub fn hedging_delay(self, hedging_delay: Duration) -> Self {
+        HedgingPolicy {
+            hedging_delay,
+            ..self
+        }
+    }
+
+    /// Set the status codes that don't end the call while other attempts may still succeed.
+    ///
+    /// Default is none.
+    pub fn non_fatal_status_codes(self, codes: impl IntoIterator<Item = Code>) -> Self {
+        HedgingPolicy {
+            non_fatal_status_codes: codes.into_iter().collect(),
+            ..self
+        }
+    }
+
+    pub(crate) fn is_non_fatal(&self, code: Code) -> bool {
+        self.non_fatal_status_codes.contains(&code)
+    }
+}
+
+impl Default for HedgingPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 2,
+            hedging_delay: Duration::ZERO,
+            non_fatal_status_codes: Vec::new(),
+        }
+    }
+}
+
+/// How the calls to a method are sent again, a method has at most one of the two.
+#[derive(Debug, Clone)]
+pub(crate) enum CallPolicy {
+    Retry(RetryPolicy),
+    Hedging(HedgingPolicy),
+}
+
+impl CallPolicy {
+    pub(crate) fn max_attempts(&self) -> usize {
+        match self {
+            CallPolicy::Retry(policy) => policy.max_attempts,
+            CallPolicy::Hedging(policy) => policy.max_attempts,
+        }
+    }
+}
+
+impl From<RetryPolicy> for CallPolicy {
+    fn from(policy: RetryPolicy) -> Self {
+        CallPolicy::Retry(policy)
+    }
+}
+
+impl From<HedgingPolicy> for CallPolicy {
+    fn from(policy: HedgingPolicy) -> Self {
+        CallPolicy::Hedging(policy)
+    }
+}
+
+/// The retry and hedging settings of an [`Endpoint`](super::Endpoint).
+#[derive(Debug, Clone)]
+pub(crate) struct RetryConfig {
+    default_policy: Option<CallPolicy>,
+    /// Policies keyed by `package.Service` or `package.Service/Method`.
+    policies: HashMap<String, CallPolicy>,
+    pub(crate) buffer_size: usize,
+    /// The maximum number of tokens and the token ratio of the retry throttle.
+    pub(crate) throttling: Option<(u32, f32)>,
+}
+
+impl RetryConfig {
+    pub(crate) fn set_default_policy(&mut self, policy: impl Into<CallPolicy>) {
+        self.default_policy = Some(policy.into());
+    }
+
+    pub(crate) fn set_policy(&mut self, name: String, policy: impl Into<CallPolicy>) {
+        self.policies
+            .insert(name.trim_matches('/').to_owned(), policy.into());
+    }
+
+    /// Find the policy for a request path of the form `/package.Service/Method`.
+    pub(crate) fn policy_for(&self, path: &str) -> Option<&CallPolicy> {
+        let method = path.trim_start_matches('/');
+        let service = method.split('/').next().unwrap_or_default();
+
+        self.policies
+            .get(method)
+            .or_else(|| self.policies.get(service))
+            .or(self.default_policy.as_ref())
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            default_policy: None,
+            policies: HashMap::new(),
+            buffer_size: DEFAULT_RETRY_BUFFER_SIZE,
+            throttling: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_lookup_prefers_most_specific_name() {
+        let mut config = RetryConfig::default();
+        assert!(config.policy_for("/test.Test/Unary").is_none());
+
+        config.set_default_policy(RetryPolicy::new().max_attempts(2));
+        config.set_policy("test.Test".into(), RetryPolicy::new().max_attempts(3));
+        config.set_policy(
+            "/test.Test/Unary".into(),
+            RetryPolicy::new().max_attempts(4),
+        );
+
+        let attempts = |path| config.policy_for(path).unwrap().max_attempts();
+        assert_eq!(attempts("/test.Test/Unary"), 4);
+        assert_eq!(attempts("/test.Test/Streaming"), 3);
+        assert_eq!(attempts("/other.Other/Unary"), 2);
+    }
+
+    #[test]
+    fn method_has_either_retry_or_hedging_policy() {
+        let mut config = RetryConfig::default();
+        config.set_policy("test.Test".into(), RetryPolicy::new());
+        config.set_policy("test.Test".into(), HedgingPolicy::new());
+
+        assert!(matches!(
+            config.policy_for("/test.Test/Unary"),
+            Some(CallPolicy::Hedging(_))
+        ));
+    }
+
+    #[test]
+    fn max_attempts_is_capped() {
+        assert_eq!(RetryPolicy::new().max_attempts(10).max_attempts, 5);
+        assert_eq!(HedgingPolicy::new().max_attempts(10).max_attempts, 5);
+    }
+}
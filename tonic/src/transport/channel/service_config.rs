@@ -0,0 +1,729 @@
+// Service configs are only parsed from JSON, so without the `service-config` feature channels
+// never have one.
+#![cfg_attr(not(feature = "service-config"), allow(dead_code, unreachable_pub))]
+
+use super::{CallPolicy, RetryConfig};
+use crate::{
+    body::BoxBody, codec::HEADER_SIZE, metadata::GRPC_TIMEOUT_HEADER,
+    request::duration_to_grpc_timeout, transport::service::grpc_timeout::try_parse_grpc_timeout,
+    Status,
+};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue};
+use http_body::{Body, SizeHint};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+#[cfg(feature = "service-config")]
+use {
+    super::{HedgingPolicy, RetryPolicy},
+    crate::{transport::Error, Code},
+    serde::Deserialize,
+    std::str::FromStr,
+};
+
+/// A gRPC [service config], the per-method settings of the clients of a service.
+///
+/// A service config is parsed from its JSON representation and attached to an [`Endpoint`] with
+/// [`Endpoint::service_config`]. The [`Channel`] built from the endpoint then applies the settings
+/// of the `methodConfig` entry matching the path of each call:
+///
+/// - `waitForReady` makes calls wait for the channel to connect, instead of failing with
+///   `UNAVAILABLE` while it is waiting to reconnect after a failed connection attempt.
+/// - `timeout` is sent to the server as the call's deadline, unless the call has a shorter one,
+///   and the channel stops waiting for the response once it elapses.
+/// - `maxRequestMessageBytes` and `maxResponseMessageBytes` limit the size of the messages of the
+///   call, unless the client sets stricter limits.
+/// - `retryPolicy` and `hedgingPolicy` are applied like policies set with
+///   [`Endpoint::method_retry_policy`] and [`Endpoint::method_hedging_policy`], and
+///   `retryThrottling` like [`Endpoint::retry_throttling`].
+///
+/// Only a [`Channel`] applies a service config: a `client::Grpc` sending its calls over another
+/// [`GrpcService`] doesn't. Such services can look up the settings of a call with
+/// [`ServiceConfig::method_config`].
+///
+/// ```
+/// # #[cfg(feature = "service-config")] {
+/// # use tonic::transport::{Endpoint, ServiceConfig};
+/// let config: ServiceConfig = r#"{
+///     "methodConfig": [{
+///         "name": [{ "service": "helloworld.Greeter" }],
+///         "timeout": "1.5s",
+///         "maxResponseMessageBytes": 1048576,
+///         "retryPolicy": {
+///             "maxAttempts": 3,
+///             "initialBackoff": "0.1s",
+///             "maxBackoff": "1s",
+///             "backoffMultiplier": 2,
+///             "retryableStatusCodes": ["UNAVAILABLE"]
+///         }
+///     }]
+/// }"#
+/// .parse()
+/// .unwrap();
+///
+/// let endpoint = Endpoint::from_static("https://example.com").service_config(config);
+/// # }
+/// ```
+///
+/// [service config]: https://github.com/grpc/grpc/blob/master/doc/service_config.md
+/// [`Endpoint`]: super::Endpoint
+/// [`Channel`]: super::Channel
+/// [`GrpcService`]: crate::client::GrpcService
+/// [`Endpoint::service_config`]: super::Endpoint::service_config
+/// [`Endpoint::method_retry_policy`]: super::Endpoint::method_retry_policy
+/// [`Endpoint::method_hedging_policy`]: super::Endpoint::method_hedging_policy
+/// [`Endpoint::retry_throttling`]: super::Endpoint::retry_throttling
+#[derive(Debug, Clone, Default)]
+pub struct ServiceConfig {
+    default_method_config: Option<MethodConfig>,
+    /// Method configs keyed by `package.Service` or `package.Service/Method`.
+    method_configs: HashMap<String, MethodConfig>,
+    /// The names of the load balancing policies, in order of preference.
+    load_balancing_policies: Vec<String>,
+    retry_throttling: Option<(u32, f32)>,
+}
+
+/// The settings of the calls to a method, see [`ServiceConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct MethodConfig {
+    timeout: Option<Duration>,
+    wait_for_ready: Option<bool>,
+    max_request_message_bytes: Option<usize>,
+    max_response_message_bytes: Option<usize>,
+    policy: Option<CallPolicy>,
+}
+
+impl ServiceConfig {
+    /// Parse a service config from its JSON representation.
+    #[cfg(feature = "service-config")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let raw: RawServiceConfig =
+            serde_json::from_str(json).map_err(Error::new_invalid_service_config)?;
+
+        raw.try_into().map_err(Error::new_invalid_service_config)
+    }
+
+    /// Find the settings for a request path of the form `/package.Service/Method`.
+    pub fn method_config(&self, path: &str) -> Option<&MethodConfig> {
+        let method = path.trim_start_matches('/');
+        let service = method.split('/').next().unwrap_or_default();
+
+        self.method_configs
+            .get(method)
+            .or_else(|| self.method_configs.get(service))
+            .or(self.default_method_config.as_ref())
+    }
+
+    /// The name of the first load balancing policy of the `loadBalancingConfig` list.
+    pub fn load_balancing_policy(&self) -> Option<&str> {
+        self.load_balancing_policies.first().map(String::as_str)
+    }
+
+    /// The names of the load balancing policies of the `loadBalancingConfig` list, in order of
+    /// preference.
+    pub(crate) fn load_balancing_policies(&self) -> impl Iterator<Item = &str> {
+        self.load_balancing_policies.iter().map(String::as_str)
+    }
+
+    /// Add the retry and hedging policies of this config to `retry`.
+    pub(crate) fn configure_retries(&self, retry: &mut RetryConfig) {
+        if let Some(policy) = self
+            .default_method_config
+            .as_ref()
+            .and_then(|config| config.policy.clone())
+        {
+            retry.set_default_policy(policy);
+        }
+
+        for (name, config) in &self.method_configs {
+            if let Some(policy) = config.policy.clone() {
+                retry.set_policy(name.clone(), policy);
+            }
+        }
+
+        if self.retry_throttling.is_some() {
+            retry.throttling = self.retry_throttling;
+        }
+    }
+
+    /// The timeout of the calls to the method at `path`, if the config sets one.
+    pub(crate) fn timeout(&self, path: &str) -> Option<Duration> {
+        self.method_config(path).and_then(|config| config.timeout)
+    }
+
+    /// Whether calls to the method at `path` wait for the channel to be ready, if the config
+    /// says so.
+    pub(crate) fn wait_for_ready(&self, path: &str) -> Option<bool> {
+        self.method_config(path)
+            .and_then(|config| config.wait_for_ready)
+    }
+
+    /// Apply the settings of the method `request` calls, returning the maximum size of its
+    /// response messages.
+    pub(crate) fn apply(&self, request: &mut http::Request<BoxBody>) -> Option<usize> {
+        let config = self.method_config(request.uri().path())?;
+
+        if let Some(limit) = config.max_request_message_bytes {
+            let body = std::mem::replace(request.body_mut(), crate::body::empty_body());
+            *request.body_mut() = BoxBody::new(LimitRequestMessages::new(body, limit));
+        }
+
+        let timeout = match (config.timeout, try_parse_grpc_timeout(request.headers())) {
+            (Some(timeout), Ok(Some(deadline))) => Some(timeout.min(deadline)),
+            (timeout, _) => timeout,
+        };
+
+        if let Some(timeout) = timeout {
+            let header = HeaderValue::from_str(&duration_to_grpc_timeout(timeout))
+                .expect("grpc-timeout is a valid header value");
+            request.headers_mut().insert(GRPC_TIMEOUT_HEADER, header);
+        }
+
+        config.max_response_message_bytes
+    }
+}
+
+#[cfg(feature = "service-config")]
+impl FromStr for ServiceConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_json(s)
+    }
+}
+
+impl MethodConfig {
+    /// The maximum duration of a call.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Whether calls wait for the channel to be ready instead of failing right away.
+    pub fn wait_for_ready(&self) -> Option<bool> {
+        self.wait_for_ready
+    }
+
+    /// The maximum size of a request message in bytes.
+    pub fn max_request_message_bytes(&self) -> Option<usize> {
+        self.max_request_message_bytes
+    }
+
+    /// The maximum size of a response message in bytes.
+    pub fn max_response_message_bytes(&self) -> Option<usize> {
+        self.max_response_message_bytes
+    }
+}
+
+/// A request body failing with `OUT_OF_RANGE` once it holds a message larger than its limit.
+///
+/// The messages were encoded before the channel found the method config of the call, so their
+/// sizes are read from the headers of the messages.
+struct LimitRequestMessages {
+    inner: BoxBody,
+    limit: usize,
+    /// The header of the next message, of which `header_len` bytes were read.
+    header: [u8; HEADER_SIZE],
+    header_len: usize,
+    /// The bytes of the current message that weren't read yet.
+    remaining: usize,
+}
+
+impl LimitRequestMessages {
+    fn new(inner: BoxBody, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            header: [0; HEADER_SIZE],
+            header_len: 0,
+            remaining: 0,
+        }
+    }
+
+    /// Read `data`, returning the length of the first message it finds over the limit.
+    fn oversized_message(&mut self, mut data: &[u8]) -> Option<usize> {
+        while !data.is_empty() {
+            if self.remaining > 0 {
+                let skipped = self.remaining.min(data.len());
+                self.remaining -= skipped;
+                data = &data[skipped..];
+                continue;
+            }
+
+            let read = (HEADER_SIZE - self.header_len).min(data.len());
+            self.header[self.header_len..self.header_len + read].copy_from_slice(&data[..read]);
+            self.header_len += read;
+            data = &data[read..];
+
+            if self.header_len == HEADER_SIZE {
+                self.header_len = 0;
+                let len = u32::from_be_bytes([
+                    self.header[1],
+                    self.header[2],
+                    self.header[3],
+                    self.header[4],
+                ]) as usize;
+
+                if len > self.limit {
+                    return Some(len);
+                }
+                self.remaining = len;
+            }
+        }
+
+        None
+    }
+}
+
+impl Body for LimitRequestMessages {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let data = ready!(Pin::new(&mut self.inner).poll_data(cx));
+        if let Some(Ok(data)) = &data {
+            if let Some(len) = self.oversized_message(data) {
+                return Poll::Ready(Some(Err(Status::out_of_range(format!(
+                    "Error, message length too large: found {} bytes, the limit is: {} bytes",
+                    len, self.limit
+                )))));
+            }
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawServiceConfig {
+    load_balancing_policy: Option<String>,
+    #[serde(default)]
+    load_balancing_config: Vec<HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    method_config: Vec<RawMethodConfig>,
+    retry_throttling: Option<RawRetryThrottling>,
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMethodConfig {
+    #[serde(default)]
+    name: Vec<RawName>,
+    wait_for_ready: Option<bool>,
+    timeout: Option<String>,
+    max_request_message_bytes: Option<usize>,
+    max_response_message_bytes: Option<usize>,
+    retry_policy: Option<RawRetryPolicy>,
+    hedging_policy: Option<RawHedgingPolicy>,
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+struct RawName {
+    #[serde(default)]
+    service: String,
+    #[serde(default)]
+    method: String,
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRetryPolicy {
+    max_attempts: usize,
+    initial_backoff: String,
+    max_backoff: String,
+    backoff_multiplier: f64,
+    retryable_status_codes: Vec<RawCode>,
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawHedgingPolicy {
+    max_attempts: usize,
+    hedging_delay: Option<String>,
+    #[serde(default)]
+    non_fatal_status_codes: Vec<RawCode>,
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRetryThrottling {
+    max_tokens: u32,
+    token_ratio: f32,
+}
+
+#[cfg(feature = "service-config")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawCode {
+    Number(i32),
+    Name(String),
+}
+
+#[cfg(feature = "service-config")]
+impl TryFrom<RawServiceConfig> for ServiceConfig {
+    type Error = String;
+
+    fn try_from(raw: RawServiceConfig) -> Result<Self, Self::Error> {
+        let mut config = ServiceConfig::default();
+
+        for raw_method_config in raw.method_config {
+            let names = raw_method_config
+                .name
+                .iter()
+                .map(|name| match (name.service.as_str(), name.method.as_str()) {
+                    ("", "") => Ok(None),
+                    ("", _) => Err("a method name requires a service name".to_owned()),
+                    (service, "") => Ok(Some(service.to_owned())),
+                    (service, method) => Ok(Some(format!("{}/{}", service, method))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let method_config = MethodConfig::try_from(raw_method_config)?;
+
+            for name in names {
+                let previous = match name {
+                    None => config.default_method_config.replace(method_config.clone()),
+                    Some(name) => config.method_configs.insert(name, method_config.clone()),
+                };
+
+                if previous.is_some() {
+                    return Err("a method is configured more than once".to_owned());
+                }
+            }
+        }
+
+        // `loadBalancingConfig` takes precedence over the deprecated `loadBalancingPolicy`.
+        for entry in raw.load_balancing_config {
+            let mut entry = entry.into_iter();
+            match (entry.next(), entry.next()) {
+                (Some((name, _)), None) => config.load_balancing_policies.push(name),
+                _ => return Err("a loadBalancingConfig entry must name one policy".to_owned()),
+            }
+        }
+        if config.load_balancing_policies.is_empty() {
+            if let Some(name) = raw.load_balancing_policy {
+                config
+                    .load_balancing_policies
+                    .push(name.to_ascii_lowercase());
+            }
+        }
+
+        if let Some(throttling) = raw.retry_throttling {
+            if throttling.max_tokens == 0 || throttling.token_ratio <= 0.0 {
+                return Err("retryThrottling requires positive maxTokens and tokenRatio".to_owned());
+            }
+            config.retry_throttling = Some((throttling.max_tokens, throttling.token_ratio));
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(feature = "service-config")]
+impl TryFrom<RawMethodConfig> for MethodConfig {
+    type Error = String;
+
+    fn try_from(raw: RawMethodConfig) -> Result<Self, Self::Error> {
+        let policy = match (raw.retry_policy, raw.hedging_policy) {
+            (Some(_), Some(_)) => {
+                return Err("a method can't have both a retryPolicy and a hedgingPolicy".to_owned())
+            }
+            (Some(retry), None) => Some(retry_policy(retry)?.into()),
+            (None, Some(hedging)) => Some(hedging_policy(hedging)?.into()),
+            (None, None) => None,
+        };
+
+        Ok(MethodConfig {
+            timeout: raw.timeout.as_deref().map(parse_duration).transpose()?,
+            wait_for_ready: raw.wait_for_ready,
+            max_request_message_bytes: raw.max_request_message_bytes,
+            max_response_message_bytes: raw.max_response_message_bytes,
+            policy,
+        })
+    }
+}
+
+#[cfg(feature = "service-config")]
+fn retry_policy(raw: RawRetryPolicy) -> Result<RetryPolicy, String> {
+    let initial_backoff = parse_duration(&raw.initial_backoff)?;
+    let max_backoff = parse_duration(&raw.max_backoff)?;
+
+    if raw.max_attempts < 2
+        || initial_backoff.is_zero()
+        || max_backoff.is_zero()
+        || raw.backoff_multiplier <= 0.0
+        || raw.retryable_status_codes.is_empty()
+    {
+        return Err(
+            "retryPolicy requires maxAttempts above 1, positive backoffs and multiplier, and \
+             retryable status codes"
+                .to_owned(),
+        );
+    }
+
+    Ok(RetryPolicy::new()
+        .max_attempts(raw.max_attempts)
+        .initial_backoff(initial_backoff)
+        .max_backoff(max_backoff)
+        .backoff_multiplier(raw.backoff_multiplier)
+        .retryable_status_codes(parse_codes(raw.retryable_status_codes)?))
+}
+
+#[cfg(feature = "service-config")]
+fn hedging_policy(raw: RawHedgingPolicy) -> Result<HedgingPolicy, String> {
+    if raw.max_attempts < 2 {
+        return Err("hedgingPolicy requires maxAttempts above 1".to_owned());
+    }
+
+    let hedging_delay = match raw.hedging_delay {
+        Some(delay) => parse_duration(&delay)?,
+        None => Duration::ZERO,
+    };
+
+    Ok(HedgingPolicy::new()
+        .max_attempts(raw.max_attempts)
+        .hedging_delay(hedging_delay)
+        .non_fatal_status_codes(parse_codes(raw.non_fatal_status_codes)?))
+}
+
+#[cfg(feature = "service-config")]
+/// Parse a duration in the JSON format of `google.protobuf.Duration`, such as `1.5s`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {:?}", s);
+
+    let seconds = s.strip_suffix('s').ok_or_else(invalid)?;
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+
+    if whole.is_empty() || fraction.len() > 9 {
+        return Err(invalid());
+    }
+    if !whole
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let secs = whole.parse::<u64>().map_err(|_| invalid())?;
+    let nanos = format!("{:0<9}", fraction)
+        .parse::<u32>()
+        .map_err(|_| invalid())?;
+
+    Ok(Duration::new(secs, nanos))
+}
+
+#[cfg(feature = "service-config")]
+fn parse_codes(raw: Vec<RawCode>) -> Result<Vec<Code>, String> {
+    raw.into_iter()
+        .map(|code| match code {
+            RawCode::Number(number @ 0..=16) => Ok(Code::from_i32(number)),
+            RawCode::Name(name) => code_from_name(&name),
+            RawCode::Number(number) => Err(format!("invalid status code {}", number)),
+        })
+        .collect()
+}
+
+#[cfg(feature = "service-config")]
+fn code_from_name(name: &str) -> Result<Code, String> {
+    let code = match name {
+        "OK" => Code::Ok,
+        "CANCELLED" => Code::Cancelled,
+        "UNKNOWN" => Code::Unknown,
+        "INVALID_ARGUMENT" => Code::InvalidArgument,
+        "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+        "NOT_FOUND" => Code::NotFound,
+        "ALREADY_EXISTS" => Code::AlreadyExists,
+        "PERMISSION_DENIED" => Code::PermissionDenied,
+        "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+        "FAILED_PRECONDITION" => Code::FailedPrecondition,
+        "ABORTED" => Code::Aborted,
+        "OUT_OF_RANGE" => Code::OutOfRange,
+        "UNIMPLEMENTED" => Code::Unimplemented,
+        "INTERNAL" => Code::Internal,
+        "UNAVAILABLE" => Code::Unavailable,
+        "DATA_LOSS" => Code::DataLoss,
+        "UNAUTHENTICATED" => Code::Unauthenticated,
+        _ => return Err(format!("invalid status code {:?}", name)),
+    };
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "service-config")]
+    use crate::Code;
+
+    #[cfg(feature = "service-config")]
+    fn parse(json: &str) -> ServiceConfig {
+        json.parse().unwrap()
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn parses_method_config() {
+        let config = parse(
+            r#"{
+                "methodConfig": [{
+                    "name": [{ "service": "test.Test", "method": "Unary" }],
+                    "waitForReady": true,
+                    "timeout": "1.25s",
+                    "maxRequestMessageBytes": 1024,
+                    "maxResponseMessageBytes": 2048
+                }]
+            }"#,
+        );
+
+        let method = config.method_config("/test.Test/Unary").unwrap();
+        assert_eq!(method.timeout(), Some(Duration::from_millis(1250)));
+        assert_eq!(method.wait_for_ready(), Some(true));
+        assert_eq!(method.max_request_message_bytes(), Some(1024));
+        assert_eq!(method.max_response_message_bytes(), Some(2048));
+
+        assert!(config.method_config("/test.Test/Streaming").is_none());
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn method_config_lookup_prefers_most_specific_name() {
+        let config = parse(
+            r#"{
+                "methodConfig": [
+                    { "name": [{}], "timeout": "1s" },
+                    { "name": [{ "service": "test.Test" }], "timeout": "2s" },
+                    { "name": [{ "service": "test.Test", "method": "Unary" }], "timeout": "3s" }
+                ]
+            }"#,
+        );
+
+        let timeout = |path| config.method_config(path).unwrap().timeout().unwrap();
+        assert_eq!(timeout("/test.Test/Unary"), Duration::from_secs(3));
+        assert_eq!(timeout("/test.Test/Streaming"), Duration::from_secs(2));
+        assert_eq!(timeout("/other.Other/Unary"), Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn configures_retries() {
+        let config = parse(
+            r#"{
+                "methodConfig": [{
+                    "name": [{ "service": "test.Test" }],
+                    "retryPolicy": {
+                        "maxAttempts": 4,
+                        "initialBackoff": "0.1s",
+                        "maxBackoff": "1s",
+                        "backoffMultiplier": 2,
+                        "retryableStatusCodes": ["UNAVAILABLE", 8]
+                    }
+                }, {
+                    "name": [{ "service": "test.Test", "method": "Hedged" }],
+                    "hedgingPolicy": { "maxAttempts": 3, "hedgingDelay": "0.05s" }
+                }],
+                "retryThrottling": { "maxTokens": 10, "tokenRatio": 0.5 }
+            }"#,
+        );
+
+        let mut retry = RetryConfig::default();
+        config.configure_retries(&mut retry);
+
+        match retry.policy_for("/test.Test/Unary") {
+            Some(CallPolicy::Retry(policy)) => {
+                assert_eq!(policy.max_attempts, 4);
+                assert_eq!(policy.initial_backoff, Duration::from_millis(100));
+                assert!(policy.is_retryable(Code::ResourceExhausted));
+            }
+            other => panic!("expected a retry policy, got {:?}", other),
+        }
+        match retry.policy_for("/test.Test/Hedged") {
+            Some(CallPolicy::Hedging(policy)) => {
+                assert_eq!(policy.hedging_delay, Duration::from_millis(50))
+            }
+            other => panic!("expected a hedging policy, got {:?}", other),
+        }
+        assert_eq!(retry.throttling, Some((10, 0.5)));
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn parses_load_balancing_config() {
+        let config = parse(
+            r#"{
+                "loadBalancingPolicy": "PICK_FIRST",
+                "loadBalancingConfig": [{ "round_robin": {} }, { "pick_first": {} }]
+            }"#,
+        );
+        assert_eq!(config.load_balancing_policy(), Some("round_robin"));
+
+        let config = parse(r#"{ "loadBalancingPolicy": "ROUND_ROBIN" }"#);
+        assert_eq!(config.load_balancing_policy(), Some("round_robin"));
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn rejects_invalid_configs() {
+        let invalid = [
+            "not json",
+            r#"{ "methodConfig": [{ "name": [{ "method": "Unary" }] }] }"#,
+            r#"{ "methodConfig": [{ "name": [{}] }, { "name": [{}] }] }"#,
+            r#"{ "methodConfig": [{ "name": [{}], "timeout": "1m" }] }"#,
+            r#"{ "methodConfig": [{ "name": [{}], "hedgingPolicy": { "maxAttempts": 1 } }] }"#,
+            r#"{ "loadBalancingConfig": [{ "round_robin": {}, "pick_first": {} }] }"#,
+        ];
+
+        for json in invalid {
+            assert!(json.parse::<ServiceConfig>().is_err(), "{}", json);
+        }
+    }
+
+    #[cfg(feature = "service-config")]
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("1s"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_duration("0.000000001s"), Ok(Duration::from_nanos(1)));
+        assert_eq!(parse_duration("2.5s"), Ok(Duration::from_millis(2500)));
+        assert!(parse_duration("1").is_err());
+        assert!(parse_duration(".5s").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("1.0000000001s").is_err());
+    }
+
+    #[test]
+    fn limits_request_messages_split_across_chunks() {
+        let mut body = LimitRequestMessages::new(crate::body::empty_body(), 4);
+
+        // A message of 4 bytes whose header is split, then the header of a 5 byte message.
+        assert_eq!(body.oversized_message(&[0, 0, 0]), None);
+        assert_eq!(body.oversized_message(&[0, 4, 1, 2]), None);
+        assert_eq!(body.oversized_message(&[3, 4, 0, 0]), None);
+        assert_eq!(body.oversized_message(&[0, 0, 5]), Some(5));
+    }
+}
@@ -0,0 +1,412 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    sync::{futures::Notified, watch, Notify},
+    time::Instant,
+};
+
+/// The connectivity state of a [`Channel`](super::Channel).
+///
+/// Follows the gRPC [connectivity semantics]. The state of a channel balancing several endpoints
+/// is the best state of its endpoints: it is `Ready` when any endpoint is ready, otherwise
+/// `Connecting` when any endpoint is connecting, otherwise `Idle` when any endpoint is idle, and
+/// `TransientFailure` when every endpoint failed to connect.
+///
+/// [connectivity semantics]: https://github.com/grpc/grpc/blob/master/doc/connectivity-semantics-and-api.md
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectivityState {
+    /// No connection is established or being established. The channel connects when it is used.
+    Idle,
+    /// The channel is establishing a connection.
+    Connecting,
+    /// The channel is connected and can send calls.
+    Ready,
+    /// The last connection attempt failed, the channel waits before the next one.
+    TransientFailure,
+    /// The channel has shut down and won't send any more calls.
+    Shutdown,
+}
+
+/// Tracks the connectivity state of the connections of a channel.
+#[derive(Debug)]
+pub(crate) struct ChannelState {
+    connections: Mutex<Connections>,
+    state: watch::Sender<ConnectivityState>,
+    /// When every connection is backing off, the end of the first backoff.
+    backoff: watch::Sender<Option<Instant>>,
+    failures: Notify,
+    /// The connection of another channel this channel reports its state to.
+    parent: Option<ConnectionState>,
+    /// The number of connections open to a server, which stay open while calls run on them.
+    open: watch::Sender<usize>,
+    /// Whether the open connections are closed, cutting off their calls.
+    closed: watch::Sender<bool>,
+}
+
+#[derive(Debug, Default)]
+struct Connections {
+    next_id: u64,
+    states: HashMap<u64, (ConnectivityState, Option<Instant>)>,
+    is_shutdown: bool,
+}
+
+impl ChannelState {
+    pub(crate) fn new() -> Arc<Self> {
+        Self::with_parent(None)
+    }
+
+    /// Track the connections of a single connection of another channel, `parent`, whose state is
+    /// the state of these connections.
+    pub(crate) fn nested(parent: ConnectionState) -> Arc<Self> {
+        Self::with_parent(Some(parent))
+    }
+
+    fn with_parent(parent: Option<ConnectionState>) -> Arc<Self> {
+        Arc::new(ChannelState {
+            connections: Mutex::new(Connections::default()),
+            state: watch::channel(ConnectivityState::Idle).0,
+            backoff: watch::channel(None).0,
+            failures: Notify::new(),
+            parent,
+            open: watch::channel(0).0,
+            closed: watch::channel(false).0,
+        })
+    }
+
+    /// The channel at the root of nested channels, which tracks the open connections.
+    fn root(self: &Arc<Self>) -> Arc<Self> {
+        match &self.parent {
+            Some(parent) => parent.channel.root(),
+            None => self.clone(),
+        }
+    }
+
+    /// Track a new connection of the channel.
+    ///
+    /// When `is_channel` is true the connection is the whole channel, which shuts down once the
+    /// connection is dropped.
+    pub(crate) fn connection(self: &Arc<Self>, is_channel: bool) -> ConnectionState {
+        let id = self.update(|connections| {
+            let id = connections.next_id;
+            connections.next_id += 1;
+            connections
+                .states
+                .insert(id, (ConnectivityState::Idle, None));
+            id
+        });
+
+        ConnectionState {
+            channel: self.clone(),
+            id,
+            is_channel,
+        }
+    }
+
+    pub(crate) fn get(&self) -> ConnectivityState {
+        *self.state.borrow()
+    }
+
+    pub(crate) fn watch(&self) -> watch::Receiver<ConnectivityState> {
+        self.state.subscribe()
+    }
+
+    pub(crate) fn watch_backoff(&self) -> watch::Receiver<Option<Instant>> {
+        self.backoff.subscribe()
+    }
+
+    /// Wait until a connection fails to connect, or return right away if one did since the last
+    /// call.
+    pub(crate) fn connection_failed(&self) -> Notified<'_> {
+        self.failures.notified()
+    }
+
+    /// Move the channel to `Shutdown`, which it never leaves.
+    pub(crate) fn shutdown(&self) {
+        self.update(|connections| connections.is_shutdown = true);
+    }
+
+    /// Track a connection open to a server until the returned handle is dropped.
+    pub(crate) fn open_connection(self: &Arc<Self>) -> OpenConnection {
+        let channel = self.root();
+        channel.open.send_modify(|open| *open += 1);
+        let closed = channel.closed.subscribe();
+        OpenConnection { channel, closed }
+    }
+
+    /// Wait until every connection open to a server is closed.
+    pub(crate) async fn closed(&self) {
+        let mut open = self.open.subscribe();
+        while *open.borrow_and_update() > 0 {
+            if open.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Close the connections open to a server, cutting off their calls.
+    pub(crate) fn close_connections(&self) {
+        self.closed.send_replace(true);
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut Connections) -> T) -> T {
+        let mut connections = self.connections.lock().unwrap();
+        let result = f(&mut connections);
+
+        let (state, backoff) = connections.aggregate();
+        let state_changed = self
+            .state
+            .send_if_modified(|current| replace(current, state));
+        let backoff_changed = self
+            .backoff
+            .send_if_modified(|current| replace(current, backoff));
+
+        if let Some(parent) = &self.parent {
+            if state_changed || backoff_changed {
+                parent.set(state, backoff);
+            }
+        }
+
+        result
+    }
+}
+
+impl Connections {
+    fn aggregate(&self) -> (ConnectivityState, Option<Instant>) {
+        if self.is_shutdown {
+            return (ConnectivityState::Shutdown, None);
+        }
+
+        let has = |state| self.states.values().any(|(s, _)| *s == state);
+        let state = if has(ConnectivityState::Ready) {
+            ConnectivityState::Ready
+        } else if has(ConnectivityState::Connecting) {
+            ConnectivityState::Connecting
+        } else if self.states.is_empty() || has(ConnectivityState::Idle) {
+            ConnectivityState::Idle
+        } else {
+            ConnectivityState::TransientFailure
+        };
+
+        let backoff = self
+            .states
+            .values()
+            .map(|(_, until)| *until)
+            .min()
+            .flatten();
+
+        (state, backoff)
+    }
+}
+
+fn replace<T: PartialEq>(current: &mut T, value: T) -> bool {
+    let modified = *current != value;
+    *current = value;
+    modified
+}
+
+/// The connectivity state of one connection of a channel.
+#[derive(Debug)]
+pub(crate) struct ConnectionState {
+    channel: Arc<ChannelState>,
+    id: u64,
+    is_channel: bool,
+}
+
+impl ConnectionState {
+    /// Watch the state of the channel at the root of nested channels, which is `Shutdown` once
+    /// the connection shouldn't connect or send calls anymore.
+    pub(crate) fn watch_channel(&self) -> watch::Receiver<ConnectivityState> {
+        self.channel.root().watch()
+    }
+
+    /// Whether the channel at the root of nested channels shut down.
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.channel.root().get() == ConnectivityState::Shutdown
+    }
+
+    /// The channel at the root of nested channels, which tracks the connections open to servers.
+    pub(crate) fn root_channel(&self) -> Arc<ChannelState> {
+        self.channel.root()
+    }
+
+    /// A handle reading the state of the connection.
+    pub(crate) fn reader(&self) -> ConnectionStateReader {
+        ConnectionStateReader {
+            channel: self.channel.clone(),
+            id: self.id,
+        }
+    }
+
+    /// Set the state of the connection, and when it is backing off, the end of its backoff.
+    pub(crate) fn set(&self, state: ConnectivityState, backoff_until: Option<Instant>) {
+        self.channel.update(|connections| {
+            connections.states.insert(self.id, (state, backoff_until));
+        });
+
+        if state == ConnectivityState::TransientFailure {
+            self.channel.failures.notify_one();
+        }
+    }
+}
+
+impl Drop for ConnectionState {
+    fn drop(&mut self) {
+        self.channel.update(|connections| {
+            connections.states.remove(&self.id);
+            if self.is_channel {
+                connections.is_shutdown = true;
+            }
+        });
+    }
+}
+
+/// A connection open to a server, closed once [`ChannelState::close_connections`] is called.
+#[derive(Debug)]
+pub(crate) struct OpenConnection {
+    channel: Arc<ChannelState>,
+    closed: watch::Receiver<bool>,
+}
+
+impl OpenConnection {
+    /// Wait until the connection should be closed.
+    pub(crate) fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut closed = self.closed.clone();
+        async move {
+            while !*closed.borrow_and_update() {
+                if closed.changed().await.is_err() {
+                    return std::future::pending().await;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.channel.open.send_modify(|open| *open -= 1);
+    }
+}
+
+/// Reads the connectivity state of one connection of a channel.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionStateReader {
+    channel: Arc<ChannelState>,
+    id: u64,
+}
+
+impl ConnectionStateReader {
+    /// Watch the state of the channel at the root of nested channels.
+    pub(crate) fn watch_channel(&self) -> watch::Receiver<ConnectivityState> {
+        self.channel.root().watch()
+    }
+
+    pub(crate) fn get(&self) -> ConnectivityState {
+        let connections = self.channel.connections.lock().unwrap();
+        match connections.states.get(&self.id) {
+            Some((state, _)) => *state,
+            None => ConnectivityState::Shutdown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn aggregates_best_state() {
+        let channel = ChannelState::new();
+        assert_eq!(channel.get(), ConnectivityState::Idle);
+
+        let a = channel.connection(false);
+        let b = channel.connection(false);
+
+        a.set(ConnectivityState::Connecting, None);
+        assert_eq!(channel.get(), ConnectivityState::Connecting);
+
+        b.set(ConnectivityState::Ready, None);
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+
+        drop(b);
+        assert_eq!(channel.get(), ConnectivityState::Connecting);
+
+        drop(a);
+        assert_eq!(channel.get(), ConnectivityState::Idle);
+    }
+
+    #[test]
+    fn backs_off_only_when_every_connection_does() {
+        let channel = ChannelState::new();
+        let backoff = channel.watch_backoff();
+        let now = Instant::now();
+
+        let a = channel.connection(false);
+        let b = channel.connection(false);
+
+        a.set(
+            ConnectivityState::TransientFailure,
+            Some(now + Duration::from_secs(2)),
+        );
+        assert_eq!(channel.get(), ConnectivityState::Idle);
+        assert_eq!(*backoff.borrow(), None);
+
+        b.set(
+            ConnectivityState::TransientFailure,
+            Some(now + Duration::from_secs(1)),
+        );
+        assert_eq!(channel.get(), ConnectivityState::TransientFailure);
+        assert_eq!(*backoff.borrow(), Some(now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn shuts_down_with_its_only_connection() {
+        let channel = ChannelState::new();
+        let mut state = channel.watch();
+
+        let connection = channel.connection(true);
+        connection.set(ConnectivityState::Ready, None);
+        assert_eq!(*state.borrow_and_update(), ConnectivityState::Ready);
+
+        drop(connection);
+        assert!(state.has_changed().unwrap());
+        assert_eq!(*state.borrow(), ConnectivityState::Shutdown);
+    }
+
+    #[test]
+    fn nested_connections_report_to_parent() {
+        let channel = ChannelState::new();
+        let nested = ChannelState::nested(channel.connection(true));
+
+        let a = nested.connection(false);
+        let b = nested.connection(false);
+        a.set(ConnectivityState::Connecting, None);
+        assert_eq!(channel.get(), ConnectivityState::Connecting);
+        b.set(ConnectivityState::Ready, None);
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+
+        drop((a, b));
+        assert_eq!(channel.get(), ConnectivityState::Idle);
+
+        drop(nested);
+        assert_eq!(channel.get(), ConnectivityState::Shutdown);
+    }
+
+    #[tokio::test]
+    async fn closed_once_open_connections_are() {
+        let channel = ChannelState::new();
+        let nested = ChannelState::nested(channel.connection(true));
+
+        let a = channel.open_connection();
+        let b = nested.open_connection();
+        let closing = b.closed();
+        channel.close_connections();
+        closing.await;
+
+        drop((a, b));
+        channel.closed().await;
+    }
+}
@@ -17,6 +17,9 @@ pub(crate) enum Kind {
     Transport,
     InvalidUri,
     InvalidUserAgent,
+    #[cfg(feature = "service-config")]
+    InvalidServiceConfig,
+    InvalidTarget,
 }
 
 impl Error {
@@ -43,11 +46,23 @@ impl Error {
         Error::new(Kind::InvalidUserAgent)
     }
 
+    #[cfg(feature = "service-config")]
+    pub(crate) fn new_invalid_service_config(source: impl Into<Source>) -> Self {
+        Error::new(Kind::InvalidServiceConfig).with(source)
+    }
+
+    pub(crate) fn new_invalid_target(source: impl Into<Source>) -> Self {
+        Error::new(Kind::InvalidTarget).with(source)
+    }
+
     fn description(&self) -> &str {
         match &self.inner.kind {
             Kind::Transport => "transport error",
             Kind::InvalidUri => "invalid URI",
             Kind::InvalidUserAgent => "user agent is not a valid header value",
+            #[cfg(feature = "service-config")]
+            Kind::InvalidServiceConfig => "invalid service config",
+            Kind::InvalidTarget => "invalid target",
         }
     }
 }
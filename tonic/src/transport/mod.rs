@@ -97,7 +97,13 @@ mod tls;
 #[doc(inline)]
 #[cfg(feature = "channel")]
 #[cfg_attr(docsrs, doc(cfg(feature = "channel")))]
-pub use self::channel::{Channel, Endpoint};
+pub use self::channel::{
+    CallCredentials, Channel, ConnectivityState, Endpoint, HedgingPolicy, RetryPolicy,
+};
+#[doc(inline)]
+#[cfg(feature = "service-config")]
+#[cfg_attr(docsrs, doc(cfg(feature = "service-config")))]
+pub use self::channel::{MethodConfig, ServiceConfig};
 pub use self::error::Error;
 #[doc(inline)]
 pub use self::server::Server;
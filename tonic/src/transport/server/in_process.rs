@@ -0,0 +1,74 @@
+use crate::transport::{Channel, Endpoint};
+use http::Uri;
+use std::{future, io};
+use tokio::{
+    io::DuplexStream,
+    sync::mpsc::{self, UnboundedSender},
+};
+use tokio_stream::Stream;
+use tower::service_fn;
+
+/// The size of the buffers of the pipes between the clients and the server.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// A server running in the same process as its clients, which connect to it through in-memory
+/// pipes rather than sockets.
+///
+/// Created with [`Router::into_in_process`](super::Router::into_in_process). Calls go through
+/// the same HTTP/2 connections as over the network, so trailers, compression, metadata and
+/// cancellation behave the same. The server runs until the handle and every channel it created
+/// are dropped.
+///
+/// ```
+/// # use tonic::transport::{server::Routes, Server};
+/// # async fn f(routes: Routes) {
+/// let server = Server::builder()
+///     // .add_service(GreeterServer::new(greeter))
+///     .add_routes(routes)
+///     .into_in_process();
+///
+/// let channel = server.channel();
+/// // let client = GreeterClient::new(channel);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InProcess {
+    connections: UnboundedSender<DuplexStream>,
+}
+
+impl InProcess {
+    /// Create a handle to a server taking its connections from the returned stream.
+    pub(crate) fn new() -> (Self, impl Stream<Item = Result<DuplexStream, io::Error>>) {
+        let (connections, mut incoming) = mpsc::unbounded_channel();
+        let incoming = async_stream::stream! {
+            while let Some(io) = incoming.recv().await {
+                yield Ok(io);
+            }
+        };
+
+        (InProcess { connections }, incoming)
+    }
+
+    /// Create a channel to the server, which connects once it is first used.
+    pub fn channel(&self) -> Channel {
+        self.connect_lazy(&Endpoint::from(Uri::from_static("http://in-process")))
+    }
+
+    /// Create a channel to the server with the configuration of `endpoint`, which connects once
+    /// it is first used.
+    ///
+    /// The uri of the endpoint only sets the authority of the requests, and shouldn't use TLS.
+    pub fn connect_lazy(&self, endpoint: &Endpoint) -> Channel {
+        let connections = self.connections.clone();
+        endpoint.connect_with_connector_lazy(service_fn(move |_: Uri| {
+            let (client, server) = tokio::io::duplex(BUFFER_SIZE);
+            let connected = connections.send(server).map(|()| client).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    "in-process server stopped",
+                )
+            });
+            future::ready(connected)
+        }))
+    }
+}
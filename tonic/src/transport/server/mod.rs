@@ -1,6 +1,7 @@
 //! Server implementation and builder.
 
 mod conn;
+mod in_process;
 mod incoming;
 mod recover_error;
 #[cfg(feature = "tls")]
@@ -26,6 +27,7 @@ use super::service::TlsAcceptor;
 #[cfg(unix)]
 pub use unix::UdsConnectInfo;
 
+pub use in_process::InProcess;
 pub use incoming::TcpIncoming;
 
 #[cfg(feature = "tls")]
@@ -719,6 +721,40 @@ impl<L> Router<L> {
             .await
     }
 
+    /// Run the server on the current [tokio] runtime for clients in the same process, which
+    /// connect to it without sockets through the returned [`InProcess`] handle.
+    ///
+    /// This method discards any provided [`Server`] TCP and TLS configuration.
+    ///
+    /// [tokio]: https://docs.rs/tokio
+    pub fn into_in_process<ResBody>(self) -> InProcess
+    where
+        L: Layer<Routes> + Send + 'static,
+        L::Service: Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + 'static,
+        <<L as Layer<Routes>>::Service as Service<Request<Body>>>::Future: Send + 'static,
+        <<L as Layer<Routes>>::Service as Service<Request<Body>>>::Error: Into<crate::Error> + Send,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::Error>,
+    {
+        let router = self;
+        #[cfg(feature = "tls")]
+        let router = Router {
+            server: Server {
+                tls: None,
+                ..router.server
+            },
+            ..router
+        };
+
+        let (in_process, incoming) = InProcess::new();
+        tokio::spawn(async move {
+            if let Err(error) = router.serve_with_incoming(incoming).await {
+                tracing::debug!("in-process server failed: {}", error);
+            }
+        });
+        in_process
+    }
+
     /// Create a tower service out of a router.
     pub fn into_service<ResBody>(self) -> L::Service
     where
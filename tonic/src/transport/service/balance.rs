@@ -0,0 +1,341 @@
+use super::connection::{Connection, Request, Response};
+use super::executor::{Executor, SharedExec};
+use crate::transport::{
+    channel::{
+        CallTracker, ConnectivityState, HealthCheck, HealthStream, LoadBalancingPolicy, Subchannel,
+    },
+    BoxFuture, Channel,
+};
+use std::{
+    cell::Cell,
+    fmt,
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::{mpsc, watch};
+use tower::discover::{Change, Discover};
+use tower_service::Service;
+use tracing::{debug, trace};
+
+/// Balances calls over the connections of a [`Discover`] following a [`LoadBalancingPolicy`].
+pub(crate) struct Balance<D: Discover, P> {
+    discover: D,
+    policy: P,
+    /// Spawns the tasks watching the health of subchannels.
+    executor: SharedExec,
+    /// The connections in the order they were discovered.
+    subchannels: Vec<Entry<D::Key>>,
+}
+
+struct Entry<K> {
+    key: K,
+    connection: Connection,
+    is_ready: bool,
+    /// Whether the connection failed the last time it was polled. It connects again once polled,
+    /// backing off between attempts.
+    has_failed: bool,
+    health: Health,
+}
+
+impl<K> Entry<K> {
+    /// Whether calls can be sent on the subchannel.
+    fn is_usable(&self) -> bool {
+        self.is_ready && self.health.is_serving() == Some(true)
+    }
+
+    /// The state of the subchannel seen by the policy, in which a connected subchannel that isn't
+    /// serving is still connecting until its first health report, then failing.
+    fn state(&self) -> ConnectivityState {
+        if self.has_failed {
+            return ConnectivityState::TransientFailure;
+        }
+
+        match (self.connection.state(), self.health.is_serving()) {
+            (ConnectivityState::Ready, None) => ConnectivityState::Connecting,
+            (ConnectivityState::Ready, Some(false)) => ConnectivityState::TransientFailure,
+            (state, _) => state,
+        }
+    }
+}
+
+/// The health of a subchannel, see [`HealthCheck`].
+enum Health {
+    /// The endpoint of the subchannel has no health check.
+    Unchecked,
+    /// The health check starts once the subchannel is asked to connect.
+    Idle(Arc<dyn HealthCheck>, Channel),
+    Watching {
+        /// Receives the health reported by the task watching it.
+        reports: mpsc::UnboundedReceiver<bool>,
+        is_serving: Option<bool>,
+    },
+}
+
+impl Health {
+    /// Whether the subchannel is serving, `None` until it is first reported.
+    fn is_serving(&self) -> Option<bool> {
+        match self {
+            Health::Unchecked => Some(true),
+            Health::Idle(..) => None,
+            Health::Watching { is_serving, .. } => *is_serving,
+        }
+    }
+
+    /// Start the health check of `connection` if it hasn't started, watching it on a task
+    /// spawned on `executor`.
+    fn start(&mut self, connection: &Connection, executor: &SharedExec) {
+        if let Health::Idle(check, channel) = self {
+            let stream = check.watch(channel.clone());
+            let (health, reports) = mpsc::unbounded_channel();
+            executor.execute(Box::pin(watch_health(
+                stream,
+                health,
+                connection.watch_channel(),
+            )));
+            *self = Health::Watching {
+                reports,
+                is_serving: None,
+            };
+        }
+    }
+
+    /// Record the health reported since the last poll.
+    fn poll(&mut self, cx: &mut Context<'_>) {
+        if let Health::Watching {
+            reports,
+            is_serving,
+        } = self
+        {
+            while let Poll::Ready(Some(serving)) = reports.poll_recv(cx) {
+                *is_serving = Some(serving);
+            }
+        }
+    }
+}
+
+/// Send the health `stream` reports to `health` until the subchannel is removed, the stream ends
+/// or the channel shuts down, ending the calls of the health check.
+async fn watch_health(
+    mut stream: HealthStream,
+    health: mpsc::UnboundedSender<bool>,
+    mut channel: watch::Receiver<ConnectivityState>,
+) {
+    let shutdown = async {
+        while *channel.borrow_and_update() != ConnectivityState::Shutdown {
+            if channel.changed().await.is_err() {
+                return;
+            }
+        }
+    };
+    let removed = health.closed();
+    tokio::pin!(shutdown, removed);
+
+    poll_fn(|cx| {
+        if shutdown.as_mut().poll(cx).is_ready() || removed.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(());
+        }
+        loop {
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(serving)) => {
+                    let _ = health.send(serving);
+                }
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    })
+    .await
+}
+
+impl<D, P> Balance<D, P>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Error: Into<crate::Error>,
+    P: LoadBalancingPolicy,
+{
+    pub(crate) fn new(discover: D, policy: P, executor: SharedExec) -> Self {
+        Balance {
+            discover,
+            policy,
+            executor,
+            subchannels: Vec::new(),
+        }
+    }
+
+    fn update_pending_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), crate::Error> {
+        loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending | Poll::Ready(None) => return Ok(()),
+                Poll::Ready(Some(change)) => match change.map_err(Into::into)? {
+                    Change::Insert(key, connection) => {
+                        trace!("insert subchannel {}", connection.uri());
+                        let (connection, health) = match connection.health_check() {
+                            Some(check) => {
+                                let (connection, channel) = connection.share(&self.executor);
+                                (connection, Health::Idle(check, channel))
+                            }
+                            None => (connection, Health::Unchecked),
+                        };
+                        let entry = Entry {
+                            key,
+                            connection,
+                            is_ready: false,
+                            has_failed: false,
+                            health,
+                        };
+                        match self.position(&entry.key) {
+                            Some(index) => self.subchannels[index] = entry,
+                            None => self.subchannels.push(entry),
+                        }
+                    }
+                    Change::Remove(key) => {
+                        if let Some(index) = self.position(&key) {
+                            let entry = self.subchannels.remove(index);
+                            trace!("remove subchannel {}", entry.connection.uri());
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn position(&self, key: &D::Key) -> Option<usize> {
+        self.subchannels.iter().position(|entry| entry.key == *key)
+    }
+
+    /// Ask the policy which subchannels to connect and poll them, until it asks for no new one.
+    fn connect_subchannels(&mut self, cx: &mut Context<'_>) {
+        let mut polled = vec![false; self.subchannels.len()];
+
+        for entry in &mut self.subchannels {
+            entry.health.poll(cx);
+        }
+
+        loop {
+            let connect: Vec<_> = self.subchannels.iter().map(|_| Cell::new(false)).collect();
+            self.policy
+                .connect(&subchannels(&self.subchannels, &connect));
+
+            let mut polled_any = false;
+            for (index, entry) in self.subchannels.iter_mut().enumerate() {
+                if !connect[index].get() || polled[index] {
+                    continue;
+                }
+                polled[index] = true;
+                polled_any = true;
+
+                entry.health.start(&entry.connection, &self.executor);
+                entry.health.poll(cx);
+                let ready = entry.connection.poll_ready(cx);
+                if let Poll::Ready(Err(error)) = &ready {
+                    debug!("subchannel {} failed: {}", entry.connection.uri(), error);
+                }
+                entry.is_ready = matches!(ready, Poll::Ready(Ok(())));
+                entry.has_failed = matches!(ready, Poll::Ready(Err(_)));
+            }
+
+            if !polled_any {
+                break;
+            }
+        }
+
+        // Subchannels the policy no longer wants to use aren't ready until polled again.
+        for (entry, polled) in self.subchannels.iter_mut().zip(polled) {
+            entry.is_ready &= polled;
+        }
+    }
+}
+
+fn subchannels<'a, K>(entries: &'a [Entry<K>], connect: &'a [Cell<bool>]) -> Vec<Subchannel<'a>> {
+    entries
+        .iter()
+        .zip(connect)
+        .map(|(entry, connect)| Subchannel {
+            uri: entry.connection.uri(),
+            state: entry.state(),
+            is_ready: entry.is_usable(),
+            connect,
+        })
+        .collect()
+}
+
+impl<D, P> Service<Request> for Balance<D, P>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Error: Into<crate::Error>,
+    P: LoadBalancingPolicy,
+{
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_pending_from_discover(cx)?;
+        self.connect_subchannels(cx);
+
+        if self.subchannels.iter().any(Entry::is_usable) {
+            Poll::Ready(Ok(()))
+        } else {
+            trace!("no ready subchannel");
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let connect: Vec<_> = self.subchannels.iter().map(|_| Cell::new(false)).collect();
+        let views = subchannels(&self.subchannels, &connect);
+        let picked = self
+            .policy
+            .pick(&request, &views)
+            .filter(
+                |index| matches!(self.subchannels.get(*index), Some(entry) if entry.is_usable()),
+            )
+            .or_else(|| self.subchannels.iter().position(Entry::is_usable))
+            .expect("no ready subchannel; poll_ready must be called first");
+        let tracker = self.policy.track_call(&views[picked]);
+        drop(views);
+
+        let entry = &mut self.subchannels[picked];
+        entry.is_ready = false;
+        let response = entry.connection.call(request);
+
+        match tracker {
+            Some(tracker) => Box::pin(track(response, tracker)),
+            None => response,
+        }
+    }
+}
+
+/// Hand the trailers of a call to `tracker`.
+///
+/// Unless the call failed right away, its trailers only come at the end of its body, which hands
+/// them to the tracker as they pass through.
+async fn track(
+    response: BoxFuture<'static, Result<Response, crate::Error>>,
+    tracker: Box<dyn CallTracker>,
+) -> Result<Response, crate::Error> {
+    let mut response = match response.await {
+        Ok(response) => response,
+        Err(error) => {
+            tracker.finish(None);
+            return Err(error);
+        }
+    };
+
+    if response.headers().contains_key("grpc-status") {
+        tracker.finish(Some(response.headers()));
+    } else {
+        response.body_mut().track(tracker);
+    }
+    Ok(response)
+}
+
+impl<D: Discover, P> fmt::Debug for Balance<D, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Balance")
+            .field("subchannels", &self.subchannels.len())
+            .finish()
+    }
+}
@@ -1,18 +1,30 @@
-use super::{grpc_timeout::GrpcTimeout, reconnect::Reconnect, AddOrigin, UserAgent};
+use super::{
+    grpc_timeout::GrpcTimeout,
+    pool::{Pool, Shared},
+    reconnect::Reconnect,
+    AddOrigin, SharedExec, TrackedIo, UserAgent,
+};
 use crate::{
     body::BoxBody,
-    transport::{BoxFuture, Endpoint},
+    transport::{
+        channel::{
+            ConnectionState, ConnectionStateReader, ConnectivityState, HealthCheck, ResponseBody,
+        },
+        BoxFuture, Channel, Endpoint,
+    },
+    Status,
 };
 use http::Uri;
 use hyper::client::conn::Builder;
 use hyper::client::connect::Connection as HyperConnection;
 use hyper::client::service::Connect as HyperConnect;
 use std::{
-    fmt,
+    fmt, future,
+    sync::Arc,
     task::{Context, Poll},
 };
 use tokio::io::{AsyncRead, AsyncWrite};
-use tower::load::Load;
+use tokio::sync::watch;
 use tower::{
     layer::Layer,
     limit::{concurrency::ConcurrencyLimitLayer, rate::RateLimitLayer},
@@ -22,14 +34,25 @@ use tower::{
 use tower_service::Service;
 
 pub(crate) type Request = http::Request<BoxBody>;
-pub(crate) type Response = http::Response<hyper::Body>;
+pub(crate) type Response = http::Response<ResponseBody>;
 
 pub(crate) struct Connection {
     inner: BoxService<Request, Response, crate::Error>,
+    uri: Uri,
+    state: ConnectionStateReader,
+    health_check: Option<Arc<dyn HealthCheck>>,
+    /// Why the calls of the connection fail without being sent, such as call credentials that
+    /// would go over plaintext.
+    refusal: Option<&'static str>,
 }
 
 impl Connection {
-    fn new<C>(connector: C, endpoint: Endpoint, is_lazy: bool) -> Self
+    fn new<C>(
+        connector: C,
+        endpoint: Endpoint,
+        is_lazy: bool,
+        connectivity: ConnectionState,
+    ) -> Self
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::Error> + Send,
@@ -68,34 +91,116 @@ impl Connection {
             .option_layer(endpoint.rate_limit.map(|(l, d)| RateLimitLayer::new(l, d)))
             .into_inner();
 
-        let connector = HyperConnect::new(connector, settings);
-        let conn = Reconnect::new(connector, endpoint.uri.clone(), is_lazy);
+        let uri = endpoint.uri.clone();
+        let state = connectivity.reader();
+        let health_check = endpoint.health_check.clone();
+        let refusal = if endpoint.call_credentials.is_some()
+            && !endpoint.insecure_call_credentials
+            && !endpoint.uses_tls()
+        {
+            Some("call credentials require a TLS connection")
+        } else {
+            None
+        };
+
+        let channel = connectivity.root_channel();
+        let connector =
+            connector.map_response(move |io| TrackedIo::new(io, channel.open_connection()));
+        let connector = HyperConnect::new(connector, settings).map_response(|send_request| {
+            send_request.map_response(|response: http::Response<hyper::Body>| {
+                response.map(ResponseBody::new)
+            })
+        });
+        let conn = match endpoint.connection_pool {
+            Some((min, max)) => {
+                let connector = Shared::new(connector);
+                let (uri, backoff) = (endpoint.uri.clone(), endpoint.backoff.clone());
+                let (idle_timeout, executor) = (endpoint.idle_timeout, endpoint.executor.clone());
+                let connect = Box::new(move |is_lazy, connectivity| {
+                    Reconnect::new(
+                        connector.clone(),
+                        uri.clone(),
+                        is_lazy,
+                        backoff.clone(),
+                        connectivity,
+                    )
+                    .closing(idle_timeout, executor.clone())
+                });
+                BoxService::new(Pool::new(min, max, is_lazy, connectivity, connect))
+            }
+            None => {
+                let conn = Reconnect::new(
+                    connector,
+                    endpoint.uri.clone(),
+                    is_lazy,
+                    endpoint.backoff.clone(),
+                    connectivity,
+                )
+                .closing(endpoint.idle_timeout, endpoint.executor.clone());
+                BoxService::new(conn)
+            }
+        };
 
         let inner = stack.layer(conn);
 
         Self {
             inner: BoxService::new(inner),
+            uri,
+            state,
+            health_check,
+            refusal,
         }
     }
 
-    pub(crate) async fn connect<C>(connector: C, endpoint: Endpoint) -> Result<Self, crate::Error>
+    pub(crate) async fn connect<C>(
+        connector: C,
+        endpoint: Endpoint,
+        connectivity: ConnectionState,
+    ) -> Result<Self, crate::Error>
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::Error> + Send,
         C::Future: Unpin + Send,
         C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
     {
-        Self::new(connector, endpoint, false).ready_oneshot().await
+        Self::new(connector, endpoint, false, connectivity)
+            .ready_oneshot()
+            .await
     }
 
-    pub(crate) fn lazy<C>(connector: C, endpoint: Endpoint) -> Self
+    pub(crate) fn lazy<C>(connector: C, endpoint: Endpoint, connectivity: ConnectionState) -> Self
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::Error> + Send,
         C::Future: Unpin + Send,
         C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
     {
-        Self::new(connector, endpoint, true)
+        Self::new(connector, endpoint, true, connectivity)
+    }
+
+    pub(crate) fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    pub(crate) fn state(&self) -> ConnectivityState {
+        self.state.get()
+    }
+
+    /// Watch the state of the channel of the connection.
+    pub(crate) fn watch_channel(&self) -> watch::Receiver<ConnectivityState> {
+        self.state.watch_channel()
+    }
+
+    /// The health check of the endpoint of the connection.
+    pub(crate) fn health_check(&self) -> Option<Arc<dyn HealthCheck>> {
+        self.health_check.clone()
+    }
+
+    /// Share the connection with a channel sending its calls on it.
+    pub(crate) fn share(mut self, executor: &SharedExec) -> (Self, Channel) {
+        let (inner, channel) = Channel::share(self.inner, executor);
+        self.inner = BoxService::new(inner);
+        (self, channel)
     }
 }
 
@@ -109,20 +214,19 @@ impl Service<Request> for Connection {
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
-        self.inner.call(req)
-    }
-}
-
-impl Load for Connection {
-    type Metric = usize;
+        if let Some(reason) = self.refusal {
+            let status = Status::unauthenticated(reason);
+            return Box::pin(future::ready(Err(status.into())));
+        }
 
-    fn load(&self) -> Self::Metric {
-        0
+        self.inner.call(req)
     }
 }
 
 impl fmt::Debug for Connection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Connection").finish()
+        f.debug_struct("Connection")
+            .field("uri", &self.uri)
+            .finish()
     }
 }
@@ -1,5 +1,6 @@
 use super::super::BoxFuture;
 use super::io::BoxedIo;
+use super::proxy::{self, Proxy};
 #[cfg(feature = "tls")]
 use super::tls::TlsConnector;
 use http::Uri;
@@ -25,17 +26,31 @@ pub(crate) struct Connector<C> {
     #[cfg(not(feature = "tls"))]
     #[allow(dead_code)]
     tls: Option<()>,
+    proxy: Option<Proxy>,
 }
 
 impl<C> Connector<C> {
     #[cfg(not(feature = "tls"))]
     pub(crate) fn new(inner: C) -> Self {
-        Self { inner, tls: None }
+        Self {
+            inner,
+            tls: None,
+            proxy: None,
+        }
     }
 
     #[cfg(feature = "tls")]
     fn new(inner: C, tls: Option<TlsConnector>) -> Self {
-        Self { inner, tls }
+        Self {
+            inner,
+            tls,
+            proxy: None,
+        }
+    }
+
+    /// Connect through `proxy`, when set.
+    pub(crate) fn proxy(self, proxy: Option<Proxy>) -> Self {
+        Self { proxy, ..self }
     }
 
     #[cfg(feature = "tls-roots-common")]
@@ -79,10 +94,17 @@ where
 
         #[cfg(feature = "tls")]
         let is_https = uri.scheme_str() == Some("https");
-        let connect = self.inner.make_connection(uri);
+        let proxy = self.proxy.as_ref().and_then(|proxy| proxy.for_uri(&uri));
+        let connect = self
+            .inner
+            .make_connection(proxy.clone().unwrap_or_else(|| uri.clone()));
 
         Box::pin(async move {
             let io = connect.await?;
+            let io = match proxy {
+                Some(proxy) => proxy::tunnel(io, &uri, &proxy).await?,
+                None => io,
+            };
 
             #[cfg(feature = "tls")]
             {
@@ -1,26 +1,70 @@
 use super::super::service;
 use super::connection::Connection;
-use crate::transport::Endpoint;
+use crate::transport::{
+    channel::{Address, CallCredentials, ChannelState},
+    Endpoint,
+};
+use http::Uri;
 
 use std::{
     hash::Hash,
+    io,
+    path::PathBuf,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 use tokio::sync::mpsc::Receiver;
 
 use tokio_stream::Stream;
-use tower::discover::Change;
+use tower::{discover::Change, service_fn};
+use tracing::error;
 
 type DiscoverResult<K, S, E> = Result<Change<K, S>, E>;
 
 pub(crate) struct DynamicServiceStream<K: Hash + Eq + Clone> {
     changes: Receiver<Change<K, Endpoint>>,
+    state: Arc<ChannelState>,
+    /// The call credentials the channel attaches to its calls.
+    call_credentials: Option<Arc<dyn CallCredentials>>,
+    insecure_call_credentials: bool,
 }
 
 impl<K: Hash + Eq + Clone> DynamicServiceStream<K> {
-    pub(crate) fn new(changes: Receiver<Change<K, Endpoint>>) -> Self {
-        Self { changes }
+    pub(crate) fn new(changes: Receiver<Change<K, Endpoint>>, state: Arc<ChannelState>) -> Self {
+        Self {
+            changes,
+            state,
+            call_credentials: None,
+            insecure_call_credentials: false,
+        }
+    }
+
+    /// Connect to the endpoints knowing the channel attaches the call credentials of `endpoint`
+    /// to its calls.
+    pub(crate) fn with_call_credentials(mut self, endpoint: &Endpoint) -> Self {
+        self.call_credentials = endpoint.call_credentials.clone();
+        self.insecure_call_credentials = endpoint.insecure_call_credentials;
+        self
+    }
+
+    /// Whether `endpoint` has call credentials of its own, which the channel doesn't attach to
+    /// its calls.
+    fn has_own_call_credentials(&self, endpoint: &Endpoint) -> bool {
+        match (&endpoint.call_credentials, &self.call_credentials) {
+            (Some(own), Some(channel)) => {
+                Arc::as_ptr(own) as *const () != Arc::as_ptr(channel) as *const ()
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Give `endpoint` the call credentials of the channel, so that its connection refuses the
+    /// calls when they may not be sent over it.
+    fn take_call_credentials(&self, endpoint: &mut Endpoint) {
+        endpoint.call_credentials = self.call_credentials.clone();
+        endpoint.insecure_call_credentials = self.insecure_call_credentials;
     }
 }
 
@@ -31,19 +75,46 @@ impl<K: Hash + Eq + Clone> Stream for DynamicServiceStream<K> {
         let c = &mut self.changes;
         match Pin::new(&mut *c).poll_recv(cx) {
             Poll::Pending | Poll::Ready(None) => Poll::Pending,
+            Poll::Ready(Some(Change::Insert(_, endpoint)))
+                if self.has_own_call_credentials(&endpoint) =>
+            {
+                error!(
+                    "ignoring endpoint {}: the call credentials of the endpoints of a balanced \
+                     channel must be set on the endpoint the channel is built from",
+                    endpoint.uri
+                );
+                // Look at the next change.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
             Poll::Ready(Some(change)) => match change {
-                Change::Insert(k, endpoint) => {
-                    let mut http = hyper::client::connect::HttpConnector::new();
-                    http.set_nodelay(endpoint.tcp_nodelay);
-                    http.set_keepalive(endpoint.tcp_keepalive);
-                    http.set_connect_timeout(endpoint.connect_timeout);
-                    http.enforce_http(false);
-                    #[cfg(feature = "tls")]
-                    let connector = service::connector(http, endpoint.tls.clone());
-
-                    #[cfg(not(feature = "tls"))]
-                    let connector = service::connector(http);
-                    let connection = Connection::lazy(connector, endpoint);
+                Change::Insert(k, mut endpoint) => {
+                    self.take_call_credentials(&mut endpoint);
+                    let connection = match endpoint.address.clone() {
+                        Some(Address::Unix(path)) => {
+                            let connector = service_fn(move |_: Uri| connect_unix(path.clone()));
+                            #[cfg(feature = "tls")]
+                            let connector = service::connector(connector, endpoint.tls.clone());
+
+                            #[cfg(not(feature = "tls"))]
+                            let connector = service::connector(connector);
+                            Connection::lazy(connector, endpoint, self.state.connection(false))
+                        }
+                        _ => {
+                            let mut http = hyper::client::connect::HttpConnector::new();
+                            http.set_nodelay(endpoint.tcp_nodelay);
+                            http.set_keepalive(endpoint.tcp_keepalive);
+                            http.set_connect_timeout(endpoint.connect_timeout);
+                            http.enforce_http(false);
+                            #[cfg(feature = "tls")]
+                            let connector = service::connector(http, endpoint.tls.clone());
+
+                            #[cfg(not(feature = "tls"))]
+                            let connector = service::connector(http);
+                            let connector = connector.proxy(endpoint.proxy.clone());
+                            Connection::lazy(connector, endpoint, self.state.connection(false))
+                        }
+                    };
                     let change = Ok(Change::Insert(k, connection));
                     Poll::Ready(Some(change))
                 }
@@ -53,4 +124,90 @@ impl<K: Hash + Eq + Clone> Stream for DynamicServiceStream<K> {
     }
 }
 
+#[cfg(unix)]
+async fn connect_unix(path: PathBuf) -> io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+#[cfg(not(unix))]
+async fn connect_unix(_path: PathBuf) -> io::Result<tokio::net::TcpStream> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not supported on this platform",
+    ))
+}
+
 impl<K: Hash + Eq + Clone> Unpin for DynamicServiceStream<K> {}
+
+impl<K: Hash + Eq + Clone> Drop for DynamicServiceStream<K> {
+    fn drop(&mut self) {
+        // The balancer owning the endpoints is gone.
+        self.state.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::empty_body, transport::channel::StaticToken, Code, Status};
+    use tokio::sync::mpsc;
+    use tokio_stream::StreamExt;
+    use tower::Service;
+
+    async fn connect(
+        discover: &mut DynamicServiceStream<usize>,
+        tx: &mpsc::Sender<Change<usize, Endpoint>>,
+        endpoint: Endpoint,
+    ) -> Connection {
+        tx.send(Change::Insert(0, endpoint)).await.unwrap();
+        match discover.next().await {
+            Some(Ok(Change::Insert(_, connection))) => connection,
+            _ => panic!("expected a connection"),
+        }
+    }
+
+    /// Send a call like the later attempts of a retried or hedged call, which only carry the
+    /// headers and the body of the call.
+    async fn refusal(mut connection: Connection) -> Status {
+        let request = http::Request::new(empty_body());
+        let error = connection.call(request).await.unwrap_err();
+        *error.downcast::<Status>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn plaintext_endpoints_refuse_calls_with_credentials() {
+        let (tx, rx) = mpsc::channel(1);
+        let template = Endpoint::from_static("https://example.com")
+            .call_credentials(StaticToken::bearer("secret").unwrap());
+        let mut discover =
+            DynamicServiceStream::new(rx, ChannelState::new()).with_call_credentials(&template);
+
+        let endpoint = Endpoint::from_static("http://127.0.0.1:50051");
+        let status = refusal(connect(&mut discover, &tx, endpoint).await).await;
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(
+            status.message(),
+            "call credentials require a TLS connection"
+        );
+    }
+
+    #[tokio::test]
+    async fn endpoints_with_their_own_credentials_are_ignored() {
+        let (tx, rx) = mpsc::channel(2);
+        let mut discover = DynamicServiceStream::new(rx, ChannelState::new());
+
+        let endpoint = Endpoint::from_static("https://example.com")
+            .call_credentials(StaticToken::bearer("secret").unwrap());
+        tx.send(Change::Insert(0, endpoint)).await.unwrap();
+        let endpoint = Endpoint::from_static("https://example.org");
+        tx.send(Change::Insert(1, endpoint)).await.unwrap();
+
+        match discover.next().await {
+            Some(Ok(Change::Insert(key, connection))) => {
+                assert_eq!(key, 1);
+                assert_eq!(connection.uri(), "https://example.org/");
+            }
+            _ => panic!("expected a connection"),
+        }
+    }
+}
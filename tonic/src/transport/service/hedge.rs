@@ -0,0 +1,261 @@
+use super::retry::{classify, Attempts, Outcome, Pushback};
+use crate::{
+    body::BoxBody,
+    transport::{
+        channel::{HedgingPolicy, ResponseBody},
+        BoxFuture,
+    },
+};
+use http::{Request, Response};
+use std::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::Poll,
+    time::Duration,
+};
+use tokio::time::{sleep, Sleep};
+use tower_service::Service;
+use tracing::debug;
+
+enum Event {
+    /// The attempt at this index completed.
+    Completed(usize, Result<Response<ResponseBody>, crate::Error>),
+    /// The next attempt is due.
+    Hedge,
+}
+
+/// Send the attempts of a call according to a [`HedgingPolicy`], handing the first result that
+/// isn't a non-fatal failure to the caller.
+pub(super) async fn hedge<S>(
+    attempts: Attempts<S>,
+    policy: HedgingPolicy,
+    first: S::Future,
+) -> Result<Response<ResponseBody>, crate::Error>
+where
+    S: Service<Request<BoxBody>, Response = Response<ResponseBody>, Error = crate::Error>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let mut in_flight: Vec<BoxFuture<'static, Result<Response<ResponseBody>, crate::Error>>> =
+        vec![Box::pin(first)];
+    let mut sent = 1;
+    let mut next_hedge = hedge_timer(policy.hedging_delay);
+    let mut last_failure = None;
+
+    loop {
+        let event = poll_fn(|cx| {
+            for (index, attempt) in in_flight.iter_mut().enumerate() {
+                if let Poll::Ready(result) = attempt.as_mut().poll(cx) {
+                    return Poll::Ready(Event::Completed(index, result));
+                }
+            }
+
+            match &mut next_hedge {
+                Some(timer) => timer.as_mut().poll(cx).map(|()| Event::Hedge),
+                None => Poll::Pending,
+            }
+        })
+        .await;
+
+        match event {
+            Event::Hedge => {
+                next_hedge = None;
+
+                if sent < policy.max_attempts && attempts.may_send(Duration::ZERO) {
+                    debug!(attempt = sent, "hedging call");
+                    in_flight.push(Box::pin(attempts.send(sent)));
+                    sent += 1;
+                    next_hedge = hedge_timer(policy.hedging_delay);
+                } else {
+                    // No further attempts will be sent.
+                    sent = policy.max_attempts;
+                }
+            }
+            Event::Completed(index, result) => {
+                drop(in_flight.swap_remove(index));
+
+                let outcome = classify(&result, |code| policy.is_non_fatal(code));
+                attempts.record(&outcome);
+
+                match outcome {
+                    // Returning drops, and so cancels, the other attempts.
+                    Outcome::Committed { .. } => return result,
+                    Outcome::Failed { pushback } => {
+                        next_hedge = match pushback {
+                            // Don't wait for the hedging delay to replace the failed attempt.
+                            Pushback::None => hedge_timer(Duration::ZERO),
+                            Pushback::Delay(delay) => hedge_timer(delay),
+                            Pushback::Stop => {
+                                sent = policy.max_attempts;
+                                None
+                            }
+                        };
+                    }
+                }
+
+                last_failure = Some(result);
+            }
+        }
+
+        if sent >= policy.max_attempts {
+            next_hedge = None;
+        }
+
+        if in_flight.is_empty() && next_hedge.is_none() {
+            return last_failure.expect("every attempt failed");
+        }
+    }
+}
+
+fn hedge_timer(delay: Duration) -> Option<Pin<Box<Sleep>>> {
+    Some(Box::pin(sleep(delay)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        body::BoxBody,
+        transport::{
+            channel::{HedgingPolicy, ResponseBody, RetryConfig},
+            service::Retry,
+        },
+        Code, Status,
+    };
+    use http::{HeaderValue, Request, Response};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::time::Instant;
+    use tower::{service_fn, ServiceExt};
+
+    /// A service whose `n`th call completes with the code of `replies[n]` after its latency.
+    fn service(
+        replies: Vec<(Duration, Code)>,
+        calls: Arc<AtomicUsize>,
+    ) -> impl tower_service::Service<
+        Request<BoxBody>,
+        Response = Response<ResponseBody>,
+        Error = crate::Error,
+        Future = impl Send,
+    > + Clone
+           + Send
+           + 'static {
+        service_fn(move |_: Request<BoxBody>| {
+            let (latency, code) = replies[calls.fetch_add(1, Ordering::SeqCst)];
+            async move {
+                tokio::time::sleep(latency).await;
+                let mut response = Response::new(ResponseBody::new(hyper::Body::empty()));
+                if code != Code::Ok {
+                    response
+                        .headers_mut()
+                        .insert("grpc-status", HeaderValue::from(code as i32));
+                }
+                Ok(response)
+            }
+        })
+    }
+
+    fn request() -> Request<BoxBody> {
+        let mut request = Request::new(crate::body::boxed(hyper::Body::from("request")));
+        *request.uri_mut() = http::Uri::from_static("/test.Test/Unary");
+        request
+    }
+
+    fn config(policy: HedgingPolicy) -> RetryConfig {
+        let mut config = RetryConfig::default();
+        config.set_default_policy(policy);
+        config
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fastest_attempt_wins() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(
+            vec![
+                (Duration::from_secs(10), Code::Ok),
+                (Duration::from_secs(1), Code::Ok),
+            ],
+            calls.clone(),
+        );
+        let policy = HedgingPolicy::new().hedging_delay(Duration::from_millis(100));
+        let svc = Retry::new(svc, config(policy));
+
+        let start = Instant::now();
+        svc.oneshot(request()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn no_hedge_when_first_attempt_is_fast() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Duration::from_millis(10), Code::Ok)], calls.clone());
+        let policy = HedgingPolicy::new().hedging_delay(Duration::from_millis(100));
+        let svc = Retry::new(svc, config(policy));
+
+        svc.oneshot(request()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn non_fatal_failure_sends_next_attempt_right_away() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(
+            vec![
+                (Duration::from_millis(10), Code::Unavailable),
+                (Duration::from_millis(10), Code::Ok),
+            ],
+            calls.clone(),
+        );
+        let policy = HedgingPolicy::new()
+            .hedging_delay(Duration::from_secs(10))
+            .non_fatal_status_codes([Code::Unavailable]);
+        let svc = Retry::new(svc, config(policy));
+
+        let start = Instant::now();
+        let response = svc.oneshot(request()).await.unwrap();
+        assert!(Status::from_header_map(response.headers()).is_none());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fatal_failure_is_returned() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(
+            vec![
+                (Duration::from_millis(10), Code::Internal),
+                (Duration::from_millis(10), Code::Ok),
+            ],
+            calls.clone(),
+        );
+        let svc = Retry::new(svc, config(HedgingPolicy::new().max_attempts(2)));
+
+        let response = svc.oneshot(request()).await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), Code::Internal);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn last_failure_is_returned_when_all_attempts_fail() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(
+            vec![(Duration::from_millis(10), Code::Unavailable); 3],
+            calls.clone(),
+        );
+        let policy = HedgingPolicy::new()
+            .max_attempts(3)
+            .non_fatal_status_codes([Code::Unavailable]);
+        let svc = Retry::new(svc, config(policy));
+
+        let response = svc.oneshot(request()).await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::transport::channel::OpenConnection;
 use crate::transport::server::Connected;
+use crate::transport::BoxFuture;
 use hyper::client::connect::{Connected as HyperConnected, Connection};
 use std::io;
 use std::pin::Pin;
@@ -67,6 +69,90 @@ impl AsyncWrite for BoxedIo {
     }
 }
 
+/// The io of a connection of a channel, which stays open to the channel until it is dropped and
+/// fails once the channel closes its connections.
+pub(crate) struct TrackedIo<IO> {
+    io: IO,
+    closed: BoxFuture<'static, ()>,
+    _open: OpenConnection,
+}
+
+impl<IO> TrackedIo<IO> {
+    pub(crate) fn new(io: IO, open: OpenConnection) -> Self {
+        TrackedIo {
+            io,
+            closed: Box::pin(open.closed()),
+            _open: open,
+        }
+    }
+
+    fn poll_closed(&mut self, cx: &mut Context<'_>) -> io::Result<()> {
+        match self.closed.as_mut().poll(cx) {
+            Poll::Ready(()) => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "channel shut down",
+            )),
+            Poll::Pending => Ok(()),
+        }
+    }
+}
+
+impl<IO: Connection> Connection for TrackedIo<IO> {
+    fn connected(&self) -> HyperConnected {
+        self.io.connected()
+    }
+}
+
+impl<IO> AsyncRead for TrackedIo<IO>
+where
+    IO: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<IO> AsyncWrite for TrackedIo<IO>
+where
+    IO: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_closed(cx)?;
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
 pub(crate) enum ServerIo<IO> {
     Io(IO),
     #[cfg(feature = "tls")]
@@ -1,23 +1,33 @@
 mod add_origin;
+mod balance;
 mod connection;
 mod connector;
 mod discover;
 pub(crate) mod executor;
 pub(crate) mod grpc_timeout;
+mod hedge;
 mod io;
+mod pool;
+mod proxy;
 mod reconnect;
+mod replay_body;
+pub(crate) mod retry;
 mod router;
 #[cfg(feature = "tls")]
 mod tls;
 mod user_agent;
 
 pub(crate) use self::add_origin::AddOrigin;
+pub(crate) use self::balance::Balance;
 pub(crate) use self::connection::Connection;
 pub(crate) use self::connector::connector;
 pub(crate) use self::discover::DynamicServiceStream;
 pub(crate) use self::executor::SharedExec;
 pub(crate) use self::grpc_timeout::GrpcTimeout;
-pub(crate) use self::io::ServerIo;
+pub(crate) use self::io::{ServerIo, TrackedIo};
+pub(crate) use self::proxy::Proxy;
+pub(crate) use self::reconnect::{BackoffConfig, RunningCall};
+pub(crate) use self::retry::Retry;
 #[cfg(feature = "tls")]
 pub(crate) use self::tls::{TlsAcceptor, TlsConnector};
 pub(crate) use self::user_agent::UserAgent;
@@ -0,0 +1,430 @@
+use crate::transport::channel::{
+    ChannelState, ConnectionState, ConnectionStateReader, ConnectivityState,
+};
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll, Waker},
+};
+use tower_service::Service;
+use tracing::trace;
+
+/// A new connection, which takes a call when it is ready.
+const COLD: u8 = 0;
+/// A new connection waiting for the response to its first call before taking others.
+const WARMING: u8 = 1;
+/// A connection that received the settings of the server, which takes calls up to its limits.
+const WARM: u8 = 2;
+
+/// Opens a connection of a pool, lazily unless the first argument is false, reporting its state
+/// to the given [`ConnectionState`].
+pub(crate) type Connect<S> = Box<dyn FnMut(bool, ConnectionState) -> S + Send>;
+
+/// A pool of connections to a single endpoint.
+///
+/// Calls are spread across the ready connections in turn. A new connection opens when every
+/// connection is connected but none is ready to take a call, such as when each reached the
+/// maximum number of concurrent streams of the server, until the pool has `max` connections.
+/// A connection that received a GOAWAY reconnects, meanwhile the others take its calls.
+///
+/// Until a server's settings arrive the connection doesn't know its stream limit, so a new
+/// connection takes a single call until the response to that call arrives, after the settings.
+///
+/// The pool opens `min` connections once it is first polled, and closes connections beyond
+/// these once they go idle.
+pub(crate) struct Pool<S> {
+    connections: Vec<Member<S>>,
+    connect: Connect<S>,
+    state: Arc<ChannelState>,
+    min: usize,
+    max: usize,
+    /// Whether the next connection connects lazily, it only doesn't for the first connection of
+    /// a pool created with an eager connection.
+    is_lazy: bool,
+    /// The connection polled first for the next call.
+    next: usize,
+    /// The connection ready for the next call.
+    ready: Option<usize>,
+}
+
+struct Member<S> {
+    service: S,
+    state: ConnectionStateReader,
+    warmup: Arc<Warmup>,
+}
+
+/// Whether a connection of a pool is warm, with the task of the pool waiting for it to be.
+#[derive(Default)]
+struct Warmup {
+    state: AtomicU8,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Warmup {
+    fn get(&self) -> u8 {
+        self.state.load(Ordering::Acquire)
+    }
+
+    fn set(&self, state: u8) {
+        self.state.store(state, Ordering::Release);
+    }
+
+    /// End the warmup of a connection with its first call, warm when the call succeeded.
+    fn finish(&self, is_warm: bool) {
+        let state = if is_warm { WARM } else { COLD };
+        let _ = self
+            .state
+            .compare_exchange(WARMING, state, Ordering::AcqRel, Ordering::Acquire);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<S> Pool<S> {
+    pub(crate) fn new(
+        min: usize,
+        max: usize,
+        is_lazy: bool,
+        connectivity: ConnectionState,
+        connect: Connect<S>,
+    ) -> Self {
+        let max = max.max(1);
+        Pool {
+            connections: Vec::new(),
+            connect,
+            state: ChannelState::nested(connectivity),
+            min: min.min(max),
+            max,
+            is_lazy,
+            next: 0,
+            ready: None,
+        }
+    }
+
+    fn open(&mut self) {
+        trace!("opening connection {} of pool", self.connections.len() + 1);
+        let connectivity = self.state.connection(false);
+        let reader = connectivity.reader();
+        let service = (self.connect)(self.is_lazy, connectivity);
+        self.is_lazy = true;
+        self.connections.push(Member {
+            service,
+            state: reader,
+            warmup: Arc::new(Warmup::default()),
+        });
+    }
+
+    /// Close the connections beyond the first `min` that went idle.
+    fn close_idle(&mut self) {
+        let mut index = 0;
+        let min = self.min;
+        self.connections.retain(|member| {
+            index += 1;
+            index <= min || member.state.get() != ConnectivityState::Idle
+        });
+    }
+}
+
+impl<S, Request> Service<Request> for Pool<S>
+where
+    S: Service<Request, Error = crate::Error>,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.ready.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+
+        self.close_idle();
+        while self.connections.len() < self.min {
+            self.open();
+        }
+
+        loop {
+            // Drive the connections that aren't connected, and take the first ready connection
+            // from the next one on.
+            let len = self.connections.len();
+            let mut is_saturated = true;
+            for i in 0..len {
+                let index = (self.next + i) % len;
+                let member = &mut self.connections[index];
+                let is_connected = member.state.get() == ConnectivityState::Ready;
+                if !is_connected {
+                    // The connection reconnects, to a server whose settings are unknown.
+                    member.warmup.set(COLD);
+                }
+                if is_connected && self.ready.is_some() {
+                    continue;
+                }
+                if member.warmup.get() == WARMING {
+                    *member.warmup.waker.lock().unwrap() = Some(cx.waker().clone());
+                    is_saturated = false;
+                    continue;
+                }
+
+                match member.service.poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {
+                        if self.ready.is_none() {
+                            self.ready = Some(index);
+                        }
+                    }
+                    Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                    Poll::Pending => is_saturated &= is_connected,
+                }
+            }
+
+            if self.ready.is_some() {
+                return Poll::Ready(Ok(()));
+            }
+            if !is_saturated || len >= self.max {
+                return Poll::Pending;
+            }
+            self.open();
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let index = self
+            .ready
+            .take()
+            .expect("pool not ready; poll_ready must be called first");
+        self.next = (index + 1) % self.connections.len();
+
+        let member = &mut self.connections[index];
+        let warmup = match member.warmup.get() {
+            COLD => {
+                member.warmup.set(WARMING);
+                Some(member.warmup.clone())
+            }
+            _ => None,
+        };
+        ResponseFuture {
+            inner: member.service.call(request),
+            warmup,
+        }
+    }
+}
+
+/// Future that resolves to the response of a connection of a pool, warming up a new connection
+/// once it does.
+#[pin_project(PinnedDrop)]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    warmup: Option<Arc<Warmup>>,
+}
+
+impl<F, T> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<T, crate::Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = ready!(this.inner.poll(cx));
+        if let Some(warmup) = this.warmup.take() {
+            warmup.finish(output.is_ok());
+        }
+        Poll::Ready(output)
+    }
+}
+
+#[pinned_drop]
+impl<F> PinnedDrop for ResponseFuture<F> {
+    fn drop(self: Pin<&mut Self>) {
+        // The call was cancelled before its response arrived.
+        if let Some(warmup) = self.project().warmup.take() {
+            warmup.finish(false);
+        }
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<S> fmt::Debug for Pool<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("connections", &self.connections.len())
+            .field("min", &self.min)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+/// A service shared by the connections of a pool, such as the connector opening them.
+pub(crate) struct Shared<S>(Arc<Mutex<S>>);
+
+impl<S> Shared<S> {
+    pub(crate) fn new(service: S) -> Self {
+        Shared(Arc::new(Mutex::new(service)))
+    }
+}
+
+impl<S> Clone for Shared<S> {
+    fn clone(&self) -> Self {
+        Shared(self.0.clone())
+    }
+}
+
+impl<S, Request> Service<Request> for Shared<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.lock().unwrap().poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.0.lock().unwrap().call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::{poll_fn, ready, Ready};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// A connected connection, taking calls while `is_ready`.
+    struct Mock {
+        is_ready: Arc<AtomicBool>,
+        calls: Arc<AtomicUsize>,
+        _state: ConnectionState,
+    }
+
+    impl Service<()> for Mock {
+        type Response = ();
+        type Error = crate::Error;
+        type Future = Ready<Result<(), crate::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.is_ready.load(Ordering::SeqCst) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            ready(Ok(()))
+        }
+    }
+
+    /// A pool of mock connections, with the readiness and call count of each connection opened.
+    #[allow(clippy::type_complexity)]
+    fn pool(
+        min: usize,
+        max: usize,
+    ) -> (
+        Pool<Mock>,
+        Arc<Mutex<Vec<(Arc<AtomicBool>, Arc<AtomicUsize>)>>>,
+    ) {
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let connect = {
+            let opened = opened.clone();
+            Box::new(move |_, state: ConnectionState| {
+                state.set(ConnectivityState::Ready, None);
+                let is_ready = Arc::new(AtomicBool::new(true));
+                let calls = Arc::new(AtomicUsize::new(0));
+                opened
+                    .lock()
+                    .unwrap()
+                    .push((is_ready.clone(), calls.clone()));
+                Mock {
+                    is_ready,
+                    calls,
+                    _state: state,
+                }
+            })
+        };
+        let pool = Pool::new(
+            min,
+            max,
+            true,
+            ChannelState::new().connection(true),
+            connect,
+        );
+        (pool, opened)
+    }
+
+    async fn is_ready(pool: &mut Pool<Mock>) -> bool {
+        let ready = poll_fn(|cx| Poll::Ready(pool.poll_ready(cx))).await;
+        matches!(ready, Poll::Ready(Ok(())))
+    }
+
+    #[tokio::test]
+    async fn spreads_calls_across_connections() {
+        let (mut pool, opened) = pool(3, 3);
+
+        for _ in 0..6 {
+            assert!(is_ready(&mut pool).await);
+            pool.call(()).await.unwrap();
+        }
+
+        let opened = opened.lock().unwrap();
+        assert_eq!(opened.len(), 3);
+        for (_, calls) in opened.iter() {
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn opens_connections_when_saturated() {
+        let (mut pool, opened) = pool(1, 2);
+
+        assert!(is_ready(&mut pool).await);
+        pool.call(()).await.unwrap();
+        assert_eq!(opened.lock().unwrap().len(), 1);
+
+        // The first connection is at its stream limit.
+        opened.lock().unwrap()[0].0.store(false, Ordering::SeqCst);
+        assert!(is_ready(&mut pool).await);
+        pool.call(()).await.unwrap();
+        assert_eq!(opened.lock().unwrap().len(), 2);
+
+        // Both are, and the pool is full.
+        opened.lock().unwrap()[1].0.store(false, Ordering::SeqCst);
+        assert!(!is_ready(&mut pool).await);
+        assert_eq!(opened.lock().unwrap().len(), 2);
+
+        opened.lock().unwrap()[0].0.store(true, Ordering::SeqCst);
+        assert!(is_ready(&mut pool).await);
+        pool.call(()).await.unwrap();
+        assert_eq!(opened.lock().unwrap()[0].1.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn new_connection_takes_one_call_until_warm() {
+        let (mut pool, _) = pool(1, 1);
+
+        assert!(is_ready(&mut pool).await);
+        let first = pool.call(());
+        assert!(!is_ready(&mut pool).await);
+
+        first.await.unwrap();
+        for _ in 0..2 {
+            assert!(is_ready(&mut pool).await);
+            let _ = pool.call(());
+        }
+    }
+}
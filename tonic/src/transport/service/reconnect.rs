@@ -1,15 +1,47 @@
+use super::executor::{Executor, SharedExec};
+use crate::transport::channel::{ConnectionState, ConnectivityState, ResponseBody};
 use crate::Error;
 use pin_project::pin_project;
+use rand::Rng;
 use std::fmt;
 use std::{
-    future::Future,
+    future::{pending, poll_fn, Future},
     pin::Pin,
-    task::{Context, Poll},
+    sync::{Arc, Mutex, Weak},
+    task::{ready, Context, Poll},
+    time::Duration,
 };
-use tower::make::MakeService;
+use tokio::sync::{oneshot, watch};
+use tokio::time::{sleep_until, Instant, Sleep};
+use tower::{make::MakeService, util::Either};
 use tower_service::Service;
 use tracing::trace;
 
+/// Configures the delay between connection attempts, following the gRPC [connection backoff]
+/// protocol.
+///
+/// [connection backoff]: https://github.com/grpc/grpc/blob/master/doc/connection-backoff.md
+#[derive(Debug, Clone)]
+pub(crate) struct BackoffConfig {
+    pub(crate) initial_backoff: Duration,
+    pub(crate) multiplier: f64,
+    pub(crate) jitter: f64,
+    pub(crate) max_backoff: Duration,
+    pub(crate) min_connect_timeout: Duration,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            multiplier: 1.6,
+            jitter: 0.2,
+            max_backoff: Duration::from_secs(120),
+            min_connect_timeout: Duration::from_secs(20),
+        }
+    }
+}
+
 pub(crate) struct Reconnect<M, Target>
 where
     M: Service<Target>,
@@ -18,16 +50,25 @@ where
     mk_service: M,
     state: State<M::Future, M::Response>,
     target: Target,
-    error: Option<crate::Error>,
     has_been_connected: bool,
     is_lazy: bool,
+    backoff: BackoffConfig,
+    /// The backoff before the attempt after the next one.
+    current_backoff: Duration,
+    /// When the next attempt may be made if the current one fails.
+    next_attempt: Instant,
+    connectivity: Arc<ConnectionState>,
+    /// The executor of the tasks closing connections, and how long a connection stays unused
+    /// before they close it.
+    closing: Option<(SharedExec, Option<Duration>)>,
 }
 
 #[derive(Debug)]
 enum State<F, S> {
     Idle,
-    Connecting(F),
-    Connected(S),
+    Connecting(F, Pin<Box<Sleep>>),
+    Connected(Either<S, ClosingService<S>>),
+    Backoff(Pin<Box<Sleep>>),
 }
 
 impl<M, Target> Reconnect<M, Target>
@@ -35,22 +76,82 @@ where
     M: Service<Target>,
     M::Error: Into<Error>,
 {
-    pub(crate) fn new(mk_service: M, target: Target, is_lazy: bool) -> Self {
+    pub(crate) fn new(
+        mk_service: M,
+        target: Target,
+        is_lazy: bool,
+        backoff: BackoffConfig,
+        connectivity: ConnectionState,
+    ) -> Self {
         Reconnect {
             mk_service,
             state: State::Idle,
             target,
-            error: None,
             has_been_connected: false,
             is_lazy,
+            current_backoff: backoff.initial_backoff,
+            backoff,
+            next_attempt: Instant::now(),
+            connectivity: Arc::new(connectivity),
+            closing: None,
+        }
+    }
+
+    /// Drop the connection once its channel shuts down, or once no call ran on it for
+    /// `idle_timeout`, with a task spawned on `executor`. After an idle timeout the next call
+    /// connects again.
+    pub(crate) fn closing(self, idle_timeout: Option<Duration>, executor: SharedExec) -> Self {
+        Reconnect {
+            closing: Some((executor, idle_timeout)),
+            ..self
+        }
+    }
+
+    /// Start the backoff of the next attempt, returning the deadline of the current one.
+    fn start_attempt(&mut self) -> Instant {
+        let now = Instant::now();
+        let jitter = self.backoff.jitter.clamp(0.0, 1.0);
+        let backoff = self
+            .current_backoff
+            .mul_f64(rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter));
+
+        self.next_attempt = now + backoff;
+        self.current_backoff = next_backoff(&self.backoff, self.current_backoff);
+
+        self.next_attempt
+            .max(now + self.backoff.min_connect_timeout)
+    }
+
+    /// Back off after a failed attempt, or give up with `error` if the connection is eager and
+    /// never connected.
+    fn fail(&mut self, error: Error) -> Result<State<M::Future, M::Response>, Error> {
+        self.connectivity
+            .set(ConnectivityState::TransientFailure, Some(self.next_attempt));
+
+        if !(self.has_been_connected || self.is_lazy) {
+            self.state = State::Idle;
+            return Err(error);
         }
+
+        tracing::debug!("reconnect::poll_ready: {:?}", error);
+        Ok(State::Backoff(Box::pin(sleep_until(self.next_attempt))))
+    }
+}
+
+fn next_backoff(config: &BackoffConfig, backoff: Duration) -> Duration {
+    let next = backoff.as_secs_f64() * config.multiplier;
+    if next.is_finite() && next < config.max_backoff.as_secs_f64() {
+        Duration::from_secs_f64(next.max(0.0))
+    } else {
+        config.max_backoff
     }
 }
 
 impl<M, Target, S, Request> Service<Request> for Reconnect<M, Target>
 where
     M: Service<Target, Response = S>,
-    S: Service<Request>,
+    S: Service<Request> + Send + 'static,
+    S::Response: CallResponse,
     M::Future: Unpin,
     Error: From<M::Error> + From<S::Error>,
     Target: Clone,
@@ -58,63 +159,95 @@ where
 {
     type Response = S::Response;
     type Error = Error;
-    type Future = ResponseFuture<S::Future>;
+    type Future = ResponseFuture<Either<S::Future, CallFuture<S::Future>>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         let mut state;
 
-        if self.error.is_some() {
-            return Poll::Ready(Ok(()));
-        }
-
         loop {
             match self.state {
                 State::Idle => {
                     trace!("poll_ready; idle");
-                    match self.mk_service.poll_ready(cx) {
-                        Poll::Ready(r) => r?,
+                    if self.connectivity.is_shutdown() {
+                        return Poll::Ready(Err(ChannelShutdown.into()));
+                    }
+
+                    let error = match self.mk_service.poll_ready(cx) {
+                        Poll::Ready(Ok(())) => {
+                            let fut = self.mk_service.make_service(self.target.clone());
+                            let deadline = self.start_attempt();
+                            self.connectivity.set(ConnectivityState::Connecting, None);
+                            self.state = State::Connecting(fut, Box::pin(sleep_until(deadline)));
+                            continue;
+                        }
+                        Poll::Ready(Err(e)) => {
+                            self.start_attempt();
+                            e.into()
+                        }
                         Poll::Pending => {
                             trace!("poll_ready; MakeService not ready");
                             return Poll::Pending;
                         }
-                    }
+                    };
 
-                    let fut = self.mk_service.make_service(self.target.clone());
-                    self.state = State::Connecting(fut);
-                    continue;
+                    trace!("poll_ready; MakeService error");
+                    state = match self.fail(error) {
+                        Ok(backoff) => backoff,
+                        Err(error) => return Poll::Ready(Err(error)),
+                    };
                 }
-                State::Connecting(ref mut f) => {
+                State::Connecting(ref mut f, ref mut deadline) => {
                     trace!("poll_ready; connecting");
-                    match Pin::new(f).poll(cx) {
+                    let error = match Pin::new(f).poll(cx) {
                         Poll::Ready(Ok(service)) => {
-                            state = State::Connected(service);
-                        }
-                        Poll::Pending => {
-                            trace!("poll_ready; not ready");
-                            return Poll::Pending;
-                        }
-                        Poll::Ready(Err(e)) => {
-                            trace!("poll_ready; error");
-
-                            state = State::Idle;
-
-                            if !(self.has_been_connected || self.is_lazy) {
-                                return Poll::Ready(Err(e.into()));
-                            } else {
-                                let error = e.into();
-                                tracing::debug!("reconnect::poll_ready: {:?}", error);
-                                self.error = Some(error);
-                                break;
+                            if self.connectivity.is_shutdown() {
+                                self.state = State::Idle;
+                                continue;
                             }
+
+                            self.connectivity.set(ConnectivityState::Ready, None);
+                            let service = match &self.closing {
+                                Some((executor, idle_timeout)) => Either::B(ClosingService::new(
+                                    service,
+                                    *idle_timeout,
+                                    executor,
+                                    self.connectivity.clone(),
+                                )),
+                                None => Either::A(service),
+                            };
+                            self.state = State::Connected(service);
+                            continue;
                         }
+                        Poll::Ready(Err(e)) => e.into(),
+                        Poll::Pending => match deadline.as_mut().poll(cx) {
+                            Poll::Ready(()) => Error::from("connection attempt timed out"),
+                            Poll::Pending => {
+                                trace!("poll_ready; not ready");
+                                return Poll::Pending;
+                            }
+                        },
+                    };
+
+                    trace!("poll_ready; error");
+                    state = match self.fail(error) {
+                        Ok(backoff) => backoff,
+                        Err(error) => return Poll::Ready(Err(error)),
+                    };
+                }
+                State::Backoff(ref mut delay) => {
+                    trace!("poll_ready; backing off");
+                    match delay.as_mut().poll(cx) {
+                        Poll::Ready(()) => state = State::Idle,
+                        Poll::Pending => return Poll::Pending,
                     }
                 }
                 State::Connected(ref mut inner) => {
                     trace!("poll_ready; connected");
 
                     self.has_been_connected = true;
+                    self.current_backoff = self.backoff.initial_backoff;
 
-                    match inner.poll_ready(cx) {
+                    match Service::<Request>::poll_ready(inner, cx) {
                         Poll::Ready(Ok(())) => {
                             trace!("poll_ready; ready");
                             return Poll::Ready(Ok(()));
@@ -125,6 +258,7 @@ where
                         }
                         Poll::Ready(Err(_)) => {
                             trace!("poll_ready; error");
+                            self.connectivity.set(ConnectivityState::Idle, None);
                             state = State::Idle;
                         }
                     }
@@ -133,17 +267,10 @@ where
 
             self.state = state;
         }
-
-        self.state = state;
-        Poll::Ready(Ok(()))
     }
 
     fn call(&mut self, request: Request) -> Self::Future {
         tracing::trace!("Reconnect::call");
-        if let Some(error) = self.error.take() {
-            tracing::debug!("error: {}", error);
-            return ResponseFuture::error(error);
-        }
 
         let service = match self.state {
             State::Connected(ref mut service) => service,
@@ -172,32 +299,258 @@ where
     }
 }
 
-/// Future that resolves to the response or failure to connect.
-#[pin_project]
+/// A connected service, dropped by a task once its channel shuts down or once no call ran on it
+/// for the idle timeout.
+///
+/// Calls still running when the channel shuts down, such as streaming calls, keep the connection
+/// open until they end.
+struct ClosingService<S> {
+    shared: Arc<Mutex<Shared<S>>>,
+    /// The calls running on the service, counted when there is an idle timeout.
+    usage: Option<Arc<Mutex<Usage>>>,
+    /// Ends the task once the service is dropped.
+    _dropped: oneshot::Sender<()>,
+}
+
+struct Shared<S> {
+    /// The service, until it is dropped.
+    service: Option<S>,
+    /// Whether the service is ready for a call that wasn't made yet.
+    is_reserved: bool,
+    connectivity: Arc<ConnectionState>,
+}
+
 #[derive(Debug)]
-pub(crate) struct ResponseFuture<F> {
-    #[pin]
-    inner: Inner<F>,
+struct Usage {
+    /// The number of calls that didn't end yet.
+    calls: usize,
+    /// When the last call started or ended.
+    last_used: Instant,
 }
 
-#[pin_project(project = InnerProj)]
+/// A call running on a [`ClosingService`], which keeps it from being idle until it is dropped.
 #[derive(Debug)]
-enum Inner<F> {
-    Future(#[pin] F),
-    Error(Option<crate::Error>),
+pub(crate) struct RunningCall(Arc<Mutex<Usage>>);
+
+impl RunningCall {
+    fn start(usage: &Arc<Mutex<Usage>>) -> Self {
+        let mut state = usage.lock().unwrap();
+        state.calls += 1;
+        state.last_used = Instant::now();
+        RunningCall(usage.clone())
+    }
 }
 
-impl<F> ResponseFuture<F> {
-    pub(crate) fn new(inner: F) -> Self {
-        ResponseFuture {
-            inner: Inner::Future(inner),
+impl Drop for RunningCall {
+    fn drop(&mut self) {
+        let mut usage = self.0.lock().unwrap();
+        usage.calls -= 1;
+        usage.last_used = Instant::now();
+    }
+}
+
+/// The response of a call that may end after the response is received.
+pub(crate) trait CallResponse: Sized {
+    /// Keep `call` until the call of this response ends.
+    fn hold(self, call: RunningCall) -> Self;
+}
+
+impl CallResponse for http::Response<ResponseBody> {
+    /// The call ends with the body of the response.
+    fn hold(mut self, call: RunningCall) -> Self {
+        self.body_mut().hold(call);
+        self
+    }
+}
+
+impl<S: Send + 'static> ClosingService<S> {
+    fn new(
+        service: S,
+        idle_timeout: Option<Duration>,
+        executor: &SharedExec,
+        connectivity: Arc<ConnectionState>,
+    ) -> Self {
+        let channel = connectivity.watch_channel();
+        let shared = Arc::new(Mutex::new(Shared {
+            service: Some(service),
+            is_reserved: false,
+            connectivity,
+        }));
+        let idle = idle_timeout.map(|timeout| {
+            let usage = Arc::new(Mutex::new(Usage {
+                calls: 0,
+                last_used: Instant::now(),
+            }));
+            (timeout, usage)
+        });
+        let usage = idle.as_ref().map(|(_, usage)| usage.clone());
+        let (dropped, closed) = oneshot::channel();
+        let close = close(Arc::downgrade(&shared), idle, channel, closed);
+        executor.execute(Box::pin(close));
+
+        ClosingService {
+            shared,
+            usage,
+            _dropped: dropped,
         }
     }
+}
 
-    pub(crate) fn error(error: crate::Error) -> Self {
-        ResponseFuture {
-            inner: Inner::Error(Some(error)),
+/// Drop the service once its channel shuts down or once no call ran on it for the idle timeout,
+/// unless it is dropped before.
+async fn close<S>(
+    shared: Weak<Mutex<Shared<S>>>,
+    idle: Option<(Duration, Arc<Mutex<Usage>>)>,
+    mut channel: watch::Receiver<ConnectivityState>,
+    mut dropped: oneshot::Receiver<()>,
+) {
+    let shutdown = async {
+        while *channel.borrow_and_update() != ConnectivityState::Shutdown {
+            if channel.changed().await.is_err() {
+                return;
+            }
         }
+    };
+    let idle = async {
+        let (timeout, usage) = match idle {
+            Some(idle) => idle,
+            None => return pending().await,
+        };
+        let mut deadline = Instant::now() + timeout;
+        loop {
+            sleep_until(deadline).await;
+
+            let shared = match shared.upgrade() {
+                Some(shared) => shared,
+                None => return,
+            };
+            let is_reserved = shared.lock().unwrap().is_reserved;
+            let usage = usage.lock().unwrap();
+            let now = Instant::now();
+            if is_reserved || usage.calls > 0 {
+                deadline = now + timeout;
+            } else if usage.last_used + timeout > now {
+                deadline = usage.last_used + timeout;
+            } else {
+                return;
+            }
+        }
+    };
+    tokio::pin!(shutdown, idle);
+
+    let is_dropped = poll_fn(|cx| {
+        if Pin::new(&mut dropped).poll(cx).is_ready() {
+            Poll::Ready(true)
+        } else if shutdown.as_mut().poll(cx).is_ready() || idle.as_mut().poll(cx).is_ready() {
+            Poll::Ready(false)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+
+    if let (false, Some(shared)) = (is_dropped, shared.upgrade()) {
+        trace!("closing connection");
+        let mut shared = shared.lock().unwrap();
+        shared.service = None;
+        shared.connectivity.set(ConnectivityState::Idle, None);
+    }
+}
+
+impl<S, Request> Service<Request> for ClosingService<S>
+where
+    S: Service<Request>,
+    S::Response: CallResponse,
+    Error: From<S::Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = CallFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut shared = self.shared.lock().unwrap();
+        let service = match &mut shared.service {
+            Some(service) => service,
+            None => return Poll::Ready(Err("connection closed".into())),
+        };
+
+        let ready = service.poll_ready(cx).map_err(Error::from);
+        shared.is_reserved = matches!(ready, Poll::Ready(Ok(())));
+        ready
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let mut shared = self.shared.lock().unwrap();
+        shared.is_reserved = false;
+
+        let service = shared
+            .service
+            .as_mut()
+            .expect("service not ready; poll_ready must be called first");
+        let call = self.usage.as_ref().map(RunningCall::start);
+        CallFuture {
+            inner: service.call(request),
+            call,
+        }
+    }
+}
+
+impl<S> fmt::Debug for ClosingService<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClosingService").finish()
+    }
+}
+
+/// Error returned when a connection of a channel that shut down is used.
+#[derive(Debug)]
+pub(crate) struct ChannelShutdown;
+
+impl fmt::Display for ChannelShutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("channel shut down")
+    }
+}
+
+impl std::error::Error for ChannelShutdown {}
+
+/// Future that resolves to the response of the connected service.
+#[pin_project]
+#[derive(Debug)]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+}
+
+impl<F> ResponseFuture<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        ResponseFuture { inner }
+    }
+}
+
+/// Future that resolves to the response of a call on a [`ClosingService`], which holds the call
+/// until it ends.
+#[pin_project]
+pub(crate) struct CallFuture<F> {
+    #[pin]
+    inner: F,
+    call: Option<RunningCall>,
+}
+
+impl<F, T, E> Future for CallFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: CallResponse,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx)).map_err(Into::into)?;
+        Poll::Ready(Ok(match this.call.take() {
+            Some(call) => response.hold(call),
+            None => response,
+        }))
     }
 }
 
@@ -209,14 +562,362 @@ where
     type Output = Result<T, Error>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        //self.project().inner.poll(cx).map_err(Into::into)
-        let me = self.project();
-        match me.inner.project() {
-            InnerProj::Future(fut) => fut.poll(cx).map_err(Into::into),
-            InnerProj::Error(e) => {
-                let e = e.take().expect("Polled after ready.");
-                Poll::Ready(Err(e))
+        self.project().inner.poll(cx).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::channel::ChannelState;
+    use http_body::Body;
+    use std::sync::{Arc, Mutex};
+    use tower::{service_fn, ServiceExt};
+
+    type MockService = tower::util::BoxService<(), (), Error>;
+
+    impl CallResponse for () {
+        fn hold(self, _call: RunningCall) -> Self {}
+    }
+
+    /// A service that fails the first `failures` connection attempts, recording when each attempt
+    /// was made. Attempts that fail take `latency` to do so.
+    fn make_service(
+        failures: usize,
+        latency: Option<Duration>,
+        attempts: Arc<Mutex<Vec<Instant>>>,
+    ) -> impl Service<
+        (),
+        Response = MockService,
+        Error = Error,
+        Future = crate::transport::BoxFuture<'static, Result<MockService, Error>>,
+    > {
+        service_fn(move |()| {
+            let mut attempts = attempts.lock().unwrap();
+            attempts.push(Instant::now());
+            let attempt = attempts.len();
+
+            Box::pin(async move {
+                if attempt > failures {
+                    let svc = service_fn(|()| std::future::ready(Ok::<_, Error>(())));
+                    return Ok(MockService::new(svc));
+                }
+
+                match latency {
+                    Some(latency) => tokio::time::sleep(latency).await,
+                    None => std::future::pending().await,
+                }
+                Err(Error::from("connection refused"))
+            }) as crate::transport::BoxFuture<'static, _>
+        })
+    }
+
+    fn backoff() -> BackoffConfig {
+        BackoffConfig {
+            jitter: 0.0,
+            ..BackoffConfig::default()
+        }
+    }
+
+    fn elapsed_between(attempts: &[Instant]) -> Vec<Duration> {
+        attempts.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backoff_grows_after_each_failure() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(3, Some(Duration::ZERO), attempts.clone());
+        let mut svc = Reconnect::new(
+            mk,
+            (),
+            true,
+            backoff(),
+            ChannelState::new().connection(true),
+        );
+
+        ServiceExt::<()>::ready(&mut svc).await.unwrap();
+
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(
+            elapsed_between(&attempts),
+            [
+                Duration::from_millis(1000),
+                Duration::from_millis(1600),
+                Duration::from_millis(2560),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backoff_is_capped() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(3, Some(Duration::ZERO), attempts.clone());
+        let config = BackoffConfig {
+            max_backoff: Duration::from_millis(1200),
+            ..backoff()
+        };
+        let mut svc = Reconnect::new(mk, (), true, config, ChannelState::new().connection(true));
+
+        ServiceExt::<()>::ready(&mut svc).await.unwrap();
+
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(
+            elapsed_between(&attempts),
+            [
+                Duration::from_millis(1000),
+                Duration::from_millis(1200),
+                Duration::from_millis(1200),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jitter_varies_backoff() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(5, Some(Duration::ZERO), attempts.clone());
+        let config = BackoffConfig {
+            multiplier: 1.0,
+            jitter: 0.2,
+            ..backoff()
+        };
+        let mut svc = Reconnect::new(mk, (), true, config, ChannelState::new().connection(true));
+
+        ServiceExt::<()>::ready(&mut svc).await.unwrap();
+
+        for elapsed in elapsed_between(&attempts.lock().unwrap()) {
+            assert!(elapsed >= Duration::from_millis(800), "{:?}", elapsed);
+            assert!(elapsed <= Duration::from_millis(1200), "{:?}", elapsed);
+        }
+    }
+
+    /// A `MakeService` that fails the first `failures` times it's polled, recording when it was.
+    struct FailingMakeService {
+        failures: usize,
+        polls: Arc<Mutex<Vec<Instant>>>,
+    }
+
+    impl Service<()> for FailingMakeService {
+        type Response = MockService;
+        type Error = Error;
+        type Future = std::future::Ready<Result<MockService, Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            let mut polls = self.polls.lock().unwrap();
+            polls.push(Instant::now());
+            if polls.len() <= self.failures {
+                Poll::Ready(Err(Error::from("connector failed")))
+            } else {
+                Poll::Ready(Ok(()))
             }
         }
+
+        fn call(&mut self, (): ()) -> Self::Future {
+            let svc = service_fn(|()| std::future::ready(Ok::<_, Error>(())));
+            std::future::ready(Ok(MockService::new(svc)))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lazy_connection_backs_off_when_make_service_fails() {
+        let polls = Arc::new(Mutex::new(Vec::new()));
+        let mk = FailingMakeService {
+            failures: 2,
+            polls: polls.clone(),
+        };
+        let mut svc = Reconnect::new(
+            mk,
+            (),
+            true,
+            backoff(),
+            ChannelState::new().connection(true),
+        );
+
+        ServiceExt::<()>::ready(&mut svc).await.unwrap();
+
+        assert_eq!(
+            elapsed_between(&polls.lock().unwrap()),
+            [Duration::from_millis(1000), Duration::from_millis(1600)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_attempts_get_min_connect_timeout() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(2, None, attempts.clone());
+        let mut svc = Reconnect::new(
+            mk,
+            (),
+            true,
+            backoff(),
+            ChannelState::new().connection(true),
+        );
+
+        ServiceExt::<()>::ready(&mut svc).await.unwrap();
+
+        // Each attempt times out after 20 seconds, by when its backoff has already elapsed.
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(
+            elapsed_between(&attempts),
+            [Duration::from_secs(20), Duration::from_secs(20)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn publishes_backoff() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(1, Some(Duration::ZERO), attempts.clone());
+        let channel = ChannelState::new();
+        let mut backoff_until = channel.watch_backoff();
+        let mut svc = Reconnect::new(mk, (), true, backoff(), channel.connection(true));
+
+        let start = Instant::now();
+        let ready = tokio::spawn(async move {
+            ServiceExt::<()>::ready(&mut svc).await.unwrap();
+            svc
+        });
+
+        backoff_until.changed().await.unwrap();
+        assert_eq!(
+            *backoff_until.borrow_and_update(),
+            Some(start + Duration::from_secs(1))
+        );
+        assert_eq!(channel.get(), ConnectivityState::TransientFailure);
+
+        let svc = ready.await.unwrap();
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+        assert_eq!(*backoff_until.borrow(), None);
+
+        drop(svc);
+        assert_eq!(channel.get(), ConnectivityState::Shutdown);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn eager_connection_fails_on_first_error() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(1, Some(Duration::ZERO), attempts.clone());
+        let mut svc = Reconnect::new(
+            mk,
+            (),
+            false,
+            backoff(),
+            ChannelState::new().connection(true),
+        );
+
+        assert!(ServiceExt::<()>::ready(&mut svc).await.is_err());
+        assert_eq!(attempts.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_connection_is_dropped_and_reconnects() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(0, None, attempts.clone());
+        let channel = ChannelState::new();
+        let mut svc = Reconnect::new(mk, (), true, backoff(), channel.connection(true))
+            .closing(Some(Duration::from_secs(60)), SharedExec::tokio());
+
+        ServiceExt::<()>::ready(&mut svc)
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .unwrap();
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+
+        // Each call pushes the timeout back.
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        ServiceExt::<()>::ready(&mut svc)
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(channel.get(), ConnectivityState::Idle);
+        assert_eq!(attempts.lock().unwrap().len(), 1);
+
+        ServiceExt::<()>::ready(&mut svc)
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .unwrap();
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+        assert_eq!(attempts.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ready_connection_is_not_dropped() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let mk = make_service(0, None, attempts.clone());
+        let channel = ChannelState::new();
+        let mut svc = Reconnect::new(mk, (), true, backoff(), channel.connection(true))
+            .closing(Some(Duration::from_secs(60)), SharedExec::tokio());
+
+        // A call readied before the timeout is made on the same connection.
+        ServiceExt::<()>::ready(&mut svc).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(90)).await;
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+        svc.call(()).await.unwrap();
+        assert_eq!(attempts.lock().unwrap().len(), 1);
+    }
+
+    /// A connection answering its only call with `body`, closed once unused for 60 seconds.
+    fn streaming(
+        body: hyper::Body,
+        channel: &Arc<ChannelState>,
+    ) -> impl Service<(), Response = http::Response<ResponseBody>, Error = Error> {
+        let body = Arc::new(Mutex::new(Some(body)));
+        let mk = service_fn(move |()| {
+            let body = body.clone();
+            let svc = service_fn(move |()| {
+                let body = body.lock().unwrap().take().unwrap();
+                let response = http::Response::new(ResponseBody::new(body));
+                std::future::ready(Ok::<_, Error>(response))
+            });
+            std::future::ready(Ok::<_, Error>(tower::util::BoxService::new(svc)))
+        });
+        Reconnect::new(mk, (), true, backoff(), channel.connection(true))
+            .closing(Some(Duration::from_secs(60)), SharedExec::tokio())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn running_call_keeps_connection_open() {
+        let (sender, body) = hyper::Body::channel();
+        let channel = ChannelState::new();
+        let mut svc = streaming(body, &channel);
+
+        let mut response = svc.ready().await.unwrap().call(()).await.unwrap();
+
+        // The timeout starts once the body of the response ends.
+        tokio::time::sleep(Duration::from_secs(90)).await;
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+        drop(sender);
+        assert!(response.body_mut().data().await.is_none());
+        assert!(response.body_mut().trailers().await.unwrap().is_none());
+        tokio::time::sleep(Duration::from_secs(50)).await;
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(channel.get(), ConnectivityState::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_response_ends_call() {
+        let (_sender, body) = hyper::Body::channel();
+        let channel = ChannelState::new();
+        let mut svc = streaming(body, &channel);
+
+        // The server stays quiet, but the caller gives up on the call.
+        let response = svc.ready().await.unwrap().call(()).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        drop(response);
+
+        tokio::time::sleep(Duration::from_secs(50)).await;
+        assert_eq!(channel.get(), ConnectivityState::Ready);
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(channel.get(), ConnectivityState::Idle);
     }
 }
@@ -0,0 +1,254 @@
+use crate::{body::BoxBody, Status};
+use bytes::Bytes;
+use http::HeaderMap;
+use http_body::Body;
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+/// A request body that can be sent more than once.
+///
+/// The chunks of the wrapped body are recorded as they are read, so that further attempts of the
+/// same call can replay them with [`ReplayBody::replay`]. Once more than `max_buffer_size` bytes
+/// have been read the recording is dropped and the body can no longer be replayed.
+pub(crate) struct ReplayBody {
+    shared: Arc<Mutex<Shared>>,
+    /// The index of the next chunk this body yields.
+    position: usize,
+}
+
+struct Shared {
+    inner: BoxBody,
+    /// The recorded chunks, starting with chunk number `first_chunk`.
+    chunks: Vec<Bytes>,
+    first_chunk: usize,
+    buffered: usize,
+    max_buffer_size: usize,
+    is_capped: bool,
+    is_data_done: bool,
+    trailers: Option<Option<HeaderMap>>,
+    /// Bodies waiting for the inner body to yield its next chunk.
+    waiters: Vec<Waker>,
+}
+
+impl ReplayBody {
+    pub(crate) fn new(inner: BoxBody, max_buffer_size: usize) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                inner,
+                chunks: Vec::new(),
+                first_chunk: 0,
+                buffered: 0,
+                max_buffer_size,
+                is_capped: false,
+                is_data_done: false,
+                trailers: None,
+                waiters: Vec::new(),
+            })),
+            position: 0,
+        }
+    }
+
+    /// Create a body that yields the same data and trailers from the start.
+    pub(crate) fn replay(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            position: 0,
+        }
+    }
+
+    /// Whether the body grew too large to be replayed.
+    pub(crate) fn is_capped(&self) -> bool {
+        self.shared.lock().unwrap().is_capped
+    }
+}
+
+impl Shared {
+    fn chunk_count(&self) -> usize {
+        self.first_chunk + self.chunks.len()
+    }
+
+    fn record(&mut self, data: &Bytes) {
+        if self.is_capped {
+            self.first_chunk += 1;
+            return;
+        }
+
+        self.buffered += data.len();
+        if self.buffered > self.max_buffer_size {
+            self.cap();
+            self.first_chunk += 1;
+        } else {
+            self.chunks.push(data.clone());
+        }
+    }
+
+    fn cap(&mut self) {
+        self.is_capped = true;
+        self.first_chunk += self.chunks.len();
+        self.chunks = Vec::new();
+    }
+
+    fn wake_waiters(&mut self) {
+        for waker in self.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+impl Body for ReplayBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.get_mut();
+        let mut shared = this.shared.lock().unwrap();
+
+        if this.position < shared.first_chunk {
+            return Poll::Ready(Some(Err(Status::internal(
+                "request body is too large to be replayed",
+            ))));
+        }
+
+        if let Some(chunk) = shared.chunks.get(this.position - shared.first_chunk) {
+            let chunk = chunk.clone();
+            this.position += 1;
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+
+        if shared.is_data_done {
+            return Poll::Ready(None);
+        }
+
+        let result = match Pin::new(&mut shared.inner).poll_data(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => {
+                if !shared.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                    shared.waiters.push(cx.waker().clone());
+                }
+                return Poll::Pending;
+            }
+        };
+        shared.wake_waiters();
+
+        match result {
+            Some(Ok(data)) => {
+                shared.record(&data);
+                this.position = shared.chunk_count();
+                Poll::Ready(Some(Ok(data)))
+            }
+            Some(Err(status)) => {
+                // An error can't be replayed.
+                shared.cap();
+                this.position = shared.chunk_count();
+                Poll::Ready(Some(Err(status)))
+            }
+            None => {
+                shared.is_data_done = true;
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let mut shared = self.shared.lock().unwrap();
+
+        if let Some(trailers) = &shared.trailers {
+            return Poll::Ready(Ok(trailers.clone()));
+        }
+
+        match Pin::new(&mut shared.inner).poll_trailers(cx) {
+            Poll::Ready(Ok(trailers)) => {
+                shared.trailers = Some(trailers.clone());
+                Poll::Ready(Ok(trailers))
+            }
+            Poll::Ready(Err(status)) => {
+                shared.cap();
+                Poll::Ready(Err(status))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        let shared = self.shared.lock().unwrap();
+
+        shared.is_data_done
+            && self.position == shared.chunk_count()
+            && matches!(shared.trailers, Some(None))
+    }
+}
+
+impl Drop for ReplayBody {
+    fn drop(&mut self) {
+        // The dropped body may have been the one registered with the inner body, so let the
+        // others poll it themselves.
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.wake_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(chunks: &[&'static [u8]]) -> BoxBody {
+        let stream = tokio_stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok::<_, Status>(Bytes::from_static(chunk)))
+                .collect::<Vec<_>>(),
+        );
+        crate::body::boxed(hyper::Body::wrap_stream(stream))
+    }
+
+    async fn read_all(mut body: ReplayBody) -> Result<Vec<u8>, Status> {
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_chunks() {
+        let first = ReplayBody::new(body(&[b"hello ", b"world"]), 1024);
+        let second = first.replay();
+
+        assert_eq!(read_all(first).await.unwrap(), b"hello world");
+        assert_eq!(read_all(second).await.unwrap(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn replay_continues_reading_inner_body() {
+        let mut first = ReplayBody::new(body(&[b"hello ", b"world"]), 1024);
+        assert_eq!(first.data().await.unwrap().unwrap(), "hello ");
+
+        let second = first.replay();
+        drop(first);
+
+        assert_eq!(read_all(second).await.unwrap(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn stops_recording_above_limit() {
+        let first = ReplayBody::new(body(&[b"hello ", b"world"]), 8);
+        let second = first.replay();
+
+        assert_eq!(read_all(first).await.unwrap(), b"hello world");
+        assert!(second.is_capped());
+        assert_eq!(
+            read_all(second).await.unwrap_err().code(),
+            crate::Code::Internal
+        );
+    }
+}
@@ -0,0 +1,688 @@
+use super::{grpc_timeout::try_parse_grpc_timeout, hedge::hedge, replay_body::ReplayBody};
+use crate::{
+    body::BoxBody,
+    codec::compression::CompressionOverride,
+    metadata::GRPC_TIMEOUT_HEADER,
+    request::{duration_to_grpc_timeout, WaitForReady},
+    transport::{
+        channel::{CallPolicy, RequestHash, ResponseBody, RetryConfig, RetryPolicy},
+        BoxFuture,
+    },
+    Code, GrpcMethod, Status,
+};
+use http::{request::Parts, Extensions, HeaderValue, Request, Response};
+use pin_project::pin_project;
+use rand::Rng;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tower::{util::Oneshot, ServiceExt};
+use tower_service::Service;
+use tracing::debug;
+
+const PUSHBACK_HEADER: &str = "grpc-retry-pushback-ms";
+const PREVIOUS_ATTEMPTS_HEADER: &str = "grpc-previous-rpc-attempts";
+
+/// Retries failed calls according to the [`RetryPolicy`] configured for their method.
+pub(crate) struct Retry<S> {
+    inner: S,
+    config: Arc<RetryConfig>,
+    throttle: Option<Arc<TokenBucket>>,
+}
+
+impl<S> Retry<S> {
+    pub(crate) fn new(inner: S, config: RetryConfig) -> Self {
+        let throttle = config
+            .throttling
+            .map(|(max_tokens, token_ratio)| Arc::new(TokenBucket::new(max_tokens, token_ratio)));
+
+        Self {
+            inner,
+            config: Arc::new(config),
+            throttle,
+        }
+    }
+}
+
+impl<S> Retry<S> {
+    pub(crate) fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Clone for Retry<S>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            config: self.config.clone(),
+            throttle: self.throttle.clone(),
+        }
+    }
+}
+
+impl<S> Service<Request<BoxBody>> for Retry<S>
+where
+    S: Service<Request<BoxBody>, Response = Response<ResponseBody>, Error = crate::Error>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let policy = match self.config.policy_for(request.uri().path()) {
+            Some(policy) if policy.max_attempts() > 1 => policy.clone(),
+            _ => {
+                return ResponseFuture {
+                    kind: Kind::Passthrough {
+                        future: self.inner.call(request),
+                    },
+                }
+            }
+        };
+
+        let (mut parts, body) = request.into_parts();
+        let body = ReplayBody::new(body, self.config.buffer_size);
+
+        // Only the first attempt gets all the extensions, as they can't be cloned. The others get
+        // the ones tonic knows about.
+        let extensions = std::mem::take(&mut parts.extensions);
+        let attempt_extensions = AttemptExtensions::new(&extensions);
+        let mut first = attempt_request(&parts, body.replay());
+        *first.extensions_mut() = extensions;
+
+        let attempts = Attempts {
+            extensions: attempt_extensions,
+            inner: self.inner.clone(),
+            throttle: self.throttle.clone(),
+            deadline: try_parse_grpc_timeout(&parts.headers)
+                .ok()
+                .flatten()
+                .map(|timeout| Instant::now() + timeout),
+            parts,
+            body,
+        };
+
+        let first = self.inner.call(first);
+
+        let future = match policy {
+            CallPolicy::Retry(policy) => {
+                Box::pin(retry(attempts, policy, first)) as BoxFuture<'static, _>
+            }
+            CallPolicy::Hedging(policy) => Box::pin(hedge(attempts, policy, first)),
+        };
+
+        ResponseFuture {
+            kind: Kind::Retrying { future },
+        }
+    }
+}
+
+impl<S> fmt::Debug for Retry<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Retry")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Sends the attempts of a single call.
+pub(super) struct Attempts<S> {
+    inner: S,
+    throttle: Option<Arc<TokenBucket>>,
+    deadline: Option<Instant>,
+    parts: Parts,
+    extensions: AttemptExtensions,
+    body: ReplayBody,
+}
+
+/// The extensions of a call that tonic reads, copied onto each of its attempts.
+#[derive(Debug, Default)]
+struct AttemptExtensions {
+    request_hash: Option<RequestHash>,
+    grpc_method: Option<GrpcMethod>,
+    wait_for_ready: Option<WaitForReady>,
+    compression: Option<CompressionOverride>,
+}
+
+impl AttemptExtensions {
+    fn new(extensions: &Extensions) -> Self {
+        Self {
+            request_hash: extensions.get().copied(),
+            grpc_method: extensions.get().cloned(),
+            wait_for_ready: extensions.get().copied(),
+            compression: extensions.get().cloned(),
+        }
+    }
+
+    fn insert_into(&self, extensions: &mut Extensions) {
+        if let Some(request_hash) = self.request_hash {
+            extensions.insert(request_hash);
+        }
+        if let Some(grpc_method) = &self.grpc_method {
+            extensions.insert(grpc_method.clone());
+        }
+        if let Some(wait_for_ready) = self.wait_for_ready {
+            extensions.insert(wait_for_ready);
+        }
+        if let Some(compression) = &self.compression {
+            extensions.insert(compression.clone());
+        }
+    }
+}
+
+impl<S> Attempts<S>
+where
+    S: Service<Request<BoxBody>, Response = Response<ResponseBody>, Error = crate::Error> + Clone,
+{
+    /// Send another attempt of the call.
+    pub(super) fn send(&self, previous_attempts: usize) -> Oneshot<S, Request<BoxBody>> {
+        let mut request = attempt_request(&self.parts, self.body.replay());
+        self.extensions.insert_into(request.extensions_mut());
+        request.headers_mut().insert(
+            PREVIOUS_ATTEMPTS_HEADER,
+            HeaderValue::from(previous_attempts),
+        );
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let value = HeaderValue::try_from(duration_to_grpc_timeout(remaining))
+                .expect("grpc-timeout is a valid header value");
+            request.headers_mut().insert(GRPC_TIMEOUT_HEADER, value);
+        }
+
+        self.inner.clone().oneshot(request)
+    }
+
+    /// Whether another attempt may be sent after `delay`.
+    pub(super) fn may_send(&self, delay: Duration) -> bool {
+        if self.body.is_capped() {
+            return false;
+        }
+
+        if let Some(throttle) = &self.throttle {
+            if !throttle.allows_retry() {
+                return false;
+            }
+        }
+
+        match self.deadline {
+            Some(deadline) => Instant::now() + delay < deadline,
+            None => true,
+        }
+    }
+
+    /// Update the retry throttle with the outcome of an attempt.
+    ///
+    /// Only successes add tokens and only retryable failures remove some, so failures with
+    /// other codes leave the throttle unchanged.
+    pub(super) fn record(&self, outcome: &Outcome) {
+        if let Some(throttle) = &self.throttle {
+            match outcome {
+                Outcome::Committed { succeeded: true } => throttle.on_success(),
+                Outcome::Committed { succeeded: false } => {}
+                Outcome::Failed { .. } => throttle.on_failure(),
+            }
+        }
+    }
+}
+
+async fn retry<S>(
+    attempts: Attempts<S>,
+    policy: RetryPolicy,
+    first: S::Future,
+) -> Result<Response<ResponseBody>, crate::Error>
+where
+    S: Service<Request<BoxBody>, Response = Response<ResponseBody>, Error = crate::Error> + Clone,
+{
+    let mut result = first.await;
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 1;
+
+    loop {
+        let outcome = classify(&result, |code| policy.is_retryable(code));
+        attempts.record(&outcome);
+
+        let delay = match outcome {
+            Outcome::Committed { .. } => return result,
+            Outcome::Failed {
+                pushback: Pushback::None,
+            } => {
+                let delay = jitter(backoff);
+                backoff = next_backoff(&policy, backoff);
+                delay
+            }
+            Outcome::Failed {
+                pushback: Pushback::Delay(delay),
+            } => {
+                backoff = policy.initial_backoff;
+                delay
+            }
+            Outcome::Failed {
+                pushback: Pushback::Stop,
+            } => return result,
+        };
+
+        if attempt >= policy.max_attempts || !attempts.may_send(delay) {
+            return result;
+        }
+
+        debug!(attempt, ?delay, "retrying call");
+        drop(result);
+        tokio::time::sleep(delay).await;
+
+        result = attempts.send(attempt).await;
+        attempt += 1;
+    }
+}
+
+/// Decide whether the result of an attempt is handed to the caller.
+pub(super) fn classify(
+    result: &Result<Response<ResponseBody>, crate::Error>,
+    is_retryable: impl Fn(Code) -> bool,
+) -> Outcome {
+    let (code, pushback) = match result {
+        Ok(response) => match Status::from_header_map(response.headers()) {
+            // A trailers-only response, the call failed before the server sent anything.
+            Some(status) => (status.code(), parse_pushback(response.headers())),
+            // The server committed to a response, which is handed over to the caller.
+            None => return Outcome::Committed { succeeded: true },
+        },
+        Err(err) => (Status::code_from_error(&**err), Pushback::None),
+    };
+
+    if code == Code::Ok || !is_retryable(code) {
+        return Outcome::Committed {
+            succeeded: code == Code::Ok,
+        };
+    }
+
+    Outcome::Failed { pushback }
+}
+
+fn attempt_request(parts: &Parts, body: ReplayBody) -> Request<BoxBody> {
+    let mut request = Request::new(crate::body::boxed(body));
+    *request.method_mut() = parts.method.clone();
+    *request.uri_mut() = parts.uri.clone();
+    *request.version_mut() = parts.version;
+    *request.headers_mut() = parts.headers.clone();
+    request
+}
+
+pub(super) enum Outcome {
+    /// The result is handed to the caller, `succeeded` unless it is a failure that can't be
+    /// retried.
+    Committed { succeeded: bool },
+    /// The call failed in a way that can be retried.
+    Failed { pushback: Pushback },
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Pushback {
+    /// The server didn't ask for a specific delay.
+    None,
+    /// The server asked for the next attempt to start after this delay.
+    Delay(Duration),
+    /// The server asked not to retry.
+    Stop,
+}
+
+fn parse_pushback(headers: &http::HeaderMap) -> Pushback {
+    let value = match headers.get(PUSHBACK_HEADER) {
+        Some(value) => value,
+        None => return Pushback::None,
+    };
+
+    // Anything but a non-negative integer means the server doesn't want the call retried.
+    match value
+        .to_str()
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        Some(millis) => Pushback::Delay(Duration::from_millis(millis)),
+        None => Pushback::Stop,
+    }
+}
+
+fn jitter(backoff: Duration) -> Duration {
+    backoff.mul_f64(rand::thread_rng().gen_range(0.0..1.0))
+}
+
+fn next_backoff(policy: &RetryPolicy, backoff: Duration) -> Duration {
+    let next = backoff.as_secs_f64() * policy.backoff_multiplier;
+    if next.is_finite() && next < policy.max_backoff.as_secs_f64() {
+        Duration::from_secs_f64(next.max(0.0))
+    } else {
+        policy.max_backoff
+    }
+}
+
+/// Limits retries when too many calls fail, as described by the `retryThrottling` section of
+/// the [gRPC retry design].
+///
+/// Tokens are stored in thousandths to support fractional token ratios.
+///
+/// [gRPC retry design]: https://github.com/grpc/proposal/blob/master/A6-client-retries.md
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    max_tokens: u32,
+    token_ratio: u32,
+    tokens: AtomicU32,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(max_tokens: u32, token_ratio: f32) -> Self {
+        let max_tokens = max_tokens.clamp(1, 1000) * 1000;
+        let token_ratio = ((token_ratio * 1000.0) as u32).max(1);
+
+        Self {
+            max_tokens,
+            token_ratio,
+            tokens: AtomicU32::new(max_tokens),
+        }
+    }
+
+    pub(crate) fn on_failure(&self) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                Some(tokens.saturating_sub(1000))
+            });
+    }
+
+    pub(crate) fn on_success(&self) {
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                Some((tokens + self.token_ratio).min(self.max_tokens))
+            });
+    }
+
+    pub(crate) fn allows_retry(&self) -> bool {
+        self.tokens.load(Ordering::Acquire) > self.max_tokens / 2
+    }
+}
+
+/// Response future of [`Retry`].
+#[pin_project]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    kind: Kind<F>,
+}
+
+#[pin_project(project = KindProj)]
+enum Kind<F> {
+    Passthrough {
+        #[pin]
+        future: F,
+    },
+    Retrying {
+        future: BoxFuture<'static, Result<Response<ResponseBody>, crate::Error>>,
+    },
+}
+
+impl<F> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResponseBody>, crate::Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().kind.project() {
+            KindProj::Passthrough { future } => future.poll(cx),
+            KindProj::Retrying { future } => future.as_mut().poll(cx),
+        }
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use tower::service_fn;
+
+    fn trailers_only(code: Code, pushback: Option<&'static str>) -> Response<ResponseBody> {
+        let mut response = Response::new(ResponseBody::new(hyper::Body::empty()));
+        response
+            .headers_mut()
+            .insert("grpc-status", HeaderValue::from(code as i32));
+        if let Some(pushback) = pushback {
+            response
+                .headers_mut()
+                .insert(PUSHBACK_HEADER, HeaderValue::from_static(pushback));
+        }
+        response
+    }
+
+    /// A service that fails with `codes` in order, then succeeds, recording the request bodies.
+    fn service(
+        codes: Vec<(Code, Option<&'static str>)>,
+        calls: Arc<AtomicUsize>,
+    ) -> impl Service<
+        Request<BoxBody>,
+        Response = Response<ResponseBody>,
+        Error = crate::Error,
+        Future = impl Send,
+    > + Clone
+           + Send
+           + 'static {
+        service_fn(move |request: Request<BoxBody>| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            let outcome = codes.get(call).copied();
+            async move {
+                let body = hyper::body::to_bytes(request.into_body()).await?;
+                assert_eq!(body, "request");
+
+                Ok(match outcome {
+                    Some((code, pushback)) => trailers_only(code, pushback),
+                    None => Response::new(ResponseBody::new(hyper::Body::empty())),
+                })
+            }
+        })
+    }
+
+    fn request(path: &'static str) -> Request<BoxBody> {
+        let mut request = Request::new(crate::body::boxed(hyper::Body::from("request")));
+        *request.uri_mut() = http::Uri::from_static(path);
+        request
+    }
+
+    fn config(policy: RetryPolicy) -> RetryConfig {
+        let mut config = RetryConfig::default();
+        config.set_policy("test.Test".into(), policy);
+        config
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_retryable_codes() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(
+            vec![(Code::Unavailable, None), (Code::Unavailable, None)],
+            calls.clone(),
+        );
+        let svc = Retry::new(svc, config(RetryPolicy::new()));
+
+        let response = svc.oneshot(request("/test.Test/Unary")).await.unwrap();
+        assert!(Status::from_header_map(response.headers()).is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn every_attempt_gets_the_tonic_extensions() {
+        #[derive(Clone)]
+        struct User;
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let svc = service_fn({
+            let seen = seen.clone();
+            move |request: Request<BoxBody>| {
+                let extensions = request.extensions();
+                let mut seen = seen.lock().unwrap();
+                seen.push((
+                    extensions.get::<RequestHash>().copied(),
+                    extensions
+                        .get::<GrpcMethod>()
+                        .map(|method| method.method().to_owned()),
+                    extensions.get::<WaitForReady>().map(|wait| wait.0),
+                    extensions.get::<User>().is_some(),
+                ));
+                let response = match seen.len() {
+                    1 => trailers_only(Code::Unavailable, None),
+                    _ => Response::new(ResponseBody::new(hyper::Body::empty())),
+                };
+                async move { Ok::<_, crate::Error>(response) }
+            }
+        });
+        let svc = Retry::new(svc, config(RetryPolicy::new()));
+
+        let mut request = request("/test.Test/Unary");
+        request.extensions_mut().insert(RequestHash(7));
+        request
+            .extensions_mut()
+            .insert(GrpcMethod::new("test.Test", "Unary"));
+        request.extensions_mut().insert(WaitForReady(true));
+        request.extensions_mut().insert(User);
+        svc.oneshot(request).await.unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (
+                    Some(RequestHash(7)),
+                    Some("Unary".to_owned()),
+                    Some(true),
+                    true
+                ),
+                (
+                    Some(RequestHash(7)),
+                    Some("Unary".to_owned()),
+                    Some(true),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Code::Unavailable, None); 5], calls.clone());
+        let svc = Retry::new(svc, config(RetryPolicy::new().max_attempts(2)));
+
+        let response = svc.oneshot(request("/test.Test/Unary")).await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_retry_other_codes_or_methods() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Code::Internal, None)], calls.clone());
+        let svc = Retry::new(svc, config(RetryPolicy::new()));
+        svc.oneshot(request("/test.Test/Unary")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Code::Unavailable, None)], calls.clone());
+        let svc = Retry::new(svc, config(RetryPolicy::new()));
+        svc.oneshot(request("/other.Other/Unary")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn honours_pushback() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Code::Unavailable, Some("2000"))], calls.clone());
+        let svc = Retry::new(svc, config(RetryPolicy::new()));
+
+        let start = Instant::now();
+        svc.oneshot(request("/test.Test/Unary")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_millis(2000));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Code::Unavailable, Some("-1"))], calls.clone());
+        let svc = Retry::new(svc, config(RetryPolicy::new()));
+        svc.oneshot(request("/test.Test/Unary")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn backoff_grows_up_to_max() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(3));
+
+        let backoff = next_backoff(&policy, policy.initial_backoff);
+        assert_eq!(backoff, Duration::from_secs(2));
+        assert_eq!(next_backoff(&policy, backoff), Duration::from_secs(3));
+        assert!(jitter(backoff) < backoff);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_successes_refill_the_throttle() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let svc = service(vec![(Code::Internal, None); 3], calls.clone());
+        let mut config = config(RetryPolicy::new());
+        config.throttling = Some((10, 1.0));
+        let svc = Retry::new(svc, config);
+
+        let throttle = svc.throttle.clone().unwrap();
+        for _ in 0..5 {
+            throttle.on_failure();
+        }
+        assert!(!throttle.allows_retry());
+
+        // Failures with codes that aren't retryable don't add tokens.
+        for _ in 0..3 {
+            svc.clone()
+                .oneshot(request("/test.Test/Unary"))
+                .await
+                .unwrap();
+        }
+        assert!(!throttle.allows_retry());
+
+        svc.oneshot(request("/test.Test/Unary")).await.unwrap();
+        assert!(throttle.allows_retry());
+    }
+
+    #[test]
+    fn token_bucket_throttles_after_failures() {
+        let bucket = TokenBucket::new(10, 0.5);
+        assert!(bucket.allows_retry());
+
+        for _ in 0..5 {
+            bucket.on_failure();
+        }
+        assert!(!bucket.allows_retry());
+
+        for _ in 0..2 {
+            bucket.on_success();
+        }
+        assert!(bucket.allows_retry());
+    }
+}